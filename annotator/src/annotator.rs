@@ -0,0 +1,226 @@
+use engine::alpha_beta::{mate_moves, KING_CAPTURE_SCORE, MATE_SCORE};
+use engine::engine::Engine;
+use model::chess_type::{ScoreType, PAWN_SCORE};
+use model::game::ChessGame;
+use model::game_tree::{GameTree, NodeId, ROOT};
+use model::moves::Move;
+
+/// Numeric annotation glyphs of the bad moves
+const INACCURACY: u8 = 6;
+const MISTAKE: u8 = 2;
+const BLUNDER: u8 = 4;
+
+/// Losses of score, in centipawns, from which a move is marked as bad
+#[derive(Copy, Clone, Debug)]
+pub struct Thresholds {
+    pub inaccuracy: ScoreType,
+    pub mistake: ScoreType,
+    pub blunder: ScoreType,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            inaccuracy: 50,
+            mistake: 100,
+            blunder: 300,
+        }
+    }
+}
+
+impl Thresholds {
+    /// Returns the glyph of a move which loses this score, if it is bad enough to be marked
+    fn judge(&self, loss: ScoreType) -> Option<u8> {
+        if loss >= self.blunder {
+            Some(BLUNDER)
+        } else if loss >= self.mistake {
+            Some(MISTAKE)
+        } else if loss >= self.inaccuracy {
+            Some(INACCURACY)
+        } else {
+            None
+        }
+    }
+}
+
+/// Evaluation of a position by the engine, from white's point of view
+struct Evaluation {
+    score: ScoreType,
+    best_move: Option<Move>,
+    pv: Vec<Move>,
+}
+
+/// Writes a score of white in pawns, or the side which mates
+pub fn format_score(score: ScoreType) -> String {
+    if mate_moves(score).is_none() {
+        format!("{:+.2}", score as f64 / PAWN_SCORE as f64)
+    } else if score > 0 {
+        "White mates".to_string()
+    } else {
+        "Black mates".to_string()
+    }
+}
+
+/// Annotates the main line of games with the evaluations of an engine.
+///
+/// Every position of the main line is searched. The evaluation after each move is written in the
+/// comment of the move, and the moves which lose enough score compared to the best move of the
+/// engine are marked as inaccuracies (`?!`), mistakes (`?`) or blunders (`??`), with the line
+/// expected by the engine as a variation.
+pub struct Annotator {
+    engine: Box<dyn Engine>,
+    thresholds: Thresholds,
+}
+
+impl Annotator {
+    pub fn new(engine: Box<dyn Engine>, thresholds: Thresholds) -> Self {
+        Self { engine, thresholds }
+    }
+
+    pub fn annotate(&mut self, tree: &mut GameTree) {
+        let line = tree.main_line();
+        let positions: Vec<NodeId> = std::iter::once(ROOT).chain(line.iter().copied()).collect();
+        let evaluations: Vec<Evaluation> = positions
+            .iter()
+            .map(|&id| self.evaluate(*tree.node(id).game()))
+            .collect();
+
+        for (i, &id) in line.iter().enumerate() {
+            let (before, after) = (&evaluations[i], &evaluations[i + 1]);
+            let parent = positions[i];
+            let white = tree.node(parent).game().white_to_play();
+
+            let node = tree.node_mut(id);
+            let evaluation = format_score(after.score);
+            node.comment = Some(match node.comment.take() {
+                Some(comment) => format!("{comment} {evaluation}"),
+                None => evaluation,
+            });
+
+            if before.best_move == node.played() {
+                continue;
+            }
+            // The mates are all worth the same, so that a longer mate isn't a mistake
+            let clamp = |score: ScoreType| score.clamp(-KING_CAPTURE_SCORE, KING_CAPTURE_SCORE);
+            let loss = clamp(before.score) - clamp(after.score);
+            let loss = if white { loss } else { -loss };
+            let Some(nag) = self.thresholds.judge(loss) else {
+                continue;
+            };
+            if !node.nags.contains(&nag) {
+                node.nags.push(nag);
+            }
+            Self::add_variation(tree, parent, &before.pv, before.score);
+        }
+    }
+
+    fn evaluate(&mut self, game: ChessGame) -> Evaluation {
+        let white_to_play = game.white_to_play();
+        // The engine can't search the positions without legal moves
+        if game.legal_moves(white_to_play).is_empty() {
+            let score = match (game.is_in_check(white_to_play), white_to_play) {
+                (false, _) => 0,
+                (true, true) => -MATE_SCORE,
+                (true, false) => MATE_SCORE,
+            };
+            return Evaluation {
+                score,
+                best_move: None,
+                pv: vec![],
+            };
+        }
+        let result = self.engine.find_best_move(game);
+        Evaluation {
+            score: result.score,
+            best_move: result.best_move,
+            pv: result.pv,
+        }
+    }
+
+    /// Adds the line expected by the engine from a node, with its evaluation at the end. The line
+    /// stops at the first move which isn't legal, such as the capture of a king.
+    fn add_variation(tree: &mut GameTree, parent: NodeId, pv: &[Move], score: ScoreType) {
+        let mut node = parent;
+        for m in pv {
+            match tree.add_move(node, m) {
+                Some(child) => node = child,
+                None => break,
+            }
+        }
+        if node != parent {
+            tree.node_mut(node).comment = Some(format_score(score));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::annotator::{format_score, Annotator, Thresholds};
+    use engine::alpha_beta::AlphaBetaEngine;
+    use model::game_tree::GameTree;
+    use model::pgn::read_games;
+
+    fn annotate(pgn: &str, depth: usize) -> GameTree {
+        let mut tree = GameTree::from_pgn(&read_games(pgn)[0]).unwrap();
+        let engine = Box::new(AlphaBetaEngine::new(depth, 0));
+        Annotator::new(engine, Thresholds::default()).annotate(&mut tree);
+        tree
+    }
+
+    #[test]
+    fn test_judge() {
+        let thresholds = Thresholds::default();
+        assert_eq!(None, thresholds.judge(-20));
+        assert_eq!(None, thresholds.judge(49));
+        assert_eq!(Some(6), thresholds.judge(50));
+        assert_eq!(Some(2), thresholds.judge(150));
+        assert_eq!(Some(4), thresholds.judge(900));
+    }
+
+    #[test]
+    fn test_format_score() {
+        assert_eq!("+0.35", format_score(35));
+        assert_eq!("-1.00", format_score(-100));
+        assert_eq!("+0.00", format_score(0));
+        assert_eq!("White mates", format_score(19_990));
+        assert_eq!("Black mates", format_score(-10_000));
+    }
+
+    #[test]
+    fn test_blunder() {
+        let tree = annotate("1. e4 e5 2. Qh5 Nc6 3. Qxe5+ Nxe5 *", 2);
+        let line = tree.main_line();
+        let queen_sacrifice = line[4];
+        assert_eq!(vec![4], tree.node(queen_sacrifice).nags);
+
+        // The line of the engine is a variation of the blunder, with its evaluation at the end
+        let alternatives = tree.node(line[3]).children();
+        assert_eq!(2, alternatives.len());
+        let mut end = alternatives[1];
+        assert_ne!(Some("Qxe5+".to_string()), tree.san(end));
+        while let Some(&next) = tree.node(end).children().first() {
+            end = next;
+        }
+        assert!(tree.node(end).comment.is_some());
+
+        // Every move of the main line has an evaluation
+        for &id in &line {
+            assert!(tree.node(id).comment.is_some());
+        }
+        let pgn = tree.to_pgn().to_pgn();
+        assert!(pgn.contains("3. Qxe5+ $4"), "{pgn}");
+    }
+
+    #[test]
+    fn test_checkmate() {
+        let tree = annotate("1. f3 e5 2. g4 {Oops} Qh4# 0-1", 2);
+        let line = tree.main_line();
+        assert_eq!(Some("Black mates"), tree.node(line[3]).comment.as_deref());
+        assert!(tree
+            .node(line[2])
+            .comment
+            .as_deref()
+            .unwrap()
+            .starts_with("Oops "));
+    }
+}
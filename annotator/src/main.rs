@@ -0,0 +1,61 @@
+//! Annotates the games of a PGN file with the evaluations of the engine, and marks the
+//! inaccuracies, the mistakes and the blunders with the better lines of the engine.
+//!
+//! Usage: `pgn_annotator <pgn file> [options]`, with the options:
+//!
+//! - `--engine <kind>`: `alphabeta` or `iterative` (default: `iterative`)
+//! - `--depth <n>`, `--extra-depth <n>`: depth of the searches, and extra depth for the captures
+//!   (default: 6 and 0)
+//! - `--time <ms>`: time limit of each search, with the `iterative` engine. The deepening stops
+//!   after the first iteration which ends after the limit.
+//! - `--uci <command>`: external UCI engine to use instead, searching to `--depth` or for `--time`
+//! - `--inaccuracy <cp>`, `--mistake <cp>`, `--blunder <cp>`: losses of score, in centipawns, from
+//!   which the moves are marked (default: 50, 100 and 300)
+//! - `--output <file>`: file where the annotated games are written (default: standard output)
+
+use crate::annotator::{Annotator, Thresholds};
+use cli_options::{create_engine, option, parse_options};
+use model::game_tree::GameTree;
+use model::pgn::PgnReader;
+use std::fs::File;
+use std::io::{BufReader, Write};
+
+mod annotator;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(path) = args.first() else {
+        eprintln!("Usage: pgn_annotator <pgn file> [--engine <kind>] [--depth <n>] [--extra-depth <n>] [--time <ms>] [--inaccuracy <cp>] [--mistake <cp>] [--blunder <cp>] [--output <file>]");
+        std::process::exit(1);
+    };
+    let options = parse_options(&args[1..]);
+    let defaults = Thresholds::default();
+    let thresholds = Thresholds {
+        inaccuracy: option(&options, "inaccuracy", defaults.inaccuracy),
+        mistake: option(&options, "mistake", defaults.mistake),
+        blunder: option(&options, "blunder", defaults.blunder),
+    };
+    let mut annotator = Annotator::new(create_engine(&options), thresholds);
+
+    let file = File::open(path).expect("Can't read the PGN file");
+    let mut output: Box<dyn Write> = match options.get("output") {
+        Some(output) => Box::new(File::create(output).expect("Can't create the output file")),
+        None => Box::new(std::io::stdout()),
+    };
+
+    for (i, pgn) in PgnReader::new(BufReader::new(file)).enumerate() {
+        let pgn = pgn.expect("Can't read the PGN file");
+        let mut tree = match GameTree::from_pgn(&pgn) {
+            Ok(tree) => tree,
+            Err(e) => {
+                eprintln!("Skipped game {}: {e}", i + 1);
+                continue;
+            }
+        };
+        annotator.annotate(&mut tree);
+        eprintln!("Annotated game {}: {} moves", i + 1, tree.main_line().len());
+        output
+            .write_all(tree.to_pgn().to_pgn().as_bytes())
+            .expect("Can't write the annotated game");
+    }
+}
@@ -1,75 +1,181 @@
-use std::time::Instant;
-use engine::engine::Engine;
+//! Measures the speed of the engine on a set of positions, to track its performance between
+//! commits.
+//!
+//! Usage: `engine_benchmark [options]`, with the options:
+//!
+//! - `--positions <file>`: file with one FEN per line, optionally followed by `;` and the name of
+//!   the position (default: an opening, a middlegame and an endgame)
+//! - `--engine <kind>`: `alphabeta` or `iterative` (default: `iterative`)
+//! - `--depth <n>`, `--extra-depth <n>`: depth of the searches, and extra depth for the captures
+//!   (default: 6 and 0)
+//! - `--time <ms>`: time limit of each search, with the `iterative` engine
+//! - `--iterations <n>`: number of searches of each position, whose times are averaged (default: 1)
+//! - `--perft <depth>`: depth of the perft run from the standard position, 0 to skip it
+//!   (default: 5)
+//! - `--stats <true|false>`: collects and reports the statistics of the searches, such as the
+//!   hits of the transposition table and the cutoffs (default: `false`)
+//! - `--format <text|json>`: format of the report (default: `text`)
+
+use crate::report::{PerftReport, PositionReport, Report};
+use cli_options::{option, parse_options};
+use engine::alpha_beta::AlphaBetaEngine;
+use engine::engine::{Engine, SearchResult};
 use engine::iterative_deepening::IterativeDeepeningEngine;
+use model::chess_type::PAWN_SCORE;
 use model::game::ChessGame;
-use model::moves::Move;
+use std::time::{Duration, Instant};
+
+mod report;
+
+/// Positions searched when no file is given, with their names
+const DEFAULT_POSITIONS: [(&str, &str); 3] = [
+    // After e4, e5, Nf3, Nc6, d4
+    (
+        "opening",
+        "r1bqkbnr/pppp1ppp/2n5/4p3/3PP3/5N2/PPP2PPP/RNBQKB1R b KQkq d3 0 3",
+    ),
+    (
+        "middlegame",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    ),
+    ("endgame", "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1"),
+];
+
+/// Reads the positions to search, as (name, FEN)
+fn read_positions(path: Option<&String>) -> Vec<(String, String)> {
+    let Some(path) = path else {
+        return DEFAULT_POSITIONS
+            .iter()
+            .map(|(name, fen)| (name.to_string(), fen.to_string()))
+            .collect();
+    };
+    let text = std::fs::read_to_string(path).expect("Can't read the positions file");
+    let positions: Vec<(String, String)> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .enumerate()
+        .map(|(i, line)| match line.split_once(';') {
+            Some((fen, name)) => (name.trim().to_string(), fen.trim().to_string()),
+            None => (format!("#{}", i + 1), line.to_string()),
+        })
+        .collect();
+    assert!(!positions.is_empty(), "The positions file is empty");
+    positions
+}
+
+/// Settings of the searches
+struct SearchSettings {
+    kind: String,
+    depth: usize,
+    extra_depth: usize,
+    time_limit: Option<Duration>,
+    collect_stats: bool,
+}
+
+impl SearchSettings {
+    /// Searches the position with a new engine, and returns the result with the depth reached
+    fn search(&self, game: ChessGame) -> (SearchResult, usize) {
+        match self.kind.as_str() {
+            "alphabeta" => {
+                let mut engine = AlphaBetaEngine::new(self.depth, self.extra_depth);
+                engine.set_collect_stats(self.collect_stats);
+                (engine.find_best_move(game), self.depth)
+            }
+            _ => {
+                let mut engine = IterativeDeepeningEngine::new(self.depth, self.extra_depth);
+                engine.set_time_limit(self.time_limit);
+                engine.set_collect_stats(self.collect_stats);
+                let result = engine.find_best_move(game);
+                (result, engine.reached_depth())
+            }
+        }
+    }
+}
 
-/// Finds the best move at the given position, `folds` times and prints the average time spent on this position
-fn benchmark(game: ChessGame, folds: usize, is_white: bool, depth: usize) {
-    let mut times: Vec<f64> = Vec::new();
+/// Searches the position `iterations` times, and reports the average time of the searches
+fn benchmark(
+    settings: &SearchSettings,
+    name: String,
+    fen: String,
+    iterations: usize,
+) -> PositionReport {
+    let game = ChessGame::from_fen(&fen);
 
-    for _i in 0..folds {
-        // let mut engine = AlphaBetaEngine::new();
-        // engine.set_engine_depth(7, 2);
-        let mut copied_game = game.clone();
-        let engine = IterativeDeepeningEngine::new(depth, 0);
-        let mut solver: Box<dyn Engine> = Box::new(engine);
+    let mut time = Duration::ZERO;
+    let mut last = None;
+    for _ in 0..iterations {
         let start = Instant::now();
-        let result = solver.find_best_move(copied_game, false);
-        let best_move = result.best_move.unwrap();
-        let _success =
-            copied_game.apply_move_safe(Move::new(best_move.from, best_move.to, is_white));
-        let end = start.elapsed().as_millis() as f64;
-        times.push(end);
+        let result = settings.search(game);
+        time += start.elapsed();
+        last = Some(result);
     }
+    let (result, depth) = last.expect("At least one iteration is needed");
 
-    println!("-------------------");
-    println!("BENCHMARKING RESULT");
-    println!("-------------------");
-    println!("Number of iterations: {folds}");
-    println!(
-        "Mean time           : {} [ms]",
-        times.iter().sum::<f64>() / times.len() as f64
-    );
+    PositionReport {
+        name,
+        fen,
+        best_move: result.best_move.map(|m| game.to_san(&m)),
+        score: result.score * 100 / PAWN_SCORE,
+        depth,
+        nodes: result.nodes,
+        time: time / iterations as u32,
+        stats: result.stats,
+    }
 }
 
-fn main() {
-    // 1. Run the engine in an opening with all pieces
-    // Resulting position after e4, e5, Kf3, Kc6, d4
-    let game = ChessGame::new(
-        4398451320767,
-        67272588556035840,
-        2594073385365405732,
-        4611690416475996162,
-        9295429630892703873,
-        576460752303423496,
-        1152921504606846992,
-        0,
-    );
-    benchmark(game, 10, false, 6);
-    /*
-     */
-
-    // 2. Run the engine in an end-game
-    /*
-    let mut game = ChessGame::empty();
+/// Counts the leaves of the tree of legal moves at the given depth
+fn benchmark_perft(game: ChessGame, depth: usize) -> PerftReport {
+    let start = Instant::now();
+    let nodes = game.perft(depth);
+    PerftReport {
+        depth,
+        nodes,
+        time: start.elapsed(),
+    }
+}
 
-    game.set_piece(King, true, "d1");
-    game.set_piece(King, false, "d8");
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = parse_options(&args);
+    let settings = SearchSettings {
+        kind: options
+            .get("engine")
+            .cloned()
+            .unwrap_or("iterative".to_string()),
+        depth: option(&options, "depth", 6),
+        extra_depth: option(&options, "extra-depth", 0),
+        time_limit: options
+            .contains_key("time")
+            .then(|| Duration::from_millis(option(&options, "time", 0))),
+        collect_stats: option(&options, "stats", false),
+    };
+    match (settings.kind.as_str(), settings.time_limit) {
+        ("iterative", _) | ("alphabeta", None) => {}
+        (kind, _) => {
+            eprintln!("Invalid engine: {kind}. The time limit needs the iterative engine.");
+            std::process::exit(1);
+        }
+    }
+    let iterations = option(&options, "iterations", 1usize).max(1);
+    let perft_depth = option(&options, "perft", 5usize);
+    let format = options.get("format").map_or("text", String::as_str);
 
-    game.set_piece(Pawn, true, "c2");
-    game.set_piece(Pawn, true, "d2");
-    game.set_piece(Pawn, true, "e2");
-    game.set_piece(Rook, true, "a1");
-    game.set_piece(Knight, true, "b1");
-    game.set_piece(Knight, true, "c1");
+    let positions = read_positions(options.get("positions"))
+        .into_iter()
+        .map(|(name, fen)| benchmark(&settings, name, fen, iterations))
+        .collect();
+    let report = Report {
+        engine: settings.kind.clone(),
+        depth: settings.depth,
+        extra_depth: settings.extra_depth,
+        time_limit: settings.time_limit,
+        positions,
+        perft: (perft_depth > 0).then(|| benchmark_perft(ChessGame::standard_game(), perft_depth)),
+    };
 
-    game.set_piece(Pawn, false, "c7");
-    game.set_piece(Pawn, false, "d7");
-    game.set_piece(Pawn, false, "e7");
-    game.set_piece(Rook, false, "f8");
-    game.set_piece(Bishop, false, "a8");
-    game.set_piece(Bishop, false, "b8");
-    benchmark(game, 10, false, 8);
-     */
+    match format {
+        "json" => println!("{}", report.to_json()),
+        _ => print!("{}", report.to_text()),
+    }
 }
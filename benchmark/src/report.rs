@@ -0,0 +1,304 @@
+use engine::stats::SearchStats;
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Result of the search of one position
+pub struct PositionReport {
+    pub name: String,
+    pub fen: String,
+    /// Best move in standard algebraic notation
+    pub best_move: Option<String>,
+    /// Score in centipawns, from white's point of view
+    pub score: i64,
+    /// Depth of the last iteration of the search
+    pub depth: usize,
+    pub nodes: u64,
+    pub time: Duration,
+    /// Statistics of the search, when they are collected
+    pub stats: Option<SearchStats>,
+}
+
+impl PositionReport {
+    pub fn nodes_per_second(&self) -> f64 {
+        nodes_per_second(self.nodes, self.time)
+    }
+
+    /// Effective branching factor: the number of children of each node of a uniform tree with
+    /// the same number of nodes and the same depth.
+    pub fn branching_factor(&self) -> f64 {
+        if self.depth == 0 {
+            return 0.0;
+        }
+        (self.nodes as f64).powf(1.0 / self.depth as f64)
+    }
+}
+
+/// Result of a perft run
+pub struct PerftReport {
+    pub depth: usize,
+    pub nodes: u64,
+    pub time: Duration,
+}
+
+/// Results of a complete benchmark
+pub struct Report {
+    pub engine: String,
+    pub depth: usize,
+    pub extra_depth: usize,
+    pub time_limit: Option<Duration>,
+    pub positions: Vec<PositionReport>,
+    pub perft: Option<PerftReport>,
+}
+
+fn nodes_per_second(nodes: u64, time: Duration) -> f64 {
+    if time.is_zero() {
+        return 0.0;
+    }
+    nodes as f64 / time.as_secs_f64()
+}
+
+fn milliseconds(time: Duration) -> f64 {
+    time.as_secs_f64() * 1000.0
+}
+
+fn json_array(values: &[u64]) -> String {
+    let values: Vec<String> = values.iter().map(u64::to_string).collect();
+    format!("[{}]", values.join(", "))
+}
+
+fn stats_to_json(stats: &SearchStats) -> String {
+    format!(
+        "{{\"nodes_per_depth\": {}, \"quiescence_nodes\": {}, \"tt_probes\": {}, \"tt_hits\": {}, \
+         \"tt_hit_rate\": {:.4}, \"cutoffs\": {}, \"cutoffs_per_move_index\": {}, \
+         \"first_move_cutoff_rate\": {:.4}}}",
+        json_array(&stats.nodes_per_depth),
+        stats.quiescence_nodes,
+        stats.tt_probes,
+        stats.tt_hits,
+        stats.tt_hit_rate(),
+        stats.cutoffs,
+        json_array(&stats.cutoffs_per_move_index),
+        stats.first_move_cutoff_rate()
+    )
+}
+
+/// Writes a string as a JSON string literal
+fn json_string(text: &str) -> String {
+    let mut json = String::from('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if c.is_control() => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+impl Report {
+    pub fn total_nodes(&self) -> u64 {
+        self.positions.iter().map(|p| p.nodes).sum()
+    }
+
+    pub fn total_time(&self) -> Duration {
+        self.positions.iter().map(|p| p.time).sum()
+    }
+
+    /// Writes the report as a table
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let limit = match self.time_limit {
+            Some(limit) => format!(", {} ms per position", limit.as_millis()),
+            None => String::new(),
+        };
+        let _ = writeln!(
+            text,
+            "Engine: {}, depth {}, extra depth {}{limit}",
+            self.engine, self.depth, self.extra_depth
+        );
+        let _ = writeln!(
+            text,
+            "{:<12} {:<8} {:>7} {:>5} {:>12} {:>10} {:>10} {:>6}",
+            "Position", "Move", "Score", "Depth", "Nodes", "Time [ms]", "NPS", "EBF"
+        );
+        for p in &self.positions {
+            let _ = writeln!(
+                text,
+                "{:<12} {:<8} {:>7} {:>5} {:>12} {:>10.1} {:>10.0} {:>6.2}",
+                p.name,
+                p.best_move.as_deref().unwrap_or("none"),
+                p.score,
+                p.depth,
+                p.nodes,
+                milliseconds(p.time),
+                p.nodes_per_second(),
+                p.branching_factor()
+            );
+        }
+        let _ = writeln!(
+            text,
+            "{:<12} {:<8} {:>7} {:>5} {:>12} {:>10.1} {:>10.0}",
+            "Total",
+            "",
+            "",
+            "",
+            self.total_nodes(),
+            milliseconds(self.total_time()),
+            nodes_per_second(self.total_nodes(), self.total_time())
+        );
+        for p in &self.positions {
+            let Some(stats) = &p.stats else { continue };
+            let _ = writeln!(text, "\nStatistics of {}:", p.name);
+            let _ = writeln!(text, "  Nodes per depth    : {:?}", stats.nodes_per_depth);
+            let _ = writeln!(text, "  Quiescence nodes   : {}", stats.quiescence_nodes);
+            let _ = writeln!(
+                text,
+                "  TT hits            : {} / {} ({:.1} %)",
+                stats.tt_hits,
+                stats.tt_probes,
+                100.0 * stats.tt_hit_rate()
+            );
+            let _ = writeln!(
+                text,
+                "  Cutoffs            : {} ({:.1} % on the first move)",
+                stats.cutoffs,
+                100.0 * stats.first_move_cutoff_rate()
+            );
+            let _ = writeln!(
+                text,
+                "  Cutoffs per move   : {:?}",
+                stats.cutoffs_per_move_index
+            );
+        }
+        if let Some(perft) = &self.perft {
+            let _ = writeln!(
+                text,
+                "Perft {}: {} nodes in {:.1} ms, {:.0} nodes per second",
+                perft.depth,
+                perft.nodes,
+                milliseconds(perft.time),
+                nodes_per_second(perft.nodes, perft.time)
+            );
+        }
+        text
+    }
+
+    /// Writes the report as a JSON object, to compare the results of different commits
+    pub fn to_json(&self) -> String {
+        let positions: Vec<String> = self
+            .positions
+            .iter()
+            .map(|p| {
+                format!(
+                    "{{\"name\": {}, \"fen\": {}, \"best_move\": {}, \"score\": {}, \"depth\": {}, \
+                     \"nodes\": {}, \"time_ms\": {:.3}, \"nps\": {:.0}, \"branching_factor\": {:.3}, \
+                     \"stats\": {}}}",
+                    json_string(&p.name),
+                    json_string(&p.fen),
+                    p.best_move.as_deref().map_or("null".to_string(), json_string),
+                    p.score,
+                    p.depth,
+                    p.nodes,
+                    milliseconds(p.time),
+                    p.nodes_per_second(),
+                    p.branching_factor(),
+                    p.stats.as_ref().map_or("null".to_string(), stats_to_json)
+                )
+            })
+            .collect();
+        let perft = match &self.perft {
+            Some(perft) => format!(
+                "{{\"depth\": {}, \"nodes\": {}, \"time_ms\": {:.3}, \"nps\": {:.0}}}",
+                perft.depth,
+                perft.nodes,
+                milliseconds(perft.time),
+                nodes_per_second(perft.nodes, perft.time)
+            ),
+            None => "null".to_string(),
+        };
+        format!(
+            "{{\n  \"engine\": {},\n  \"depth\": {},\n  \"extra_depth\": {},\n  \"time_limit_ms\": {},\n  \
+             \"positions\": [\n    {}\n  ],\n  \"total\": {{\"nodes\": {}, \"time_ms\": {:.3}, \"nps\": {:.0}}},\n  \
+             \"perft\": {}\n}}",
+            json_string(&self.engine),
+            self.depth,
+            self.extra_depth,
+            self.time_limit
+                .map_or("null".to_string(), |t| t.as_millis().to_string()),
+            positions.join(",\n    "),
+            self.total_nodes(),
+            milliseconds(self.total_time()),
+            nodes_per_second(self.total_nodes(), self.total_time()),
+            perft
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::report::{json_string, stats_to_json, PositionReport, Report};
+    use engine::stats::SearchStats;
+    use std::time::Duration;
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!("\"e4\"", json_string("e4"));
+        assert_eq!("\"a \\\"b\\\" \\\\ \\n\"", json_string("a \"b\" \\ \n"));
+        assert_eq!("\"\\u0001\"", json_string("\u{1}"));
+    }
+
+    #[test]
+    fn test_report() {
+        let report = Report {
+            engine: "iterative".to_string(),
+            depth: 2,
+            extra_depth: 0,
+            time_limit: None,
+            positions: vec![PositionReport {
+                name: "start".to_string(),
+                fen: "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string(),
+                best_move: Some("e4".to_string()),
+                score: 30,
+                depth: 2,
+                nodes: 400,
+                time: Duration::from_millis(2),
+                stats: None,
+            }],
+            perft: None,
+        };
+        assert_eq!(20.0, report.positions[0].branching_factor());
+        assert_eq!(200_000.0, report.positions[0].nodes_per_second());
+
+        let json = report.to_json();
+        assert!(json.contains("\"best_move\": \"e4\""));
+        assert!(json.contains("\"branching_factor\": 20.000"));
+        assert!(json.contains("\"time_limit_ms\": null"));
+        assert!(json.contains("\"total\": {\"nodes\": 400, \"time_ms\": 2.000, \"nps\": 200000}"));
+        assert!(json.contains("\"perft\": null"));
+        assert!(json.contains("\"stats\": null"));
+        assert!(report.to_text().contains("start"));
+    }
+
+    #[test]
+    fn test_stats_to_json() {
+        let stats = SearchStats {
+            nodes_per_depth: vec![1, 20, 400],
+            quiescence_nodes: 0,
+            tt_probes: 400,
+            tt_hits: 100,
+            cutoffs: 10,
+            cutoffs_per_move_index: vec![9, 1],
+        };
+        assert_eq!(
+            "{\"nodes_per_depth\": [1, 20, 400], \"quiescence_nodes\": 0, \"tt_probes\": 400, \
+             \"tt_hits\": 100, \"tt_hit_rate\": 0.2500, \"cutoffs\": 10, \
+             \"cutoffs_per_move_index\": [9, 1], \"first_move_cutoff_rate\": 0.9000}",
+            stats_to_json(&stats)
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use engine::book::{encode_move, polyglot_key, BookEntry};
+use model::pgn::PgnGame;
+use std::collections::HashMap;
+
+/// Settings of the construction of a book
+#[derive(Copy, Clone, Debug)]
+pub struct BookSettings {
+    /// Only the moves played before this ply are added to the book
+    pub max_ply: usize,
+    /// Moves played in fewer games are left out of the book
+    pub min_games: u32,
+    /// Weight of a move is the sum of these weights over its games, from the point of view of
+    /// the player of the move
+    pub win_weight: u32,
+    pub draw_weight: u32,
+    pub loss_weight: u32,
+}
+
+/// Results of the games in which a move was played, from the point of view of its player
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+struct MoveStats {
+    wins: u32,
+    draws: u32,
+    losses: u32,
+}
+
+impl MoveStats {
+    fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+}
+
+/// Aggregates the moves of many games by position, to write them as a Polyglot book
+pub struct BookBuilder {
+    settings: BookSettings,
+    moves: HashMap<(u64, u16), MoveStats>,
+}
+
+impl BookBuilder {
+    pub fn new(settings: BookSettings) -> Self {
+        Self {
+            settings,
+            moves: HashMap::new(),
+        }
+    }
+
+    /// Replays a game and adds its first moves to the book. Games without a result, or with an
+    /// illegal move among the moves added to the book, are rejected.
+    pub fn add_game(&mut self, pgn: &PgnGame) -> Result<(), String> {
+        let white_score = pgn.white_score().ok_or("The game has no result")?;
+        let mut game = pgn.initial_position()?;
+
+        let mut played = vec![];
+        for san in pgn.main_line().into_iter().take(self.settings.max_ply) {
+            let white_to_play = game.white_to_play();
+            let m = game
+                .from_san(san, white_to_play)
+                .ok_or(format!("Illegal move: {san}"))?;
+            played.push((polyglot_key(&game), encode_move(&game, &m), white_to_play));
+            game.apply_move_unsafe(&m);
+        }
+
+        for (key, raw_move, white) in played {
+            let stats = self.moves.entry((key, raw_move)).or_default();
+            let score = if white {
+                white_score
+            } else {
+                1.0 - white_score
+            };
+            if score == 1.0 {
+                stats.wins += 1;
+            } else if score == 0.5 {
+                stats.draws += 1;
+            } else {
+                stats.losses += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of distinct positions in the book
+    pub fn positions(&self) -> usize {
+        let mut keys: Vec<u64> = self.moves.keys().map(|(key, _)| *key).collect();
+        keys.sort_unstable();
+        keys.dedup();
+        keys.len()
+    }
+
+    /// Returns the entries of the book, sorted by key and then from the most to the least played
+    /// move. The weights are scaled down when they don't fit in 16 bits.
+    pub fn entries(&self) -> Vec<BookEntry> {
+        let BookSettings {
+            min_games,
+            win_weight,
+            draw_weight,
+            loss_weight,
+            ..
+        } = self.settings;
+        let weighted: Vec<(u64, u16, u64)> = self
+            .moves
+            .iter()
+            .filter(|(_, stats)| stats.games() >= min_games)
+            .map(|(&(key, raw_move), stats)| {
+                let weight = stats.wins as u64 * win_weight as u64
+                    + stats.draws as u64 * draw_weight as u64
+                    + stats.losses as u64 * loss_weight as u64;
+                (key, raw_move, weight)
+            })
+            .filter(|(_, _, weight)| *weight > 0)
+            .collect();
+
+        let max_weight = weighted.iter().map(|(_, _, w)| *w).max().unwrap_or(0);
+        let scale = |weight: u64| {
+            if max_weight <= u16::MAX as u64 {
+                weight as u16
+            } else {
+                (weight * u16::MAX as u64 / max_weight).max(1) as u16
+            }
+        };
+
+        let mut entries: Vec<BookEntry> = weighted
+            .into_iter()
+            .map(|(key, raw_move, weight)| BookEntry {
+                key,
+                raw_move,
+                weight: scale(weight),
+                learn: 0,
+            })
+            .collect();
+        entries.sort_by(|a, b| {
+            a.key
+                .cmp(&b.key)
+                .then(b.weight.cmp(&a.weight))
+                .then(a.raw_move.cmp(&b.raw_move))
+        });
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::{BookBuilder, BookSettings};
+    use engine::book::{write_book, OpeningBook};
+    use model::game::ChessGame;
+    use model::moves::Move;
+    use model::pgn::PgnReader;
+
+    const SETTINGS: BookSettings = BookSettings {
+        max_ply: 4,
+        min_games: 1,
+        win_weight: 2,
+        draw_weight: 1,
+        loss_weight: 0,
+    };
+
+    fn build(settings: BookSettings) -> (BookBuilder, Vec<Result<(), String>>) {
+        let mut builder = BookBuilder::new(settings);
+        let results = PgnReader::new(&include_bytes!("../fixtures/games.pgn")[..])
+            .map(|game| builder.add_game(&game.unwrap()))
+            .collect();
+        (builder, results)
+    }
+
+    #[test]
+    fn test_build_book() {
+        let (builder, results) = build(SETTINGS);
+        assert_eq!(
+            vec![
+                Ok(()),
+                Ok(()),
+                Ok(()),
+                Ok(()),
+                Err("The game has no result".to_string()),
+                Err("Illegal move: Ke3".to_string()),
+            ],
+            results
+        );
+
+        let mut bytes = vec![];
+        write_book(&builder.entries(), &mut bytes).unwrap();
+        let book = OpeningBook::from_bytes(&bytes).unwrap();
+
+        // 1. e4 won twice (weight 4) and 1. d4 drew once (weight 1) and lost once
+        let start = ChessGame::standard_game();
+        assert_eq!(
+            vec![
+                (Move::from_str("e2", "e4", true), 4),
+                (Move::from_str("d2", "d4", true), 1)
+            ],
+            book.moves(&start)
+        );
+
+        // Black's answers are weighted from black's point of view: 1... e5 lost twice
+        let mut game = start;
+        game.apply_move_unsafe(&Move::from_str("e2", "e4", true));
+        assert!(book.moves(&game).is_empty());
+
+        // The moves after the maximum ply are not in the book
+        let mut game = start;
+        for (from, to, white) in [
+            ("e2", "e4", true),
+            ("e7", "e5", false),
+            ("g1", "f3", true),
+            ("b8", "c6", false),
+        ] {
+            game.apply_move_unsafe(&Move::from_str(from, to, white));
+        }
+        assert!(book.moves(&game).is_empty());
+    }
+
+    #[test]
+    fn test_min_games() {
+        let (builder, _) = build(BookSettings {
+            min_games: 2,
+            loss_weight: 1,
+            ..SETTINGS
+        });
+        let entries = builder.entries();
+        // 1. e4, 1. d4, 1... e5 and 2. Nf3 were played twice
+        assert_eq!(4, entries.len());
+        assert!(builder.positions() > 4);
+    }
+}
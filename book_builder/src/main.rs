@@ -0,0 +1,64 @@
+//! Builds an opening book in the Polyglot format from the games of a PGN file.
+//!
+//! Usage: `book_builder <PGN file> [options]`, with the options:
+//!
+//! - `--output <file>`: the book to write (default: `book.bin`)
+//! - `--max-ply <n>`: only the moves played before this ply are added to the book (default: 20)
+//! - `--min-games <n>`: moves played in fewer games are left out of the book (default: 1)
+//! - `--win-weight <n>`, `--draw-weight <n>`, `--loss-weight <n>`: weights of the results of the
+//!   games, from the point of view of the player of the move (default: 2, 1 and 0). Moves without
+//!   any weight are left out of the book.
+
+use crate::builder::{BookBuilder, BookSettings};
+use cli_options::{option, parse_options};
+use engine::book::write_book;
+use model::pgn::PgnReader;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+
+mod builder;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(path) = args.first() else {
+        eprintln!(
+            "Usage: book_builder <PGN file> [--output <file>] [--max-ply <n>] [--min-games <n>]"
+        );
+        std::process::exit(1);
+    };
+    let options = parse_options(&args[1..]);
+    let output = options
+        .get("output")
+        .cloned()
+        .unwrap_or("book.bin".to_string());
+    let settings = BookSettings {
+        max_ply: option(&options, "max-ply", 20),
+        min_games: option(&options, "min-games", 1),
+        win_weight: option(&options, "win-weight", 2),
+        draw_weight: option(&options, "draw-weight", 1),
+        loss_weight: option(&options, "loss-weight", 0),
+    };
+
+    let file = File::open(path).expect("Can't open the PGN file");
+    let mut builder = BookBuilder::new(settings);
+    let mut games = 0;
+    let mut skipped = 0;
+    for game in PgnReader::new(BufReader::new(file)) {
+        let game = game.expect("Can't read the PGN file");
+        games += 1;
+        if let Err(e) = builder.add_game(&game) {
+            skipped += 1;
+            let event = game.tag("Event").unwrap_or("?");
+            eprintln!("Skipped game {games} ({event}): {e}");
+        }
+    }
+
+    let entries = builder.entries();
+    let mut writer = BufWriter::new(File::create(&output).expect("Can't create the book"));
+    write_book(&entries, &mut writer).expect("Can't write the book");
+
+    println!("Games     : {games} ({skipped} skipped)");
+    println!("Positions : {}", builder.positions());
+    println!("Entries   : {}", entries.len());
+    println!("Book written to {output}");
+}
@@ -0,0 +1,96 @@
+//! Options of the command lines of the binaries of the workspace, written as `--name value`.
+//!
+//! The binaries are run by hand, so an invalid option stops them with a message rather than
+//! returning an error.
+
+use engine::alpha_beta::AlphaBetaEngine;
+use engine::engine::Engine;
+use engine::iterative_deepening::IterativeDeepeningEngine;
+use std::collections::HashMap;
+use std::time::Duration;
+use uci_client::{SearchLimit, UciEngineClient};
+
+/// Reads the options of the command line, written as `--name value`
+pub fn parse_options(args: &[String]) -> HashMap<String, String> {
+    let mut options = HashMap::new();
+    for pair in args.chunks(2) {
+        match (pair[0].strip_prefix("--"), pair.get(1)) {
+            (Some(name), Some(value)) => {
+                options.insert(name.to_string(), value.clone());
+            }
+            _ => {
+                eprintln!("Invalid option: {}", pair[0]);
+                std::process::exit(1);
+            }
+        }
+    }
+    options
+}
+
+/// Returns the value of an option, or its default value
+pub fn option<T: std::str::FromStr>(
+    options: &HashMap<String, String>,
+    name: &str,
+    default: T,
+) -> T {
+    match options.get(name) {
+        Some(value) => value.parse().unwrap_or_else(|_| {
+            eprintln!("Invalid value for --{name}: {value}");
+            std::process::exit(1);
+        }),
+        None => default,
+    }
+}
+
+/// Creates the engine given by the options `--engine` (`alphabeta` or `iterative`), `--depth`,
+/// `--extra-depth` and `--time`, the time limit in milliseconds of the `iterative` engine.
+///
+/// With `--uci <command>`, the engine is the external UCI engine started by the command, which
+/// searches for the time given with `--time`, or else to the depth given with `--depth`.
+pub fn create_engine(options: &HashMap<String, String>) -> Box<dyn Engine> {
+    let depth = option(options, "depth", 6);
+    let extra_depth = option(options, "extra-depth", 0);
+    let time_limit = options
+        .contains_key("time")
+        .then(|| Duration::from_millis(option(options, "time", 0)));
+
+    if let Some(command) = options.get("uci") {
+        let mut client = UciEngineClient::spawn(command.as_str(), &[]).unwrap_or_else(|e| {
+            eprintln!("Can't start the engine {command}: {e}");
+            std::process::exit(1);
+        });
+        client.set_limit(match time_limit {
+            Some(time) => SearchLimit::MoveTime(time),
+            None => SearchLimit::Depth(depth),
+        });
+        return Box::new(client);
+    }
+
+    match options.get("engine").map_or("iterative", String::as_str) {
+        "alphabeta" if time_limit.is_none() => Box::new(AlphaBetaEngine::new(depth, extra_depth)),
+        "iterative" => {
+            let mut engine = IterativeDeepeningEngine::new(depth, extra_depth);
+            engine.set_time_limit(time_limit);
+            Box::new(engine)
+        }
+        kind => {
+            eprintln!("Invalid engine: {kind}. The time limit needs the iterative engine.");
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{option, parse_options};
+
+    #[test]
+    fn test_options() {
+        let args = ["--depth", "8", "--output", "book.bin"].map(String::from);
+        let options = parse_options(&args);
+        assert_eq!(2, options.len());
+        assert_eq!(8, option(&options, "depth", 6));
+        assert_eq!(0.5, option(&options, "time", 0.5));
+        assert_eq!(Some("book.bin"), options.get("output").map(String::as_str));
+    }
+}
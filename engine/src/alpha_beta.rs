@@ -1,24 +1,87 @@
 use std::cmp::{max, min};
 use std::collections::HashMap;
 use model::chess_type::ScoreType;
+use model::chess_type::Type::King;
+use model::compact_move::CompactMove;
 use model::game::ChessGame;
 use model::moves::Move;
+use model::moves::MoveQuality::Principal;
 use model::moves_container::{MovesContainer, SmartMoveContainer};
+#[cfg(not(feature = "nnue"))]
+use model::pawn_hash_table::PawnHashTable;
+use crate::endgame;
 use crate::engine::{Engine, SearchResult};
+use crate::stats::SearchStats;
+use crate::tablebase::{piece_count, Tablebase, Wdl};
+use std::sync::Arc;
+#[cfg(feature = "nnue")]
+use crate::nnue::{Accumulator, Network};
+
+/// Maximum number of plies that the search can reach, including the extra depth.
+const MAX_PLY: usize = 64;
+
+/// Score of the capture of a king at the root, which is how the mates are found. The captures
+/// found deeper get lower scores.
+pub const MATE_SCORE: ScoreType = 20_000;
+
+/// Scores beyond which a king was captured in the search
+pub const KING_CAPTURE_SCORE: ScoreType = 10_000;
+
+/// Returns the number of moves of the player who mates, when the score of a search announces a
+/// mate. The king is captured one ply after the mate, so a mate in `n` moves of the side to move
+/// is scored at `2 n + 1` plies, and at `2 n + 2` plies when the other side mates.
+pub fn mate_moves(score: ScoreType) -> Option<usize> {
+    let plies = MATE_SCORE - score.abs();
+    (score.abs() >= KING_CAPTURE_SCORE).then_some((plies as usize).saturating_sub(1) / 2)
+}
+
+/// What the transposition table knows about a position
+#[derive(Copy, Clone, Default)]
+struct TableEntry {
+    /// Evaluation of the position, once it was a leaf of a search
+    evaluation: Option<ScoreType>,
+    /// Best move found when the position was searched, with the number of the search which found
+    /// it
+    best_move: Option<(u64, CompactMove)>,
+}
 
 pub struct AlphaBetaEngine {
     depth: usize,
     extra_depth: usize,
-    transposition_table: HashMap<ChessGame, ScoreType>,
+    transposition_table: HashMap<ChessGame, TableEntry>,
+    /// Cache of the evaluation of the pawn structures
+    #[cfg(not(feature = "nnue"))]
+    pawn_table: PawnHashTable,
     // killer_moves: HashMap<usize, Vec<Move>>,
+    /// Triangular table of principal variations: `pv_table[ply]` contains the best line found
+    /// from `ply`, stored between the indices `ply` and `pv_length[ply]`.
+    pv_table: [[Option<CompactMove>; MAX_PLY]; MAX_PLY],
+    pv_length: [usize; MAX_PLY],
+    /// Moves which are not allowed to be played at the root of the search (used for MultiPV)
+    excluded_root_moves: Vec<Move>,
+    /// Number of nodes visited since the beginning of the search
+    nodes: u64,
+    /// Number of the current search. Only the best moves stored by this search are tried first,
+    /// so that a search gives the same result and statistics whatever the previous searches.
+    search: u64,
+    /// Statistics of the current search, when they are collected
+    stats: Option<SearchStats>,
+    /// Endgame tablebases, probed in the positions with few pieces
+    tablebase: Option<Arc<dyn Tablebase>>,
+    /// Network which evaluates the positions
+    #[cfg(feature = "nnue")]
+    network: Arc<Network>,
+    /// Accumulators of the network for each ply of the current line
+    #[cfg(feature = "nnue")]
+    accumulators: Vec<Accumulator>,
 }
 
 impl Engine for AlphaBetaEngine {
-    fn find_best_move(&mut self, game: ChessGame, white_to_play: bool) -> SearchResult {
+    fn find_best_move(&mut self, mut game: ChessGame) -> SearchResult {
         self.reset_killer_moves();
+        self.reset_counters();
         let result = self.alpha_beta_search(
-            game,
-            white_to_play,
+            &mut game,
             0,
             i32::MIN as ScoreType,
             i32::MAX as ScoreType,
@@ -27,6 +90,12 @@ impl Engine for AlphaBetaEngine {
         );
         result
     }
+
+    fn find_best_moves(&mut self, game: ChessGame, count: usize) -> Vec<SearchResult> {
+        self.reset_killer_moves();
+        self.reset_counters();
+        self.multi_pv_search(game, count, None)
+    }
 }
 
 impl AlphaBetaEngine {
@@ -35,10 +104,30 @@ impl AlphaBetaEngine {
             depth,
             extra_depth,
             transposition_table: Default::default(),
+            #[cfg(not(feature = "nnue"))]
+            pawn_table: Default::default(),
             // killer_moves: Default::default(),
+            pv_table: [[None; MAX_PLY]; MAX_PLY],
+            pv_length: [0; MAX_PLY],
+            excluded_root_moves: vec![],
+            nodes: 0,
+            search: 0,
+            stats: None,
+            tablebase: None,
+            #[cfg(feature = "nnue")]
+            network: Network::bundled(),
+            #[cfg(feature = "nnue")]
+            accumulators: vec![],
         }
     }
 
+    /// Replaces the network which evaluates the positions
+    #[cfg(feature = "nnue")]
+    pub fn set_network(&mut self, network: Arc<Network>) {
+        self.network = network;
+        self.transposition_table.clear();
+    }
+
     #[allow(dead_code)]
     pub fn set_engine_depth(&mut self, depth: usize, extra: usize) {
         self.depth = depth;
@@ -46,6 +135,28 @@ impl AlphaBetaEngine {
         self.reset_killer_moves()
     }
 
+    /// Enables or disables the collection of the statistics of the searches, returned in
+    /// `SearchResult::stats`
+    pub fn set_collect_stats(&mut self, collect_stats: bool) {
+        self.stats = collect_stats.then(SearchStats::default);
+    }
+
+    /// Sets the endgame tablebases, which give the score of the positions which have at most
+    /// `Tablebase::max_pieces` pieces and the move to play when the root is one of them
+    pub fn set_tablebase(&mut self, tablebase: Option<Arc<dyn Tablebase>>) {
+        self.tablebase = tablebase;
+        self.transposition_table.clear();
+    }
+
+    /// Resets the number of nodes and the statistics at the beginning of a search
+    pub(crate) fn reset_counters(&mut self) {
+        self.nodes = 0;
+        self.search += 1;
+        if let Some(stats) = &mut self.stats {
+            *stats = SearchStats::default();
+        }
+    }
+
     fn reset_killer_moves(&mut self) {
         // self.killer_moves.clear();
         // for i in 0..self.depth + self.extra_depth {
@@ -53,7 +164,44 @@ impl AlphaBetaEngine {
         // }
     }
 
-    /// Returns the best move found using alpha-beta pruning with
+    /// Searches the `count` best lines of the position.
+    ///
+    /// Each line is obtained with a complete search of the root, in which the moves of the lines
+    /// already found are excluded. The results are therefore sorted from the best to the worst.
+    pub fn multi_pv_search(
+        &mut self,
+        mut game: ChessGame,
+        count: usize,
+        first_move_to_evaluate: Option<Move>,
+    ) -> Vec<SearchResult> {
+        let mut results = Vec::with_capacity(count);
+        self.excluded_root_moves.clear();
+
+        while results.len() < count {
+            let result = self.alpha_beta_search(
+                &mut game,
+                0,
+                i32::MIN as ScoreType,
+                i32::MAX as ScoreType,
+                false,
+                first_move_to_evaluate.filter(|m| !self.excluded_root_moves.contains(m)),
+            );
+
+            match result.best_move {
+                Some(m) => {
+                    self.excluded_root_moves.push(m);
+                    results.push(result);
+                }
+                // No more moves to analyse in this position
+                None => break,
+            }
+        }
+
+        self.excluded_root_moves.clear();
+        results
+    }
+
+    /// Returns the best move of the side to move found using alpha-beta pruning with
     /// * smart move ordering
     /// * extra depth for captures move only
     ///
@@ -72,28 +220,78 @@ impl AlphaBetaEngine {
     /// Algorithm taken from
     /// https://en.wikipedia.org/wiki/Alpha%E2%80%93beta_pruning#Pseudocode
     /// (fail-soft variation)
+    ///
+    /// The moves are played on `game` with `make_move` and taken back with `unmake_move`, instead
+    /// of copying the game at each node, so `game` is the same at the end of the search.
     pub fn alpha_beta_search(
         &mut self,
-        game: ChessGame,
-        white_to_play: bool,
+        game: &mut ChessGame,
         depth: usize,
         mut alpha: ScoreType,
         mut beta: ScoreType,
         is_last_move_a_capture: bool,
         first_move_to_evaluate: Option<Move>,
     ) -> SearchResult {
+        let white_to_play = game.white_to_play();
+        self.pv_length[depth] = depth;
+        self.nodes += 1;
+        if let Some(stats) = &mut self.stats {
+            stats.add_node(depth, depth > self.depth);
+        }
+
+        // The accumulators of the other plies are updated incrementally from this one
+        #[cfg(feature = "nnue")]
+        if depth == 0 {
+            self.accumulators = vec![Accumulator::new(&self.network, game); MAX_PLY];
+        }
+
+        // The positions with few pieces are solved by the tablebases
+        if depth == 0 && self.excluded_root_moves.is_empty() {
+            if let Some((m, wdl)) = self.probe_tablebase_root(game) {
+                let score = wdl.score(0);
+                return SearchResult {
+                    score: if white_to_play { score } else { -score },
+                    best_move: Some(m),
+                    pv: vec![m],
+                    nodes: self.nodes,
+                    stats: self.stats.clone(),
+                };
+            }
+        } else if depth > 0 {
+            if let Some(wdl) = self.probe_tablebase(game) {
+                let score = wdl.score(depth);
+                return SearchResult {
+                    score: if white_to_play { score } else { -score },
+                    best_move: None,
+                    pv: vec![],
+                    nodes: 0,
+                    stats: None,
+                };
+            }
+        }
+
         // Terminal node
         if (!is_last_move_a_capture && depth >= self.depth)
             || (is_last_move_a_capture && depth >= self.depth + self.extra_depth)
             || game.is_finished()
+            || depth + 1 >= MAX_PLY
         {
-            let s = *self
-                .transposition_table
-                .entry(game)
-                .or_insert_with(|| game.score());
+            let s = if game.is_finished() {
+                // The faster mates are preferred
+                let score = MATE_SCORE - depth as ScoreType;
+                if game.pieces(King, true) != 0 { score } else { -score }
+            } else {
+                match endgame::evaluate(game) {
+                    Some(s) => s,
+                    None => self.cached_evaluation(*game, depth),
+                }
+            };
             return SearchResult {
                 score: s,
                 best_move: None,
+                pv: vec![],
+                nodes: if depth == 0 { self.nodes } else { 0 },
+                stats: if depth == 0 { self.stats.clone() } else { None },
             };
         }
 
@@ -101,9 +299,17 @@ impl AlphaBetaEngine {
         let mut container = SmartMoveContainer::new();
         game.update_move_container(&mut container, white_to_play);
 
-        // Optionally set the first move
-        // (used for iterative deepening)
-        if let Some(first_move) = first_move_to_evaluate {
+        // Optionally set the first move (used for iterative deepening), or else the best move
+        // found when the position was reached earlier in the search
+        let first_move = first_move_to_evaluate.or_else(|| {
+            match self.transposition_table.get(game)?.best_move? {
+                (search, best_move) if search == self.search => {
+                    Some(best_move.to_move(white_to_play))
+                }
+                _ => None,
+            }
+        });
+        if let Some(first_move) = first_move {
             container.set_first_move(first_move);
         }
 
@@ -122,22 +328,39 @@ impl AlphaBetaEngine {
         
         // TODO is there a way to not keep track of the best move at runtime ?
         let mut best_move = None;
+        // Index of the current move in the moves searched in this node
+        let mut move_index = 0;
 
         while container.has_next() {
             // Use the move container to get the next best move, as per move ordering logic
-            let mut new_game = game.clone();
             let m = container.pop_next_move();
-            new_game.apply_move_unsafe(&m);
 
-            let result = self.alpha_beta_search(
-                new_game,
-                !white_to_play,
-                depth + 1,
-                alpha,
-                beta,
-                m.is_capture(),
-                None,
-            );
+            // When analysing several lines, the moves of the lines already found are skipped
+            if depth == 0 && self.excluded_root_moves.contains(&m) {
+                continue;
+            }
+            // The first move is also generated with the other moves, and is only searched once
+            if m.quality != Principal && first_move == Some(m) {
+                continue;
+            }
+
+            #[cfg(feature = "nnue")]
+            {
+                let mut accumulator = self.accumulators[depth];
+                accumulator.update(&self.network, game, &m);
+                self.accumulators[depth + 1] = accumulator;
+            }
+
+            // The first move loses its quality, so the captures are found from the game, whose
+            // destination square is empty for the en passant captures
+            let compact_move = CompactMove::new(game, &m);
+            let is_capture = game.type_at_index(m.to).is_some() || compact_move.is_en_passant();
+
+            // The move is played on the game of this node, and taken back after its search
+            let undo = game.make_move(&m);
+            let result = self.alpha_beta_search(game, depth + 1, alpha, beta, is_capture, None);
+            game.unmake_move(undo);
+            move_index += 1;
 
             if white_to_play {
                 // value := max(value, alphabeta(child, depth − 1, α, β, FALSE))
@@ -148,6 +371,7 @@ impl AlphaBetaEngine {
                 if result.score > score {
                     best_move = Some(m);
                     score = result.score;
+                    self.update_pv(depth, compact_move);
                 }
                 alpha = max(alpha, score);
                 if score >= beta {
@@ -156,6 +380,7 @@ impl AlphaBetaEngine {
                     //     .get_mut(&depth)
                     //     .expect("The datastructure is always initialized to support this usage")
                     //     .push(m);
+                    self.count_cutoff(move_index - 1);
                     break;
                 }
             } else {
@@ -167,17 +392,121 @@ impl AlphaBetaEngine {
                 if result.score < score {
                     best_move = Some(m);
                     score = result.score;
+                    self.update_pv(depth, compact_move);
                 }
                 beta = min(beta, score);
                 if score <= alpha {
+                    self.count_cutoff(move_index - 1);
                     break;
                 }
             }
         }
 
+        // A side which loses its king after all its moves without being in check is stalemated
+        let king_lost = if white_to_play {
+            score <= -KING_CAPTURE_SCORE
+        } else {
+            score >= KING_CAPTURE_SCORE
+        };
+        if king_lost
+            && !game.is_in_check(white_to_play)
+            && game.legal_moves(white_to_play).is_empty()
+        {
+            score = 0;
+        }
+
         // Once we reach this point, we have explored all the possible moves of this branch
         // ==> we know which is the best move
-        SearchResult { score, best_move }
+        if let Some(m) = best_move {
+            let entry = self.transposition_table.entry(*game).or_default();
+            entry.best_move = Some((self.search, CompactMove::new(game, &m)));
+        }
+        let (pv, nodes, stats) = if depth == 0 {
+            // The sides alternate along the line
+            let pv = self.pv_table[0][..self.pv_length[0]]
+                .iter()
+                .flatten()
+                .enumerate()
+                .map(|(ply, m)| m.to_move(white_to_play == (ply % 2 == 0)))
+                .collect();
+            (pv, self.nodes, self.stats.clone())
+        } else {
+            (vec![], 0, None)
+        };
+        SearchResult {
+            score,
+            best_move,
+            pv,
+            nodes,
+            stats,
+        }
+    }
+
+    /// Returns the tablebase, if the position can be found in it
+    fn tablebase_for(&self, game: &ChessGame) -> Option<&dyn Tablebase> {
+        let tablebase = self.tablebase.as_deref()?;
+        // The positions in which the king of the side which just played is attacked are illegal
+        let probed = !game.is_finished()
+            && piece_count(game) <= tablebase.max_pieces()
+            && !game.is_in_check(!game.white_to_play());
+        probed.then_some(tablebase)
+    }
+
+    fn probe_tablebase(&self, game: &ChessGame) -> Option<Wdl> {
+        self.tablebase_for(game)?.probe_wdl(game)
+    }
+
+    fn probe_tablebase_root(&self, game: &ChessGame) -> Option<(Move, Wdl)> {
+        self.tablebase_for(game)?.best_move(game)
+    }
+
+    fn count_cutoff(&mut self, move_index: usize) {
+        if let Some(stats) = &mut self.stats {
+            stats.add_cutoff(move_index);
+        }
+    }
+
+    /// Evaluates a leaf of the search, or reads its score from the transposition table
+    fn cached_evaluation(&mut self, game: ChessGame, depth: usize) -> ScoreType {
+        let cached = self
+            .transposition_table
+            .get(&game)
+            .and_then(|entry| entry.evaluation);
+        if let Some(stats) = &mut self.stats {
+            stats.add_tt_probe(cached.is_some());
+        }
+        match cached {
+            Some(s) => s,
+            None => {
+                let s = self.evaluate(&game, depth);
+                self.transposition_table.entry(game).or_default().evaluation = Some(s);
+                s
+            }
+        }
+    }
+
+    /// Evaluates a leaf of the search, with the classical evaluation of the model, reduced in the
+    /// endgames which it can't tell from wins
+    #[cfg(not(feature = "nnue"))]
+    fn evaluate(&mut self, game: &ChessGame, _depth: usize) -> ScoreType {
+        endgame::scale(game, game.score_with_pawn_table(&mut self.pawn_table))
+    }
+
+    /// Evaluates a leaf of the search, with the network. Its scores are not scaled like the
+    /// classical ones: the network learned the drawish endgames from the results of its games.
+    #[cfg(feature = "nnue")]
+    fn evaluate(&mut self, _game: &ChessGame, depth: usize) -> ScoreType {
+        self.accumulators[depth].evaluate(&self.network)
+    }
+
+    /// Stores `m` as the best move at `depth`, followed by the principal variation of the child.
+    fn update_pv(&mut self, depth: usize, m: CompactMove) {
+        self.pv_table[depth][depth] = Some(m);
+        let child_length = self.pv_length[depth + 1];
+        for ply in depth + 1..child_length {
+            self.pv_table[depth][ply] = self.pv_table[depth + 1][ply];
+        }
+        self.pv_length[depth] = child_length.max(depth + 1);
     }
 }
 
@@ -188,7 +517,7 @@ mod tests {
     use model::game::ChessGame;
     use model::moves::Move;
     use model::utils::{chesspos_to_index, index_to_chesspos};
-    use crate::alpha_beta::AlphaBetaEngine;
+    use crate::alpha_beta::{mate_moves, AlphaBetaEngine};
     use crate::engine::Engine;
 
     #[test]
@@ -208,7 +537,7 @@ mod tests {
         let mut engine = AlphaBetaEngine::new(6, 0);
 
         // If it is white to play, white captures the pawn
-        let result = engine.find_best_move(game.clone(), true);
+        let result = engine.find_best_move(game);
         assert_eq!(
             result.best_move.unwrap().from,
             chesspos_to_index("e4").unwrap()
@@ -219,7 +548,8 @@ mod tests {
         );
 
         // Same if it is for black
-        let result = engine.find_best_move(game.clone(), false);
+        game.set_white_to_play(false);
+        let result = engine.find_best_move(game);
         assert_eq!(
             result.best_move.unwrap().from,
             chesspos_to_index("d5").unwrap()
@@ -260,7 +590,7 @@ mod tests {
         let mut engine = Box::new(AlphaBetaEngine::new(6, 0));
 
         // If it is white to play, it should capture the bishop
-        let result = engine.find_best_move(game.clone(), true);
+        let result = engine.find_best_move(game);
         println!(
             "{} {}",
             index_to_chesspos(result.best_move.unwrap().from),
@@ -276,7 +606,8 @@ mod tests {
         );
 
         // If black is playing, it should capture the pawn
-        let result = engine.find_best_move(game.clone(), false);
+        game.set_white_to_play(false);
+        let result = engine.find_best_move(game);
         println!(
             "{} {}",
             index_to_chesspos(result.best_move.unwrap().from),
@@ -331,7 +662,7 @@ mod tests {
         ];
 
         // If it is white to play, it should move the pawn up and not capture anything
-        let result = engine.find_best_move(game.clone(), true);
+        let result = engine.find_best_move(game);
         println!(
             "{} {}",
             index_to_chesspos(result.best_move.unwrap().from),
@@ -340,7 +671,8 @@ mod tests {
         assert!(valid_white_moves.contains(&result.best_move.unwrap()));
 
         // If black is playing, it should capture the pawn with a piece
-        let result = engine.find_best_move(game.clone(), false);
+        game.set_white_to_play(false);
+        let result = engine.find_best_move(game);
         println!(
             "{} {}",
             index_to_chesspos(result.best_move.unwrap().from),
@@ -374,7 +706,7 @@ mod tests {
         let mut engine = AlphaBetaEngine::new(6, 0);
 
         // If it is white to play, it should move the pawn up and not capture anything
-        let result = engine.find_best_move(game.clone(), true);
+        let result = engine.find_best_move(game);
         println!(
             "{} {}",
             index_to_chesspos(result.best_move.unwrap().from),
@@ -395,7 +727,7 @@ mod tests {
     /// We want to make sure that black sees this treat.
     fn test_simple_engine5() {
         // This position is the one where black is not supposed to play a5->a4
-        let pos1 = ChessGame::new(
+        let mut pos1 = ChessGame::new(
             402973695,
             71494648782447360,
             2594073385365405732,
@@ -410,7 +742,8 @@ mod tests {
         engine.set_engine_depth(4, 4);
 
         // What is the best move for black ?
-        let _ = engine.find_best_move(pos1.clone(), false);
+        pos1.set_white_to_play(false);
+        let _ = engine.find_best_move(pos1);
 
         // Now we wonder, what is the best move for white, given there is one less depth ?
         let mut pos2 = pos1.clone();
@@ -420,7 +753,7 @@ mod tests {
             false,
         ));
         engine.set_engine_depth(3, 4);
-        let _ = engine.find_best_move(pos2.clone(), true);
+        let _ = engine.find_best_move(pos2);
 
         // let's understand why is the move that attacks a4 is not seen as strong
         let mut pos3 = pos2.clone();
@@ -430,7 +763,60 @@ mod tests {
             true,
         ));
         engine.set_engine_depth(2, 4);
-        let _ = engine.find_best_move(pos3.clone(), false);
+        let _ = engine.find_best_move(pos3);
+    }
+
+    #[test]
+    /// The number of moves of the mates is read from the scores
+    fn test_mate_moves() {
+        let mut engine = AlphaBetaEngine::new(5, 0);
+        let game = ChessGame::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let score = engine.find_best_move(game).score;
+        assert!(score > 0);
+        assert_eq!(Some(1), mate_moves(score));
+
+        // Black is mated after its only move
+        let game = ChessGame::from_fen("k7/8/1K6/8/8/8/8/7R b - - 0 1");
+        let score = engine.find_best_move(game).score;
+        assert!(score > 0);
+        assert_eq!(Some(1), mate_moves(score));
+
+        let game = ChessGame::from_fen("2r1r1k1/5ppp/8/8/Q7/8/5PPP/4R1K1 w - - 0 1");
+        assert_eq!(Some(2), mate_moves(engine.find_best_move(game).score));
+        let game = ChessGame::standard_game();
+        assert_eq!(None, mate_moves(engine.find_best_move(game).score));
+    }
+
+    #[test]
+    /// The MultiPV search returns the two captures, sorted from the best to the worst
+    fn test_multi_pv() {
+        let mut game = ChessGame::empty();
+        game.set_piece(King, true, "a2");
+        game.set_piece(King, false, "a7");
+        game.set_piece(Pawn, true, "e4");
+        game.set_piece(Pawn, false, "d5");
+        game.set_piece(Knight, false, "f5");
+
+        let mut engine = AlphaBetaEngine::new(3, 0);
+        let results = engine.find_best_moves(game, 3);
+        assert_eq!(3, results.len());
+
+        let best = results[0].best_move.unwrap();
+        assert_eq!(Move::from_str("e4", "f5", true), best);
+        let second = results[1].best_move.unwrap();
+        assert_eq!(Move::from_str("e4", "d5", true), second);
+
+        for window in results.windows(2) {
+            assert!(window[0].score >= window[1].score);
+        }
+        for result in &results {
+            assert_eq!(result.best_move, result.pv.first().copied());
+            assert_eq!(3, result.pv.len());
+            // The moves of the sides alternate, starting with white
+            for (ply, m) in result.pv.iter().enumerate() {
+                assert_eq!(ply % 2 == 0, m.is_white);
+            }
+        }
     }
 
     #[test]
@@ -450,9 +836,76 @@ mod tests {
         engine.set_engine_depth(1, 0);
 
         // Asserts that the black captures the knight
-        let result = engine.find_best_move(game, true);
+        let result = engine.find_best_move(game);
         let best_move = result.best_move.unwrap();
         assert_eq!(chesspos_to_index("e4").unwrap(), best_move.from);
         assert_eq!(chesspos_to_index("f5").unwrap(), best_move.to);
     }
+
+    #[test]
+    fn test_en_passant_extension() {
+        // The only capture of white is en passant, whose replies are searched as for the other
+        // captures
+        let mut game = ChessGame::from_fen("4r1k1/3p4/8/4P3/8/8/8/4Q1K1 b - - 0 1");
+        game.apply_move_unsafe(&Move::from_str("d7", "d5", false));
+        let mut engine = AlphaBetaEngine::new(1, 1);
+        engine.set_collect_stats(true);
+        let stats = engine.find_best_move(game).stats.unwrap();
+        assert!(stats.quiescence_nodes > 0);
+    }
+
+    #[test]
+    fn test_search_stats() {
+        let game = ChessGame::from_fen(
+            "r1bqkbnr/pppp1ppp/2n5/4p3/3PP3/5N2/PPP2PPP/RNBQKB1R b KQkq - 0 3",
+        );
+        assert!(AlphaBetaEngine::new(2, 0)
+            .find_best_move(game)
+            .stats
+            .is_none());
+
+        let mut engine = AlphaBetaEngine::new(4, 2);
+        engine.set_collect_stats(true);
+        let result = engine.find_best_move(game);
+        let stats = result.stats.expect("The statistics are collected");
+        assert_eq!(result.nodes, stats.nodes());
+        assert_eq!(1, stats.nodes_per_depth[0]);
+        assert_eq!(7, stats.nodes_per_depth.len());
+        assert!(stats.quiescence_nodes > 0);
+        assert!(stats.tt_hits > 0 && stats.tt_hits < stats.tt_probes);
+        assert_eq!(stats.cutoffs, stats.cutoffs_per_move_index.iter().sum::<u64>());
+        assert!(stats.first_move_cutoff_rate() > 0.5);
+
+        // The statistics are reset for each search
+        let second = engine.find_best_move(game).stats.unwrap();
+        assert_eq!(stats.nodes(), second.nodes());
+    }
+
+    #[test]
+    fn test_tablebase() {
+        use crate::tablebase::syzygy::tests::{test_directory, write_winning_kqk};
+        use crate::tablebase::syzygy::SyzygyTablebase;
+        use crate::tablebase::TABLEBASE_WIN_SCORE;
+        use std::sync::Arc;
+
+        let directory = test_directory("alpha_beta");
+        write_winning_kqk(&directory);
+        let tablebase = SyzygyTablebase::open(directory.to_str().unwrap()).unwrap();
+        let mut engine = AlphaBetaEngine::new(2, 0);
+        engine.set_tablebase(Some(Arc::new(tablebase)));
+
+        // Taking the knight leads to a won position of the tables
+        let game = ChessGame::from_fen("8/8/8/3k4/8/8/1n6/KQ6 w - - 0 1");
+        let result = engine.find_best_move(game);
+        assert_eq!(TABLEBASE_WIN_SCORE - 1, result.score);
+        assert_eq!("b2", index_to_chesspos(result.best_move.unwrap().to));
+
+        // At the root, the move is given by the tables
+        let game = ChessGame::from_fen("8/8/8/8/8/2k5/8/K2Q4 w - - 0 1");
+        let result = engine.find_best_move(game);
+        assert_eq!(TABLEBASE_WIN_SCORE, result.score);
+        assert_eq!(1, result.pv.len());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
 }
@@ -0,0 +1,63 @@
+//! Generates the distance-to-mate tables of the `tablebase::dtm` module.
+//!
+//! The tables of the given materials are generated with the tables that they depend on, and all
+//! of them are written in the output directory. Without materials, all the endgames with 3 and 4
+//! pieces are generated.
+//!
+//! Usage: `generate_tablebases <output directory> [materials, such as KQvK or KRPvKR...]`
+
+use engine::tablebase::dtm::generator::generate;
+use engine::tablebase::dtm::{Material, EXTENSION, MAX_PIECES};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Instant;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let Some(directory) = args.next().map(PathBuf::from) else {
+        eprintln!("Usage: generate_tablebases <output directory> [materials...]");
+        std::process::exit(1);
+    };
+    let materials: Vec<Material> = args
+        .map(|name| match Material::parse(&name) {
+            Some(material) if material.piece_count() <= MAX_PIECES => material,
+            _ => {
+                eprintln!(
+                    "Invalid material {name}, expected at most {MAX_PIECES} pieces as in KRvKN"
+                );
+                std::process::exit(1);
+            }
+        })
+        .collect();
+    let materials = if materials.is_empty() {
+        Material::all(MAX_PIECES)
+    } else {
+        materials
+    };
+
+    std::fs::create_dir_all(&directory).expect("Can't create the output directory");
+    let start = Instant::now();
+    let mut tables = HashMap::new();
+    for material in &materials {
+        generate(material, &mut tables);
+        println!(
+            "{} tables generated after {:.1}s",
+            tables.len(),
+            start.elapsed().as_secs_f32()
+        );
+    }
+
+    let mut tables: Vec<_> = tables.values().collect();
+    tables.sort_by_key(|table| (table.material().piece_count(), table.material().name()));
+    for table in tables {
+        let name = table.material().name();
+        let path = directory.join(format!("{name}.{EXTENSION}"));
+        table.write(&path).expect("Can't write the table");
+        println!(
+            "{name}: {} positions, longest mate in {} plies, {} bytes",
+            table.material().size(),
+            table.max_plies(),
+            std::fs::metadata(&path).map_or(0, |metadata| metadata.len())
+        );
+    }
+}
@@ -0,0 +1,341 @@
+//! Trains the network of the `nnue` module on positions from self-play games.
+//!
+//! The engine plays against itself with a shallow search, and with some random moves to visit
+//! varied positions. Each position is labelled with a mix of the classical evaluation of the
+//! position and of the result of the game, both converted into a winning probability. The network
+//! is then trained with floating point numbers and quantized into a weights file.
+//!
+//! Usage: `train_nnue [games] [epochs] [output file]`
+
+use engine::alpha_beta::AlphaBetaEngine;
+use engine::engine::Engine;
+use engine::nnue::{feature_indices, Network, HIDDEN, INPUTS};
+use model::chess_type::Type::{Bishop, King, Knight, Pawn, Queen, Rook};
+use model::game::ChessGame;
+use model::moves::Move;
+use model::moves_container::SimpleMovesContainer;
+use std::fs::File;
+use std::io::BufWriter;
+use std::time::Instant;
+
+const DEFAULT_GAMES: usize = 400;
+const DEFAULT_EPOCHS: usize = 40;
+const DEFAULT_OUTPUT: &str = "engine/nets/default.nnue";
+
+/// Games are stopped (and considered as draws) after this number of plies
+const MAX_PLIES: usize = 200;
+/// The first moves of each game are random, to start from different positions
+const RANDOM_PLIES: usize = 8;
+/// Probability of playing a random move after the opening
+const RANDOM_MOVE_PROBABILITY: f32 = 0.1;
+/// Weight of the evaluation in the target, the rest being the result of the game
+const EVALUATION_WEIGHT: f32 = 0.75;
+/// Scale used to convert centipawns into winning probabilities
+const SIGMOID_SCALE: f32 = 400.0;
+
+const BATCH_SIZE: usize = 256;
+const LEARNING_RATE: f32 = 0.002;
+
+/// xorshift64* pseudo-random generator, with a fixed seed so that the training is reproducible
+struct Random(u64);
+
+impl Random {
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+
+    /// Random number between 0 and 1
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// A position of the training set: the active inputs of each point of view, and the target
+/// winning probability of white.
+struct Sample {
+    white: Vec<usize>,
+    black: Vec<usize>,
+    target: f32,
+}
+
+impl Sample {
+    fn new(game: &ChessGame, target: f32) -> Self {
+        let mut white = vec![];
+        let mut black = vec![];
+        for (piece_index, piece) in [Pawn, Knight, Bishop, Rook, Queen, King]
+            .into_iter()
+            .enumerate()
+        {
+            for is_white in [true, false] {
+                let mut bitboard = game.pieces(piece, is_white);
+                while bitboard != 0 {
+                    let sq = bitboard.trailing_zeros() as usize;
+                    let (w, b) = feature_indices(piece_index, is_white, sq);
+                    white.push(w);
+                    black.push(b);
+                    bitboard &= bitboard - 1;
+                }
+            }
+        }
+        Self {
+            white,
+            black,
+            target,
+        }
+    }
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+/// Returns the moves which don't leave the king of the player in check
+fn legal_moves(game: &ChessGame, white: bool) -> Vec<Move> {
+    let mut container = SimpleMovesContainer::new();
+    game.update_move_container(&mut container, white);
+    container
+        .moves
+        .into_iter()
+        .filter(|m| {
+            let mut new_game = *game;
+            new_game.apply_move_unsafe(m);
+            !new_game.is_in_check(white)
+        })
+        .collect()
+}
+
+/// Plays a game of the engine against itself, and returns the visited positions with their
+/// classical evaluation, and the result of the game (1 if white won, 0 if black won).
+fn play_game(random: &mut Random) -> (Vec<(ChessGame, f32)>, f32) {
+    let mut engine = AlphaBetaEngine::new(2, 2);
+    let mut game = ChessGame::standard_game();
+    let mut positions = vec![];
+
+    for ply in 0..MAX_PLIES {
+        let white = game.white_to_play();
+        let moves = legal_moves(&game, white);
+        if moves.is_empty() {
+            // Checkmate, or stalemate
+            let result = match (game.is_in_check(white), white) {
+                (false, _) => 0.5,
+                (true, true) => 0.0,
+                (true, false) => 1.0,
+            };
+            return (positions, result);
+        }
+
+        let random_move = moves[random.below(moves.len())];
+        let m = if ply < RANDOM_PLIES || random.next_f32() < RANDOM_MOVE_PROBABILITY {
+            random_move
+        } else {
+            engine
+                .find_best_move(game)
+                .best_move
+                .filter(|m| moves.contains(m))
+                .unwrap_or(random_move)
+        };
+
+        game.apply_move_unsafe(&m);
+        positions.push((game, game.score() as f32));
+    }
+
+    (positions, 0.5)
+}
+
+/// Network with floating point weights, trained with Adam
+struct Trainer {
+    /// All the parameters: feature weights, feature biases, then output weights
+    parameters: Vec<f32>,
+    gradients: Vec<f32>,
+    first_moments: Vec<f32>,
+    second_moments: Vec<f32>,
+    steps: i32,
+}
+
+const FEATURE_BIASES: usize = INPUTS * HIDDEN;
+const OUTPUT_WEIGHTS: usize = FEATURE_BIASES + HIDDEN;
+const PARAMETERS: usize = OUTPUT_WEIGHTS + HIDDEN;
+
+impl Trainer {
+    fn new(random: &mut Random) -> Self {
+        let parameters = (0..PARAMETERS)
+            .map(|i| {
+                let scale = if i < FEATURE_BIASES { 0.1 } else { 0.5 };
+                (random.next_f32() - 0.5) * scale
+            })
+            .collect();
+        Self {
+            parameters,
+            gradients: vec![0.0; PARAMETERS],
+            first_moments: vec![0.0; PARAMETERS],
+            second_moments: vec![0.0; PARAMETERS],
+            steps: 0,
+        }
+    }
+
+    fn accumulate(&self, features: &[usize]) -> [f32; HIDDEN] {
+        let mut accumulator: [f32; HIDDEN] =
+            std::array::from_fn(|i| self.parameters[FEATURE_BIASES + i]);
+        for feature in features {
+            let weights = &self.parameters[feature * HIDDEN..(feature + 1) * HIDDEN];
+            for (value, weight) in accumulator.iter_mut().zip(weights) {
+                *value += weight;
+            }
+        }
+        accumulator
+    }
+
+    /// Returns the predicted winning probability of white, and the hidden layers
+    fn forward(&self, sample: &Sample) -> (f32, [f32; HIDDEN], [f32; HIDDEN]) {
+        let white = self.accumulate(&sample.white);
+        let black = self.accumulate(&sample.black);
+        let mut output = 0.0;
+        for i in 0..HIDDEN {
+            output += self.parameters[OUTPUT_WEIGHTS + i]
+                * (white[i].clamp(0.0, 1.0) - black[i].clamp(0.0, 1.0));
+        }
+        // The output is multiplied by `SCALE` to get centipawns, which are divided by the same
+        // value in the sigmoid
+        (sigmoid(output), white, black)
+    }
+
+    /// Adds the gradients of the squared error of the sample, and returns the error
+    fn backward(&mut self, sample: &Sample) -> f32 {
+        let (prediction, white, black) = self.forward(sample);
+        let error = prediction - sample.target;
+        let output_gradient = 2.0 * error * prediction * (1.0 - prediction);
+
+        for i in 0..HIDDEN {
+            let weight = self.parameters[OUTPUT_WEIGHTS + i];
+            self.gradients[OUTPUT_WEIGHTS + i] +=
+                output_gradient * (white[i].clamp(0.0, 1.0) - black[i].clamp(0.0, 1.0));
+
+            // The clipped activation only lets the gradient through between 0 and 1
+            let white_gradient = if white[i] > 0.0 && white[i] < 1.0 {
+                output_gradient * weight
+            } else {
+                0.0
+            };
+            let black_gradient = if black[i] > 0.0 && black[i] < 1.0 {
+                -output_gradient * weight
+            } else {
+                0.0
+            };
+
+            self.gradients[FEATURE_BIASES + i] += white_gradient + black_gradient;
+            for feature in &sample.white {
+                self.gradients[feature * HIDDEN + i] += white_gradient;
+            }
+            for feature in &sample.black {
+                self.gradients[feature * HIDDEN + i] += black_gradient;
+            }
+        }
+
+        error * error
+    }
+
+    /// Applies the accumulated gradients with Adam
+    fn step(&mut self, batch_size: usize) {
+        const BETA1: f32 = 0.9;
+        const BETA2: f32 = 0.999;
+        self.steps += 1;
+        let correction1 = 1.0 - BETA1.powi(self.steps);
+        let correction2 = 1.0 - BETA2.powi(self.steps);
+
+        for i in 0..PARAMETERS {
+            let gradient = self.gradients[i] / batch_size as f32;
+            self.first_moments[i] = BETA1 * self.first_moments[i] + (1.0 - BETA1) * gradient;
+            self.second_moments[i] =
+                BETA2 * self.second_moments[i] + (1.0 - BETA2) * gradient * gradient;
+            let m = self.first_moments[i] / correction1;
+            let v = self.second_moments[i] / correction2;
+            self.parameters[i] -= LEARNING_RATE * m / (v.sqrt() + 1e-8);
+
+            // Keep the feature weights small enough for the quantized accumulators
+            if i < OUTPUT_WEIGHTS {
+                self.parameters[i] = self.parameters[i].clamp(-1.0, 1.0);
+            }
+            self.gradients[i] = 0.0;
+        }
+    }
+
+    fn to_network(&self) -> Network {
+        Network::from_float(
+            &self.parameters[..FEATURE_BIASES],
+            &self.parameters[FEATURE_BIASES..OUTPUT_WEIGHTS],
+            &self.parameters[OUTPUT_WEIGHTS..],
+        )
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let games = args
+        .get(1)
+        .and_then(|a| a.parse().ok())
+        .unwrap_or(DEFAULT_GAMES);
+    let epochs = args
+        .get(2)
+        .and_then(|a| a.parse().ok())
+        .unwrap_or(DEFAULT_EPOCHS);
+    let output = args.get(3).map(String::as_str).unwrap_or(DEFAULT_OUTPUT);
+
+    let mut random = Random(0x9E3779B97F4A7C15);
+
+    // 1. Self-play
+    let start = Instant::now();
+    let mut samples = vec![];
+    for i in 0..games {
+        let (positions, result) = play_game(&mut random);
+        for (game, score) in positions {
+            let target = EVALUATION_WEIGHT * sigmoid(score / SIGMOID_SCALE)
+                + (1.0 - EVALUATION_WEIGHT) * result;
+            samples.push(Sample::new(&game, target));
+        }
+        if (i + 1) % 50 == 0 {
+            println!("{} games, {} positions", i + 1, samples.len());
+        }
+    }
+    println!("Self-play: {:.1} s", start.elapsed().as_secs_f32());
+
+    // Shuffle, and keep some positions to validate the network
+    for i in (1..samples.len()).rev() {
+        samples.swap(i, random.below(i + 1));
+    }
+    let validation = samples.split_off(samples.len() * 9 / 10);
+
+    // 2. Training
+    let mut trainer = Trainer::new(&mut random);
+    for epoch in 0..epochs {
+        let mut training_error = 0.0;
+        for batch in samples.chunks(BATCH_SIZE) {
+            for sample in batch {
+                training_error += trainer.backward(sample);
+            }
+            trainer.step(batch.len());
+        }
+        let validation_error: f32 = validation
+            .iter()
+            .map(|sample| (trainer.forward(sample).0 - sample.target).powi(2))
+            .sum();
+        println!(
+            "Epoch {:>3}: training error {:.5}, validation error {:.5}",
+            epoch + 1,
+            training_error / samples.len() as f32,
+            validation_error / validation.len() as f32
+        );
+    }
+
+    // 3. Quantization
+    let network = trainer.to_network();
+    let mut writer = BufWriter::new(File::create(output).expect("Can't create the output file"));
+    network.write(&mut writer).expect("Can't write the network");
+    println!("Network written to {output}");
+}
@@ -0,0 +1,395 @@
+//! Opening books in the Polyglot format.
+//!
+//! A book is a list of 16 bytes entries, sorted by the key of their position: the key (8 bytes),
+//! the move (2 bytes), its weight (2 bytes) and a learning value (4 bytes), all in big endian. The
+//! keys are Zobrist hashes computed with the random numbers of the Polyglot specification, so the
+//! books made by other tools can be used.
+//!
+//! Specification: http://hgm.nubati.net/book_format.html
+
+use model::chess_type::Type;
+use model::chess_type::Type::{Bishop, King, Knight, Pawn, Queen, Rook};
+use model::game::ChessGame;
+use model::moves::Move;
+use model::utils::ChessPosition;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+mod keys;
+
+use keys::RANDOM64;
+
+/// Size of an entry of a book, in bytes
+const ENTRY_SIZE: usize = 16;
+
+/// An entry of a book: a move which can be played in the position of the key
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BookEntry {
+    pub key: u64,
+    /// Move, encoded as in the Polyglot format
+    pub raw_move: u16,
+    /// Relative probability of playing the move
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl BookEntry {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            key: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            raw_move: u16::from_be_bytes(bytes[8..10].try_into().unwrap()),
+            weight: u16::from_be_bytes(bytes[10..12].try_into().unwrap()),
+            learn: u32::from_be_bytes(bytes[12..16].try_into().unwrap()),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; ENTRY_SIZE] {
+        let mut bytes = [0; ENTRY_SIZE];
+        bytes[0..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.raw_move.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        bytes[12..16].copy_from_slice(&self.learn.to_be_bytes());
+        bytes
+    }
+}
+
+/// Writes the entries of a book, which must be sorted by key
+pub fn write_book(entries: &[BookEntry], writer: &mut impl Write) -> io::Result<()> {
+    for entry in entries {
+        writer.write_all(&entry.to_bytes())?;
+    }
+    Ok(())
+}
+
+/// Returns the Polyglot key of a position, with its side to move.
+///
+/// The en passant file is only part of the key when a pawn of the side to move can actually
+/// capture en passant, as required by the format.
+pub fn polyglot_key(game: &ChessGame) -> u64 {
+    let white_to_play = game.white_to_play();
+    let mut key = 0;
+    for (piece_index, piece) in [Pawn, Knight, Bishop, Rook, Queen, King]
+        .into_iter()
+        .enumerate()
+    {
+        for white in [false, true] {
+            let kind = 2 * piece_index + white as usize;
+            let mut bitboard = game.pieces(piece, white);
+            while bitboard != 0 {
+                let square = bitboard.trailing_zeros() as usize;
+                key ^= RANDOM64[64 * kind + square];
+                bitboard &= bitboard - 1;
+            }
+        }
+    }
+
+    for (i, available) in game.castling_rights().into_iter().enumerate() {
+        if available {
+            key ^= RANDOM64[768 + i];
+        }
+    }
+
+    if let Some(square) = game.en_passant_square() {
+        let file = square % 8;
+        // The pawns which can capture are next to the pawn which has just moved
+        let pushed_pawn = if white_to_play {
+            square - 8
+        } else {
+            square + 8
+        };
+        let pawns = game.pieces(Pawn, white_to_play);
+        let can_capture = [-1, 1]
+            .into_iter()
+            .filter(|offset| (0..8).contains(&(file + offset)))
+            .any(|offset| pawns & (1 << (pushed_pawn + offset)) != 0);
+        if can_capture {
+            key ^= RANDOM64[772 + file as usize];
+        }
+    }
+
+    if white_to_play {
+        key ^= RANDOM64[780];
+    }
+    key
+}
+
+/// Pieces of the promotions in the Polyglot format, from 1 to 4
+const PROMOTIONS: [Type; 4] = [Knight, Bishop, Rook, Queen];
+
+/// Decodes a move of a book. Castling is written in the Polyglot format as the king capturing its
+/// own rook, and is converted to the move of the king by two squares. Returns `None` for an
+/// invalid promotion piece.
+pub fn decode_move(game: &ChessGame, raw_move: u16) -> Option<Move> {
+    let white_to_play = game.white_to_play();
+    let to = (raw_move & 0x3f) as ChessPosition;
+    let from = ((raw_move >> 6) & 0x3f) as ChessPosition;
+    let underpromotion = match (raw_move >> 12) & 0x7 {
+        0 | 4 => None,
+        promotion => Some(*PROMOTIONS.get(promotion as usize - 1)?),
+    };
+
+    let is_king = game.pieces(King, white_to_play) & (1 << from) != 0;
+    let to = match (is_king, from, to) {
+        (true, 4, 7) | (true, 60, 63) => from + 2,
+        (true, 4, 0) | (true, 60, 56) => from - 2,
+        _ => to,
+    };
+    Some(Move {
+        underpromotion,
+        ..Move::new(from, to, white_to_play)
+    })
+}
+
+/// Encodes a move in the Polyglot format
+pub fn encode_move(game: &ChessGame, m: &Move) -> u16 {
+    let is_king = game.pieces(King, m.is_white) & (1 << m.from) != 0;
+    let to = if is_king && (m.to - m.from).abs() == 2 {
+        // Castling: the king goes to the square of the rook
+        if m.to > m.from {
+            m.from + 3
+        } else {
+            m.from - 4
+        }
+    } else {
+        m.to
+    };
+    let promotion = if game.is_promotion(m) {
+        let piece = m.underpromotion.unwrap_or(Queen);
+        PROMOTIONS.iter().position(|&p| p == piece).unwrap() as u16 + 1
+    } else {
+        0
+    };
+    (to as u16) | ((m.from as u16) << 6) | promotion << 12
+}
+
+/// An opening book, loaded in memory
+pub struct OpeningBook {
+    /// Entries sorted by key
+    entries: Vec<BookEntry>,
+    /// State of the xorshift generator used to choose the moves
+    random: u64,
+}
+
+impl OpeningBook {
+    /// Loads a book from a file
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Reads a book from the content of a file
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "The size of the book is not a multiple of the size of the entries",
+            ));
+        }
+        let mut entries: Vec<BookEntry> = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(BookEntry::from_bytes)
+            .collect();
+        // Books should already be sorted, but the lookups rely on it
+        entries.sort_by_key(|entry| entry.key);
+
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        Ok(Self {
+            entries,
+            random: seed | 1,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Sets the seed of the random choice of the moves, to make it reproducible
+    pub fn set_seed(&mut self, seed: u64) {
+        self.random = seed | 1;
+    }
+
+    /// Returns the entries of the given key
+    pub fn entries(&self, key: u64) -> &[BookEntry] {
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let end = self.entries.partition_point(|entry| entry.key <= key);
+        &self.entries[start..end]
+    }
+
+    /// Returns the legal moves of the book in the position, with their weights
+    pub fn moves(&self, game: &ChessGame) -> Vec<(Move, u16)> {
+        let entries = self.entries(polyglot_key(game));
+        if entries.is_empty() {
+            return vec![];
+        }
+        let legal_moves: Vec<Move> = game
+            .legal_moves(game.white_to_play())
+            .into_iter()
+            .flat_map(|m| game.with_underpromotions(m))
+            .collect();
+        entries
+            .iter()
+            .filter_map(|entry| {
+                let m = decode_move(game, entry.raw_move)?;
+                legal_moves.contains(&m).then_some((m, entry.weight))
+            })
+            .collect()
+    }
+
+    /// Chooses a move of the book in the position, with a probability proportional to its weight.
+    /// Returns `None` when the position is not in the book.
+    pub fn choose_move(&mut self, game: &ChessGame) -> Option<Move> {
+        let moves = self.moves(game);
+        let total: u64 = moves.iter().map(|(_, weight)| *weight as u64).sum();
+        if total == 0 {
+            // Moves without weight are only played when there is no other choice
+            return moves.first().map(|(m, _)| *m);
+        }
+
+        let mut target = self.next_random() % total;
+        for (m, weight) in moves {
+            if target < weight as u64 {
+                return Some(m);
+            }
+            target -= weight as u64;
+        }
+        unreachable!("The target is lower than the sum of the weights")
+    }
+
+    /// xorshift64* pseudo-random generator
+    fn next_random(&mut self) -> u64 {
+        self.random ^= self.random >> 12;
+        self.random ^= self.random << 25;
+        self.random ^= self.random >> 27;
+        self.random.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::book::{decode_move, encode_move, polyglot_key, write_book, OpeningBook};
+    use model::chess_type::Type::Knight;
+    use model::game::ChessGame;
+    use model::moves::Move;
+
+    const FIXTURE: &[u8] = include_bytes!("../books/fixture.bin");
+
+    /// Keys given by the Polyglot specification
+    #[test]
+    fn test_polyglot_keys() {
+        let mut game = ChessGame::standard_game();
+        assert_eq!(0x463b96181691fc9c, polyglot_key(&game));
+
+        let moves = [
+            (("e2", "e4"), 0x823c9b50fd114196),
+            (("d7", "d5"), 0x0756b94461c50fb0),
+            (("e4", "e5"), 0x662fafb965db29d4),
+            // The pawn in e5 can capture en passant
+            (("f7", "f5"), 0x22a48b5a8e47ff78),
+            (("e1", "e2"), 0x652a607ca3f242c1),
+            (("e8", "f7"), 0x00fdd303c946bdd9),
+        ];
+        for ((from, to), key) in moves {
+            game.apply_move_unsafe(&Move::from_str(from, to, game.white_to_play()));
+            assert_eq!(key, polyglot_key(&game), "after {from}{to}");
+        }
+
+        // The castling rights and the en passant square are read from the FEN
+        for (fen, key) in [
+            (
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+                0x22a48b5a8e47ff78,
+            ),
+            (
+                "rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 0 4",
+                0x5c3f9b829b279560,
+            ),
+        ] {
+            assert_eq!(key, polyglot_key(&ChessGame::from_fen(fen)), "{fen}");
+        }
+    }
+
+    #[test]
+    fn test_moves_encoding() {
+        let game = ChessGame::from_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        for m in game.legal_moves(true) {
+            for m in game.with_underpromotions(m) {
+                let raw_move = encode_move(&game, &m);
+                assert_eq!(Some(m), decode_move(&game, raw_move));
+            }
+        }
+        // Castling is encoded as the king capturing its rook
+        let castling = Move::from_str("e8", "c8", false);
+        assert_eq!(0x0f38, encode_move(&game, &castling));
+        // Promotions of b7 to a knight and to a queen, and an invalid piece
+        let knight = Move {
+            underpromotion: Some(Knight),
+            ..Move::from_str("b7", "b8", true)
+        };
+        assert_eq!(Some(knight), decode_move(&game, 0x1000 | 0x0c79));
+        assert_eq!(0x4000 | 0x0c79, encode_move(&game, &Move::from_str("b7", "b8", true)));
+        assert_eq!(None, decode_move(&game, 0x5000 | 0x0c79));
+    }
+
+    #[test]
+    fn test_fixture() {
+        let mut book = OpeningBook::from_bytes(FIXTURE).unwrap();
+        assert_eq!(5, book.len());
+
+        let game = ChessGame::standard_game();
+        assert_eq!(
+            vec![
+                (Move::from_str("e2", "e4", true), 3),
+                (Move::from_str("d2", "d4", true), 1)
+            ],
+            book.moves(&game)
+        );
+
+        // The moves are chosen according to their weights
+        book.set_seed(42);
+        let e4 = (0..400)
+            .filter(|_| book.choose_move(&game) == Some(Move::from_str("e2", "e4", true)))
+            .count();
+        assert!((250..350).contains(&e4), "e4 was chosen {e4} times");
+
+        let game = ChessGame::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        assert_eq!(
+            Some(Move::from_str("e1", "g1", true)),
+            book.choose_move(&game)
+        );
+
+        // Out of book
+        let mut game = ChessGame::standard_game();
+        game.set_white_to_play(false);
+        assert!(book.moves(&game).is_empty());
+        let mut game = ChessGame::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1");
+        assert_eq!(None, book.choose_move(&game));
+        game.set_white_to_play(true);
+        assert!(book.choose_move(&game).is_some());
+    }
+
+    #[test]
+    fn test_write_book() {
+        let book = OpeningBook::from_bytes(FIXTURE).unwrap();
+        let mut bytes = vec![];
+        write_book(&book.entries, &mut bytes).unwrap();
+        assert_eq!(FIXTURE, bytes);
+    }
+
+    #[test]
+    fn test_invalid_book() {
+        assert!(OpeningBook::from_bytes(&FIXTURE[..20]).is_err());
+
+        // Illegal moves of the book are ignored
+        let mut bytes = FIXTURE[..16].to_vec();
+        bytes[8..10].copy_from_slice(&0x0324u16.to_be_bytes()); // e2e5
+        let book = OpeningBook::from_bytes(&bytes).unwrap();
+        assert!(book.moves(&ChessGame::standard_game()).is_empty());
+    }
+}
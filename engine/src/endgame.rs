@@ -0,0 +1,224 @@
+//! Knowledge of the endgames which the evaluation doesn't understand.
+//!
+//! The endgames are recognised by their material. Against a lone king, the positions are either
+//! draws or known wins, in which the score leads the search to the mate: the weak king is pushed
+//! to the edge (or to the corner of the bishop with a bishop and a knight) and the strong king
+//! comes closer. The king and pawn against king endgame is solved by a bitbase. In the other
+//! endgames, the side ahead without pawns and with too little extra material to mate gets a
+//! reduced score.
+
+use model::chess_type::Type::{Bishop, King, Knight, Pawn, Queen, Rook};
+use model::chess_type::{ScoreType, Type, PAWN_SCORE};
+use model::game::ChessGame;
+
+pub mod kpk;
+
+/// Score of the known wins, above the scores of the evaluation and below the scores of the
+/// tablebases and of the mates
+pub const KNOWN_WIN: ScoreType = 5_000;
+
+/// Pieces other than the kings, with the pawns first
+const PIECES: [Type; 5] = [Pawn, Knight, Bishop, Rook, Queen];
+
+/// Returns the score of the position from white's point of view, if it is an endgame against a
+/// lone king.
+///
+/// The score of the king and pawn endgame depends on the side to move of the game.
+pub fn evaluate(game: &ChessGame) -> Option<ScoreType> {
+    if game.is_finished() {
+        return None;
+    }
+    let strong = if count_pieces(game, false).iter().all(|&n| n == 0) {
+        true
+    } else if count_pieces(game, true).iter().all(|&n| n == 0) {
+        false
+    } else {
+        return None;
+    };
+
+    let score = match count_pieces(game, strong) {
+        // Kings, and a minor piece or two knights, can't mate
+        [0, 0, 0, 0, 0] | [0, 1, 0, 0, 0] | [0, 0, 1, 0, 0] | [0, 2, 0, 0, 0] => 0,
+        [1, 0, 0, 0, 0] => king_and_pawn(game, strong, game.white_to_play() == strong),
+        [0, 1, 1, 0, 0] => bishop_and_knight(game, strong),
+        [_, _, _, rooks, queens] if rooks + queens > 0 => mating_net(game, strong),
+        [0, _, _, _, _] => mating_net(game, strong),
+        // The pawns and minor pieces are left to the evaluation
+        _ => return None,
+    };
+    Some(if strong { score } else { -score })
+}
+
+/// Reduces the score of the side ahead, when it has no pawns and not enough extra material to win
+pub fn scale(game: &ChessGame, score: ScoreType) -> ScoreType {
+    if score == 0 || game.is_finished() {
+        return score;
+    }
+    let strong = score > 0;
+    if game.pieces(Pawn, strong) != 0 {
+        return score;
+    }
+    let strong_material = non_pawn_material(game, strong);
+    let weak_material = non_pawn_material(game, !strong);
+    if strong_material - weak_material > Bishop.score() {
+        score
+    } else if strong_material < Rook.score() {
+        // A minor piece can't win without pawns
+        0
+    } else {
+        score / 4
+    }
+}
+
+/// Numbers of pawns, knights, bishops, rooks and queens of a side
+fn count_pieces(game: &ChessGame, white: bool) -> [u32; 5] {
+    PIECES.map(|piece| game.pieces(piece, white).count_ones())
+}
+
+fn non_pawn_material(game: &ChessGame, white: bool) -> ScoreType {
+    PIECES[1..]
+        .iter()
+        .map(|piece| game.pieces(*piece, white).count_ones() as ScoreType * piece.score())
+        .sum()
+}
+
+fn material(game: &ChessGame, white: bool) -> ScoreType {
+    PAWN_SCORE
+        * (game.pieces(Pawn, white).count_ones() as ScoreType + non_pawn_material(game, white))
+}
+
+fn king_square(game: &ChessGame, white: bool) -> usize {
+    game.pieces(King, white).trailing_zeros() as usize
+}
+
+fn distance(a: usize, b: usize) -> ScoreType {
+    (a % 8).abs_diff(b % 8).max((a / 8).abs_diff(b / 8)) as ScoreType
+}
+
+/// Bonus for the weak king far from the centre, from 0 to 120
+fn push_to_edge(square: usize) -> ScoreType {
+    let from_centre = |coordinate: usize| coordinate.max(3) - coordinate.min(4);
+    20 * (from_centre(square % 8) + from_centre(square / 8)) as ScoreType
+}
+
+/// Bonus for the kings close to each other, from 0 to 60
+fn push_close(a: usize, b: usize) -> ScoreType {
+    10 * (7 - distance(a, b))
+}
+
+/// Any material with a rook or a queen mates a lone king, which is pushed to the edge
+fn mating_net(game: &ChessGame, strong: bool) -> ScoreType {
+    let strong_king = king_square(game, strong);
+    let weak_king = king_square(game, !strong);
+    KNOWN_WIN
+        + material(game, strong)
+        + push_to_edge(weak_king)
+        + push_close(strong_king, weak_king)
+}
+
+/// The bishop and the knight only mate in the corners of the colour of the bishop
+fn bishop_and_knight(game: &ChessGame, strong: bool) -> ScoreType {
+    let strong_king = king_square(game, strong);
+    let weak_king = king_square(game, !strong);
+    let bishop = game.pieces(Bishop, strong).trailing_zeros() as usize;
+    // a1 and h8 are dark squares
+    let corners = if (bishop % 8 + bishop / 8).is_multiple_of(2) {
+        [0, 63]
+    } else {
+        [7, 56]
+    };
+    // Number of king moves to the closest of these corners along the edges, from 0 to 7
+    let to_corner = corners
+        .map(|corner: usize| {
+            (weak_king % 8).abs_diff(corner % 8) + (weak_king / 8).abs_diff(corner / 8)
+        })
+        .into_iter()
+        .min()
+        .unwrap() as ScoreType;
+    KNOWN_WIN
+        + material(game, strong)
+        + 40 * (7 - to_corner)
+        + push_to_edge(weak_king)
+        + push_close(strong_king, weak_king)
+}
+
+/// The bitbase tells whether the pawn promotes, and the pawn is pushed to get closer to the queen
+fn king_and_pawn(game: &ChessGame, strong: bool, strong_to_play: bool) -> ScoreType {
+    // The bitbase is seen from the side of white
+    let flip = if strong { 0 } else { 56 };
+    let pawn = game.pieces(Pawn, strong).trailing_zeros() as usize ^ flip;
+    let strong_king = king_square(game, strong) ^ flip;
+    let weak_king = king_square(game, !strong) ^ flip;
+    if kpk::probe(strong_king, pawn, weak_king, strong_to_play) {
+        KNOWN_WIN + PAWN_SCORE + 20 * (pawn / 8) as ScoreType
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::endgame::{evaluate, scale, KNOWN_WIN};
+    use model::game::ChessGame;
+
+    fn score(fen: &str) -> Option<i64> {
+        evaluate(&ChessGame::from_fen(fen))
+    }
+
+    #[test]
+    fn test_recognised_endgames() {
+        // Draws by insufficient material
+        assert_eq!(Some(0), score("8/8/4k3/8/8/2K5/8/8 w - - 0 1"));
+        assert_eq!(Some(0), score("8/8/4k3/8/8/2K5/5B2/8 w - - 0 1"));
+        assert_eq!(Some(0), score("8/8/4k3/8/8/2K5/3N1N2/8 w - - 0 1"));
+        assert_eq!(Some(0), score("8/8/4kn2/8/8/2K5/8/8 w - - 0 1"));
+
+        // Wins against a lone king, for both sides
+        assert!(score("8/8/4k3/8/8/2K5/8/3Q4 w - - 0 1").unwrap() > KNOWN_WIN);
+        assert!(score("8/8/4k3/8/8/2K5/8/3q4 w - - 0 1").unwrap() < -KNOWN_WIN);
+        assert!(score("8/8/4k3/8/8/2K5/8/3R4 w - - 0 1").unwrap() > KNOWN_WIN);
+        assert!(score("8/8/4k3/8/8/2K5/8/2BN4 w - - 0 1").unwrap() > KNOWN_WIN);
+
+        // The other endgames are left to the evaluation
+        assert_eq!(None, score("8/8/4k3/8/8/2K5/8/3R3r w - - 0 1"));
+        assert_eq!(None, score("8/8/4k3/8/8/2K5/3P4/3N4 w - - 0 1"));
+        assert_eq!(None, score("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"));
+    }
+
+    #[test]
+    fn test_king_and_pawn() {
+        // With the opposition, white wins
+        assert!(score("8/4k3/8/4K3/4P3/8/8/8 b - - 0 1").unwrap() > KNOWN_WIN);
+        assert_eq!(Some(0), score("8/4k3/8/4K3/4P3/8/8/8 w - - 0 1"));
+        // Same position for black
+        assert!(score("8/8/8/4p3/4k3/8/4K3/8 w - - 0 1").unwrap() < -KNOWN_WIN);
+        assert_eq!(Some(0), score("8/8/8/4p3/4k3/8/4K3/8 b - - 0 1"));
+    }
+
+    #[test]
+    fn test_mating_nets() {
+        // The weak king is better in the centre
+        let centre = score("8/8/8/4k3/8/2K5/8/3R4 w - - 0 1").unwrap();
+        let edge = score("4k3/8/8/8/8/2K5/8/3R4 w - - 0 1").unwrap();
+        assert!(edge > centre);
+
+        // With a dark-squared bishop, the king is mated in a1 or h8
+        let right_corner = score("7k/8/8/8/8/2K5/8/2BN4 w - - 0 1").unwrap();
+        let wrong_corner = score("k7/8/8/8/8/2K5/8/2BN4 w - - 0 1").unwrap();
+        assert!(right_corner > wrong_corner);
+    }
+
+    #[test]
+    fn test_scale() {
+        // A rook against a minor piece is drawish
+        let game = ChessGame::from_fen("8/8/4k3/4n3/8/2K5/8/3R4 w - - 0 1");
+        assert_eq!(50, scale(&game, 200));
+        // A minor piece against pawns can't win
+        let game = ChessGame::from_fen("8/8/4k3/4p3/8/2K5/8/3B4 w - - 0 1");
+        assert_eq!(0, scale(&game, 150));
+        assert_eq!(-150, scale(&game, -150));
+        // A queen against a rook wins
+        let game = ChessGame::from_fen("8/8/4k3/4r3/8/2K5/8/3Q4 w - - 0 1");
+        assert_eq!(400, scale(&game, 400));
+    }
+}
@@ -0,0 +1,212 @@
+//! Bitbase of the king and pawn against king endgame.
+//!
+//! The result of every position is computed once, by retrograde analysis: the positions which are
+//! won or drawn immediately are classified first, and the others are classified from the results
+//! of their moves until nothing changes. The promotions which lose the new queen are not
+//! considered, which is fine since the positions in which the pawn can promote safely are wins.
+//! Only the pawns of the files a to d are stored, the other positions are mirrored.
+
+use once_cell::sync::Lazy;
+
+/// 2 sides to move, 24 squares of the pawn (files a to d, ranks 2 to 7), 64 squares of each king
+const MAX_INDEX: usize = 2 * 24 * 64 * 64;
+
+const WHITE: usize = 0;
+const BLACK: usize = 1;
+
+/// Results of the positions during the generation, which are combined with a bitwise or
+const INVALID: u8 = 0;
+const UNKNOWN: u8 = 1;
+const DRAW: u8 = 2;
+const WIN: u8 = 4;
+
+/// One bit per position, set if the position is won by the side with the pawn
+static KPK: Lazy<Vec<u64>> = Lazy::new(generate);
+
+/// Returns true if the side with the pawn wins the position. The squares are given with the pawn of
+/// the strong side moving up the board, so the positions with a black pawn must be flipped first.
+pub fn probe(strong_king: usize, pawn: usize, weak_king: usize, strong_to_play: bool) -> bool {
+    // The bitbase only contains the pawns of the files a to d
+    let (strong_king, pawn, weak_king) = if pawn % 8 > 3 {
+        (strong_king ^ 7, pawn ^ 7, weak_king ^ 7)
+    } else {
+        (strong_king, pawn, weak_king)
+    };
+    let side_to_move = if strong_to_play { WHITE } else { BLACK };
+    let i = index(side_to_move, weak_king, strong_king, pawn);
+    (KPK[i / 64] >> (i % 64)) & 1 == 1
+}
+
+fn index(side_to_move: usize, black_king: usize, white_king: usize, pawn: usize) -> usize {
+    white_king | black_king << 6 | side_to_move << 12 | (pawn % 8) << 13 | (6 - pawn / 8) << 15
+}
+
+/// Returns the side to move, the black king, the white king and the pawn of an index
+fn decode(index: usize) -> (usize, usize, usize, usize) {
+    let pawn = (6 - (index >> 15)) * 8 + ((index >> 13) & 3);
+    ((index >> 12) & 1, (index >> 6) & 63, index & 63, pawn)
+}
+
+fn distance(a: usize, b: usize) -> usize {
+    (a % 8).abs_diff(b % 8).max((a / 8).abs_diff(b / 8))
+}
+
+fn king_attacks(square: usize) -> u64 {
+    (0..64)
+        .filter(|&to| distance(square, to) == 1)
+        .fold(0, |attacks, to| attacks | 1 << to)
+}
+
+/// Squares attacked by a white pawn
+fn pawn_attacks(square: usize) -> u64 {
+    let mut attacks = 0;
+    if !square.is_multiple_of(8) {
+        attacks |= 1 << (square + 7);
+    }
+    if square % 8 < 7 {
+        attacks |= 1 << (square + 9);
+    }
+    attacks
+}
+
+fn squares(mut bitboard: u64) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        (bitboard != 0).then(|| {
+            let square = bitboard.trailing_zeros() as usize;
+            bitboard &= bitboard - 1;
+            square
+        })
+    })
+}
+
+fn generate() -> Vec<u64> {
+    let kings: Vec<u64> = (0..64).map(king_attacks).collect();
+    let mut results: Vec<u8> = (0..MAX_INDEX).map(|i| initial_result(&kings, i)).collect();
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for i in 0..MAX_INDEX {
+            if results[i] == UNKNOWN {
+                results[i] = result_of_moves(&kings, &results, i);
+                changed |= results[i] != UNKNOWN;
+            }
+        }
+    }
+
+    let mut bits = vec![0; MAX_INDEX / 64];
+    for (i, _) in results.iter().enumerate().filter(|(_, &r)| r == WIN) {
+        bits[i / 64] |= 1 << (i % 64);
+    }
+    bits
+}
+
+/// Classifies the illegal positions, and the positions which are won or drawn immediately
+fn initial_result(kings: &[u64], index: usize) -> u8 {
+    let (side_to_move, black_king, white_king, pawn) = decode(index);
+    let promotion = pawn + 8;
+
+    if distance(white_king, black_king) <= 1
+        || white_king == pawn
+        || black_king == pawn
+        || (side_to_move == WHITE && pawn_attacks(pawn) & 1 << black_king != 0)
+    {
+        INVALID
+    } else if side_to_move == WHITE
+        && pawn / 8 == 6
+        && white_king != promotion
+        && (distance(black_king, promotion) > 1 || distance(white_king, promotion) == 1)
+    {
+        // The pawn promotes, and the queen can't be taken
+        WIN
+    } else if side_to_move == BLACK
+        && kings[black_king] & !(kings[white_king] | pawn_attacks(pawn)) == 0
+    {
+        // Stalemate
+        DRAW
+    } else if side_to_move == BLACK && kings[black_king] & !kings[white_king] & 1 << pawn != 0 {
+        // The pawn is taken
+        DRAW
+    } else {
+        UNKNOWN
+    }
+}
+
+/// Classifies a position from the results of the positions reached by its moves
+fn result_of_moves(kings: &[u64], results: &[u8], index: usize) -> u8 {
+    let (side_to_move, black_king, white_king, pawn) = decode(index);
+    let mut reached = INVALID;
+
+    if side_to_move == WHITE {
+        for to in squares(kings[white_king]) {
+            reached |= results[self::index(BLACK, black_king, to, pawn)];
+        }
+        // The pushes on the square of a king lead to invalid positions
+        if pawn / 8 < 6 {
+            let push = pawn + 8;
+            reached |= results[self::index(BLACK, black_king, white_king, push)];
+            if pawn / 8 == 1 && push != white_king && push != black_king {
+                reached |= results[self::index(BLACK, black_king, white_king, push + 8)];
+            }
+        }
+        if reached & WIN != 0 {
+            WIN
+        } else if reached & UNKNOWN != 0 {
+            UNKNOWN
+        } else {
+            DRAW
+        }
+    } else {
+        for to in squares(kings[black_king]) {
+            reached |= results[self::index(WHITE, to, white_king, pawn)];
+        }
+        if reached & DRAW != 0 {
+            DRAW
+        } else if reached & UNKNOWN != 0 {
+            UNKNOWN
+        } else {
+            WIN
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::endgame::kpk::{decode, index, probe, KPK, MAX_INDEX};
+    use model::utils::chesspos_to_index;
+
+    fn square(name: &str) -> usize {
+        chesspos_to_index(name).unwrap() as usize
+    }
+
+    #[test]
+    fn test_index() {
+        for i in 0..MAX_INDEX {
+            let (side_to_move, black_king, white_king, pawn) = decode(i);
+            assert_eq!(i, index(side_to_move, black_king, white_king, pawn));
+        }
+    }
+
+    #[test]
+    fn test_kpk() {
+        // The king on the sixth rank in front of its pawn always wins
+        assert!(probe(square("e6"), square("e5"), square("e8"), false));
+        assert!(probe(square("e6"), square("e5"), square("e8"), true));
+        // Otherwise, the king needs the opposition
+        assert!(probe(square("e5"), square("e4"), square("e7"), false));
+        assert!(!probe(square("e5"), square("e4"), square("e7"), true));
+        // The rook pawns are drawn when the defending king reaches the corner
+        assert!(!probe(square("h6"), square("h5"), square("h8"), false));
+        assert!(!probe(square("h6"), square("h5"), square("h8"), true));
+        // The square of the pawn
+        assert!(!probe(square("h1"), square("a4"), square("f4"), false));
+        assert!(probe(square("h1"), square("a4"), square("g4"), false));
+        assert!(probe(square("h1"), square("a4"), square("f4"), true));
+        // A pawn on the second rank can be pushed twice
+        assert!(probe(square("a1"), square("d2"), square("h3"), true));
+
+        // Same number of wins as the bitbase of Stockfish, which is built the same way
+        let wins: u32 = KPK.iter().map(|bits| bits.count_ones()).sum();
+        assert_eq!(111_282, wins);
+    }
+}
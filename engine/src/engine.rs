@@ -1,14 +1,45 @@
 use model::chess_type::ScoreType;
 use model::game::ChessGame;
 use model::moves::Move;
+use crate::stats::SearchStats;
 
+#[derive(Debug)]
 pub struct SearchResult {
     pub score: ScoreType,
     pub best_move: Option<Move>,
+    /// Principal variation: the line of moves expected by the engine, starting with `best_move`.
+    /// It is only filled for the root of the search.
+    pub pv: Vec<Move>,
+    /// Number of nodes visited since the beginning of the search. Like `pv`, it is only filled
+    /// for the root of the search.
+    pub nodes: u64,
+    /// Statistics of the search, when the engine collects them. Like `pv`, they are only filled
+    /// for the root of the search.
+    pub stats: Option<SearchStats>,
 }
 
 pub trait Engine {
-    /// For a given chess game, finds the solver's best move and returns it as an Option of a move.
-    /// The function also returns the NPS (nodes per second) in the unit k-nps (for benchmarking)
-    fn find_best_move(&mut self, game: ChessGame, white_to_play: bool) -> SearchResult;
+    /// For a given chess game, finds the solver's best move for the side to move and returns it as
+    /// an Option of a move. The function also returns the number of visited nodes (for benchmarking)
+    fn find_best_move(&mut self, game: ChessGame) -> SearchResult;
+
+    /// Finds the best move of the position reached by playing `moves` from `start`. The engines
+    /// which know the previous positions of the game, such as the UCI engines, can then detect
+    /// the repetitions, while the other ones only search the last position.
+    fn find_best_move_in_game(&mut self, start: ChessGame, moves: &[Move]) -> SearchResult {
+        let mut game = start;
+        for m in moves {
+            game.apply_move_unsafe(m);
+        }
+        self.find_best_move(game)
+    }
+
+    /// MultiPV analysis: finds the `count` best moves of the position, sorted from the best to the
+    /// worst, each one with its own score and principal variation.
+    ///
+    /// Engines which can't analyse several lines only return their best move.
+    fn find_best_moves(&mut self, game: ChessGame, count: usize) -> Vec<SearchResult> {
+        let _ = count;
+        vec![self.find_best_move(game)]
+    }
 }
@@ -2,6 +2,9 @@ use model::chess_type::ScoreType;
 use model::game::ChessGame;
 use crate::alpha_beta::AlphaBetaEngine;
 use crate::engine::{Engine, SearchResult};
+use crate::tablebase::Tablebase;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// A search engine which uses iterative deepening to sort the best moves at
 /// each level.
@@ -9,19 +12,27 @@ pub struct IterativeDeepeningEngine {
     depth: usize,
     extra_depth: usize,
     initial_depth: usize,
+    /// When set, no new iteration is started once this time has elapsed
+    time_limit: Option<Duration>,
+    /// Depth of the last iteration of the last search
+    reached_depth: usize,
+    collect_stats: bool,
+    tablebase: Option<Arc<dyn Tablebase>>,
 }
 
 impl Engine for IterativeDeepeningEngine {
-    fn find_best_move(&mut self, game: ChessGame, white_to_play: bool) -> SearchResult {
+    fn find_best_move(&mut self, mut game: ChessGame) -> SearchResult {
+        let start = Instant::now();
         let mut search_engine = AlphaBetaEngine::new(6, 0);
+        search_engine.set_collect_stats(self.collect_stats);
+        search_engine.set_tablebase(self.tablebase.clone());
         let mut first_move = None;
 
         let mut depth = self.initial_depth;
         loop {
             search_engine.set_engine_depth(depth, self.extra_depth);
             let result = search_engine.alpha_beta_search(
-                game,
-                white_to_play,
+                &mut game,
                 0,
                 i32::MIN as ScoreType,
                 i32::MAX as ScoreType,
@@ -29,7 +40,9 @@ impl Engine for IterativeDeepeningEngine {
                 first_move,
             );
 
-            if depth == self.depth {
+            let out_of_time = self.time_limit.is_some_and(|limit| start.elapsed() >= limit);
+            if depth == self.depth || out_of_time {
+                self.reached_depth = depth;
                 return result;
             }
 
@@ -37,6 +50,23 @@ impl Engine for IterativeDeepeningEngine {
             depth += 1;
         }
     }
+
+    fn find_best_moves(&mut self, game: ChessGame, count: usize) -> Vec<SearchResult> {
+        // The previous iterations only search the best line, which is used to sort the moves of
+        // the last iteration.
+        let first_move = if self.depth > self.initial_depth {
+            let mut previous = IterativeDeepeningEngine::new(self.depth - 1, self.extra_depth);
+            previous.set_tablebase(self.tablebase.clone());
+            previous.find_best_move(game).best_move
+        } else {
+            None
+        };
+
+        let mut search_engine = AlphaBetaEngine::new(self.depth, self.extra_depth);
+        search_engine.set_collect_stats(self.collect_stats);
+        search_engine.set_tablebase(self.tablebase.clone());
+        search_engine.multi_pv_search(game, count, first_move)
+    }
 }
 
 impl IterativeDeepeningEngine {
@@ -45,6 +75,33 @@ impl IterativeDeepeningEngine {
             depth,
             extra_depth,
             initial_depth: 1,
+            time_limit: None,
+            reached_depth: 0,
+            collect_stats: false,
+            tablebase: None,
         }
     }
+
+    /// Limits the time of the searches: the deepening stops after the first iteration which ends
+    /// after the limit, even if the maximum depth isn't reached.
+    pub fn set_time_limit(&mut self, time_limit: Option<Duration>) {
+        self.time_limit = time_limit;
+    }
+
+    /// Enables or disables the collection of the statistics of the searches. They add up the
+    /// nodes of all the iterations.
+    pub fn set_collect_stats(&mut self, collect_stats: bool) {
+        self.collect_stats = collect_stats;
+    }
+
+    /// Sets the endgame tablebases used by the searches
+    pub fn set_tablebase(&mut self, tablebase: Option<Arc<dyn Tablebase>>) {
+        self.tablebase = tablebase;
+    }
+
+    /// Returns the depth of the last completed iteration of the last search, which is lower than
+    /// the maximum depth when the time limit is reached.
+    pub fn reached_depth(&self) -> usize {
+        self.reached_depth
+    }
 }
@@ -1,5 +1,10 @@
 pub mod alpha_beta;
+pub mod book;
+pub mod endgame;
 pub mod iterative_deepening;
 pub mod engine;
+pub mod nnue;
+pub mod stats;
+pub mod tablebase;
 #[cfg(test)]
 mod tests;
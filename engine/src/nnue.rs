@@ -0,0 +1,419 @@
+//! Efficiently updatable neural network (NNUE) evaluation.
+//!
+//! # Architecture
+//!
+//! The network has 768 inputs: one for each combination of color (2), type of piece (6) and
+//! square (64). It is evaluated from the point of view of both players: the inputs are relative to
+//! the player ("own" and "enemy" pieces), and the board is flipped vertically for black.
+//!
+//! Each point of view has an accumulator: the sum of the biases and of the weights of the active
+//! inputs, for each of the `HIDDEN` neurons of the hidden layer. The accumulators are updated
+//! incrementally from the move played, since only the inputs of the pieces that it moves change.
+//!
+//! The score is from white's point of view whatever the side to move, like the classical
+//! evaluation, so the output is computed from the difference between the two points of view, which
+//! gives opposite scores to mirrored positions:
+//!
+//! ```text
+//! score = sum(output_weights[i] * (crelu(white[i]) - crelu(black[i]))) * SCALE / (QA * QB)
+//! ```
+//!
+//! where `crelu` clamps the values between 0 and `QA`. The score is in centipawns, from white's
+//! point of view, like `ChessGame::score`.
+//!
+//! # Weights file format
+//!
+//! All the values are little-endian.
+//!
+//! | Field            | Type  | Count            | Description                                |
+//! |------------------|-------|------------------|--------------------------------------------|
+//! | magic            | bytes | 4                | `NNUE`                                     |
+//! | version          | u32   | 1                | `1`                                        |
+//! | inputs           | u32   | 1                | `768`                                      |
+//! | hidden           | u32   | 1                | must be equal to `HIDDEN`                  |
+//! | feature weights  | i16   | inputs * hidden  | the `hidden` weights of input 0, then 1... |
+//! | feature biases   | i16   | hidden           | biases of the hidden layer                 |
+//! | output weights   | i16   | hidden           | weights of the output                      |
+//!
+//! The feature weights and biases are quantized with `QA`, the output weights with `QB`.
+//!
+//! The index of an input is `color * 384 + piece * 64 + square`, where `color` is 0 for the
+//! pieces of the player and 1 for the pieces of its opponent, `piece` is 0 to 5 for pawn, knight,
+//! bishop, rook, queen and king, and `square` is the square from white's point of view (a1 is 0,
+//! h8 is 63), flipped vertically (`square ^ 56`) for black.
+
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use model::chess_type::ScoreType;
+use model::chess_type::Type::{Bishop, King, Knight, Pawn, Queen, Rook};
+use model::chess_type::Type;
+use model::compact_move::CompactMove;
+use model::game::ChessGame;
+use model::moves::Move;
+use model::utils::ChessPosition;
+use once_cell::sync::Lazy;
+
+/// Number of inputs of the network
+pub const INPUTS: usize = 768;
+
+/// Number of neurons of the hidden layer
+pub const HIDDEN: usize = 32;
+
+/// Quantization of the feature weights and biases: the clipped activations are between 0 and `QA`
+pub const QA: i32 = 255;
+
+/// Quantization of the output weights
+pub const QB: i32 = 64;
+
+/// Scale of the output, to convert it into centipawns
+pub const SCALE: i32 = 400;
+
+const MAGIC: &[u8; 4] = b"NNUE";
+const VERSION: u32 = 1;
+
+/// Order of the pieces in the inputs of the network
+const PIECES: [Type; 6] = [Pawn, Knight, Bishop, Rook, Queen, King];
+
+/// Network trained by `train_nnue` on positions from self-play games
+static BUNDLED: Lazy<Arc<Network>> = Lazy::new(|| {
+    let bytes = include_bytes!("../nets/default.nnue");
+    Arc::new(Network::read(&mut bytes.as_slice()).expect("The bundled network is valid"))
+});
+
+/// The quantized weights of a network
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Network {
+    feature_weights: Vec<[i16; HIDDEN]>,
+    feature_biases: [i16; HIDDEN],
+    output_weights: [i16; HIDDEN],
+}
+
+impl Network {
+    /// Returns the network bundled with the engine
+    pub fn bundled() -> Arc<Network> {
+        BUNDLED.clone()
+    }
+
+    /// Quantizes a network trained with floating point numbers.
+    ///
+    /// `feature_weights` contains the `HIDDEN` weights of each input, one input after the other.
+    pub fn from_float(
+        feature_weights: &[f32],
+        feature_biases: &[f32],
+        output_weights: &[f32],
+    ) -> Self {
+        assert_eq!(INPUTS * HIDDEN, feature_weights.len());
+        let quantize = |value: f32, factor: i32| {
+            (value * factor as f32)
+                .round()
+                .clamp(i16::MIN as f32, i16::MAX as f32) as i16
+        };
+
+        Self {
+            feature_weights: feature_weights
+                .chunks(HIDDEN)
+                .map(|weights| std::array::from_fn(|i| quantize(weights[i], QA)))
+                .collect(),
+            feature_biases: std::array::from_fn(|i| quantize(feature_biases[i], QA)),
+            output_weights: std::array::from_fn(|i| quantize(output_weights[i], QB)),
+        }
+    }
+
+    /// Loads a network from a weights file
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::read(&mut BufReader::new(File::open(path)?))
+    }
+
+    /// Reads a network in the weights file format
+    pub fn read(reader: &mut impl Read) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("Not a network file"));
+        }
+        if read_u32(reader)? != VERSION {
+            return Err(invalid("Unsupported version of network file"));
+        }
+        if read_u32(reader)? as usize != INPUTS || read_u32(reader)? as usize != HIDDEN {
+            return Err(invalid("Unsupported architecture of network"));
+        }
+
+        let mut feature_weights = vec![[0; HIDDEN]; INPUTS];
+        for weights in feature_weights.iter_mut() {
+            read_i16s(reader, weights)?;
+        }
+        let mut feature_biases = [0; HIDDEN];
+        read_i16s(reader, &mut feature_biases)?;
+        let mut output_weights = [0; HIDDEN];
+        read_i16s(reader, &mut output_weights)?;
+
+        Ok(Self {
+            feature_weights,
+            feature_biases,
+            output_weights,
+        })
+    }
+
+    /// Writes the network in the weights file format
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        for value in [VERSION, INPUTS as u32, HIDDEN as u32] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        let values = self
+            .feature_weights
+            .iter()
+            .flatten()
+            .chain(self.feature_biases.iter())
+            .chain(self.output_weights.iter());
+        for value in values {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Evaluates a game from scratch, without any accumulator to update
+    pub fn evaluate(&self, game: &ChessGame) -> ScoreType {
+        Accumulator::new(self, game).evaluate(self)
+    }
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_i16s(reader: &mut impl Read, values: &mut [i16]) -> io::Result<()> {
+    let mut bytes = [0u8; 2];
+    for value in values {
+        reader.read_exact(&mut bytes)?;
+        *value = i16::from_le_bytes(bytes);
+    }
+    Ok(())
+}
+
+/// Returns the indices of the input of a piece, from the point of view of white and of black
+pub fn feature_indices(piece_index: usize, white_piece: bool, sq: usize) -> (usize, usize) {
+    let white_view = (!white_piece as usize) * 384 + piece_index * 64 + sq;
+    let black_view = (white_piece as usize) * 384 + piece_index * 64 + (sq ^ 56);
+    (white_view, black_view)
+}
+
+/// Returns the indices of the input of a piece of the game
+fn features(piece: Type, white: bool, sq: ChessPosition) -> (usize, usize) {
+    let piece_index = PIECES.iter().position(|p| *p == piece).expect("All the pieces are inputs");
+    feature_indices(piece_index, white, sq as usize)
+}
+
+/// Calls `f` with the bitboard of each type and color of piece, and the index of the piece in the
+/// inputs of the network.
+fn for_each_bitboard(game: &ChessGame, mut f: impl FnMut(usize, bool, u64)) {
+    for (piece_index, piece) in PIECES.into_iter().enumerate() {
+        for white in [true, false] {
+            f(piece_index, white, game.pieces(piece, white));
+        }
+    }
+}
+
+/// The hidden layer of the network, from the point of view of each player. The weights are
+/// summed in 32 bits, since the sum of the weights of 32 pieces can exceed the range of `i16`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Accumulator {
+    white: [i32; HIDDEN],
+    black: [i32; HIDDEN],
+}
+
+impl Accumulator {
+    /// Computes the accumulator of a game from scratch
+    pub fn new(network: &Network, game: &ChessGame) -> Self {
+        let biases = network.feature_biases.map(i32::from);
+        let mut accumulator = Self {
+            white: biases,
+            black: biases,
+        };
+        for_each_bitboard(game, |piece_index, white, mut bitboard| {
+            while bitboard != 0 {
+                let sq = bitboard.trailing_zeros() as usize;
+                accumulator.add(network, feature_indices(piece_index, white, sq));
+                bitboard &= bitboard - 1;
+            }
+        });
+        accumulator
+    }
+
+    /// Updates the accumulator of `game` so that it becomes the accumulator of the game after
+    /// `apply_move_unsafe(m)`.
+    ///
+    /// Only the inputs changed by the move are updated: the moved piece, and the captured piece,
+    /// the rook of a castling or the promoted piece.
+    pub fn update(&mut self, network: &Network, game: &ChessGame, m: &Move) {
+        let Some(piece) = game.type_at_index(m.from) else {
+            // `apply_move_unsafe` doesn't play the moves without piece
+            return;
+        };
+        let white = game.pieces(piece, true) & (1 << m.from) != 0;
+        let compact_move = CompactMove::new(game, m);
+        let placed = if compact_move.is_promotion() {
+            compact_move.underpromotion().unwrap_or(Queen)
+        } else {
+            piece
+        };
+        self.remove(network, features(piece, white, m.from));
+        self.add(network, features(placed, white, m.to));
+
+        if compact_move.is_en_passant() {
+            let captured = if white { m.to - 8 } else { m.to + 8 };
+            self.remove(network, features(Pawn, !white, captured));
+        } else if let Some(captured) = game.type_at_index(m.to) {
+            self.remove(network, features(captured, !white, m.to));
+        }
+
+        if compact_move.is_castle() {
+            let (rook_from, rook_to) = if m.to > m.from {
+                (m.from + 3, m.from + 1)
+            } else {
+                (m.from - 4, m.from - 1)
+            };
+            self.remove(network, features(Rook, white, rook_from));
+            self.add(network, features(Rook, white, rook_to));
+        }
+    }
+
+    fn add(&mut self, network: &Network, (white_view, black_view): (usize, usize)) {
+        for (value, weight) in self.white.iter_mut().zip(&network.feature_weights[white_view]) {
+            *value += *weight as i32;
+        }
+        for (value, weight) in self.black.iter_mut().zip(&network.feature_weights[black_view]) {
+            *value += *weight as i32;
+        }
+    }
+
+    fn remove(&mut self, network: &Network, (white_view, black_view): (usize, usize)) {
+        for (value, weight) in self.white.iter_mut().zip(&network.feature_weights[white_view]) {
+            *value -= *weight as i32;
+        }
+        for (value, weight) in self.black.iter_mut().zip(&network.feature_weights[black_view]) {
+            *value -= *weight as i32;
+        }
+    }
+
+    /// Computes the output of the network, in centipawns from white's point of view
+    pub fn evaluate(&self, network: &Network) -> ScoreType {
+        let mut output: i32 = 0;
+        for i in 0..HIDDEN {
+            let white = self.white[i].clamp(0, QA);
+            let black = self.black[i].clamp(0, QA);
+            output += network.output_weights[i] as i32 * (white - black);
+        }
+        (output as ScoreType * SCALE as ScoreType) / (QA * QB) as ScoreType
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use model::chess_type::Type::{Knight, Queen};
+    use model::game::ChessGame;
+    use model::moves::Move;
+    use model::utils::IntoChessPosition;
+    use crate::nnue::{Accumulator, Network, HIDDEN, INPUTS};
+
+    #[test]
+    fn test_write_and_read() {
+        let network = Network::bundled();
+        let mut bytes = vec![];
+        network.write(&mut bytes).unwrap();
+        assert_eq!(*network, Network::read(&mut bytes.as_slice()).unwrap());
+
+        // Any change of the architecture is detected
+        bytes[12] = 64;
+        assert!(Network::read(&mut bytes.as_slice()).is_err());
+        assert!(Network::read(&mut &b"NNUE"[..]).is_err());
+    }
+
+    #[test]
+    fn test_incremental_update() {
+        let network = Network::bundled();
+        let mut game = ChessGame::from_fen("r3k2r/p1pp1pP1/8/3Pp3/8/8/PPP2PPP/R3K2R w KQkq - 0 1");
+        let mut accumulator = Accumulator::new(&network, &game);
+
+        // Castling, en passant, capture with promotion and regular moves
+        let moves = [
+            ("e1", "g1", true),
+            ("c7", "c5", false),
+            ("d5", "c6", true),
+            ("e8", "c8", false),
+            ("g7", "h8", true),
+            ("d8", "d1", false),
+        ];
+        for (from, to, white) in moves {
+            let m = Move::new(from.as_chess_position(), to.as_chess_position(), white);
+            accumulator.update(&network, &game, &m);
+            game.apply_move_unsafe(&m);
+            assert_eq!(Accumulator::new(&network, &game), accumulator);
+        }
+
+        // Underpromotion
+        let mut game = ChessGame::from_fen("1r5k/P7/8/8/8/8/8/K7 w - - 0 1");
+        let mut accumulator = Accumulator::new(&network, &game);
+        let m = Move {
+            underpromotion: Some(Knight),
+            ..Move::from_str("a7", "b8", true)
+        };
+        accumulator.update(&network, &game, &m);
+        game.apply_move_unsafe(&m);
+        assert_eq!(Accumulator::new(&network, &game), accumulator);
+    }
+
+    #[test]
+    fn test_large_weights() {
+        // The weights of all the pieces add up beyond the range of i16
+        let network = Network::from_float(&vec![1000.0; INPUTS * HIDDEN], &[0.0; HIDDEN], &[1.0; HIDDEN]);
+        let mut game = ChessGame::standard_game();
+        let mut accumulator = Accumulator::new(&network, &game);
+        assert_eq!(0, accumulator.evaluate(&network));
+
+        let m = Move::from_str("e2", "e4", true);
+        accumulator.update(&network, &game, &m);
+        game.apply_move_unsafe(&m);
+        assert_eq!(Accumulator::new(&network, &game), accumulator);
+    }
+
+    /// Flips the board vertically and swaps the colors of the pieces
+    fn mirror_fen(board: &str) -> String {
+        let ranks: Vec<String> = board
+            .split('/')
+            .rev()
+            .map(|rank| {
+                rank.chars()
+                    .map(|c| if c.is_uppercase() { c.to_ascii_lowercase() } else { c.to_ascii_uppercase() })
+                    .collect()
+            })
+            .collect();
+        format!("{} w - - 0 1", ranks.join("/"))
+    }
+
+    #[test]
+    fn test_mirrored_positions() {
+        let network = Network::bundled();
+        let board = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5Q2/PPPP1PPP/RNB1K1NR";
+        let game = ChessGame::from_fen(&format!("{board} w - - 0 1"));
+        let mirrored = ChessGame::from_fen(&mirror_fen(board));
+        assert_eq!(network.evaluate(&game), -network.evaluate(&mirrored));
+        assert_eq!(0, network.evaluate(&ChessGame::standard_game()));
+    }
+
+    #[test]
+    fn test_bundled_network_knows_material() {
+        let network = Network::bundled();
+        let mut game = ChessGame::standard_game();
+        let equal = network.evaluate(&game);
+        game.set_piece(Queen, true, "d4");
+        assert!(network.evaluate(&game) > equal + 300);
+    }
+}
@@ -0,0 +1,105 @@
+//! Statistics of a search, to understand where the nodes are spent and how well the moves are
+//! ordered.
+//!
+//! They are only collected when enabled with `AlphaBetaEngine::set_collect_stats`, so that the
+//! usual searches don't pay for them.
+
+/// Counters collected during a search
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    /// Number of nodes visited at each ply, starting with the root
+    pub nodes_per_depth: Vec<u64>,
+    /// Nodes beyond the depth of the search, reached by the extra depth given to captures
+    pub quiescence_nodes: u64,
+    /// Lookups of the transposition table, and the ones which found the position
+    pub tt_probes: u64,
+    pub tt_hits: u64,
+    /// Number of nodes in which a move caused a cutoff
+    pub cutoffs: u64,
+    /// Number of cutoffs caused by the n-th move searched in the node
+    pub cutoffs_per_move_index: Vec<u64>,
+}
+
+/// Increments the counter of the given index, growing the vector when needed
+fn increment(counters: &mut Vec<u64>, index: usize) {
+    if counters.len() <= index {
+        counters.resize(index + 1, 0);
+    }
+    counters[index] += 1;
+}
+
+impl SearchStats {
+    pub(crate) fn add_node(&mut self, depth: usize, is_quiescence: bool) {
+        increment(&mut self.nodes_per_depth, depth);
+        if is_quiescence {
+            self.quiescence_nodes += 1;
+        }
+    }
+
+    pub(crate) fn add_tt_probe(&mut self, hit: bool) {
+        self.tt_probes += 1;
+        if hit {
+            self.tt_hits += 1;
+        }
+    }
+
+    pub(crate) fn add_cutoff(&mut self, move_index: usize) {
+        self.cutoffs += 1;
+        increment(&mut self.cutoffs_per_move_index, move_index);
+    }
+
+    /// Total number of nodes
+    pub fn nodes(&self) -> u64 {
+        self.nodes_per_depth.iter().sum()
+    }
+
+    /// Proportion of the lookups of the transposition table which found the position
+    pub fn tt_hit_rate(&self) -> f64 {
+        ratio(self.tt_hits, self.tt_probes)
+    }
+
+    /// Proportion of the cutoffs caused by the first move: the closer to 1, the better the moves
+    /// are ordered.
+    pub fn first_move_cutoff_rate(&self) -> f64 {
+        ratio(
+            self.cutoffs_per_move_index.first().copied().unwrap_or(0),
+            self.cutoffs,
+        )
+    }
+}
+
+fn ratio(count: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 / total as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stats::SearchStats;
+
+    #[test]
+    fn test_counters() {
+        let mut stats = SearchStats::default();
+        stats.add_node(0, false);
+        stats.add_node(2, true);
+        stats.add_node(2, false);
+        assert_eq!(vec![1, 0, 2], stats.nodes_per_depth);
+        assert_eq!(3, stats.nodes());
+        assert_eq!(1, stats.quiescence_nodes);
+
+        stats.add_tt_probe(true);
+        stats.add_tt_probe(false);
+        assert_eq!(0.5, stats.tt_hit_rate());
+
+        assert_eq!(0.0, stats.first_move_cutoff_rate());
+        stats.add_cutoff(0);
+        stats.add_cutoff(0);
+        stats.add_cutoff(0);
+        stats.add_cutoff(3);
+        assert_eq!(vec![3, 0, 0, 1], stats.cutoffs_per_move_index);
+        assert_eq!(0.75, stats.first_move_cutoff_rate());
+    }
+}
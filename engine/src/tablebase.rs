@@ -0,0 +1,82 @@
+//! Endgame tablebases: databases of the exact results of the positions with few pieces.
+//!
+//! The search asks the tablebases for the result of the positions which have at most
+//! `Tablebase::max_pieces` pieces, instead of evaluating them, and plays the moves that they
+//! advise at the root. The `syzygy` module reads the widespread Syzygy tables, and the `dtm` module
+//! generates and reads our own distance-to-mate tables of the smallest endgames.
+
+use model::chess_type::ScoreType;
+use model::chess_type::Type::{Bishop, King, Knight, Pawn, Queen, Rook};
+use model::game::ChessGame;
+use model::moves::Move;
+
+pub mod dtm;
+pub mod syzygy;
+
+/// Score of a won position found in the tablebases, from which the number of plies from the root
+/// is subtracted to prefer the shortest paths to the tablebases. It is lower than the scores of
+/// the mates found by the search.
+pub const TABLEBASE_WIN_SCORE: ScoreType = 9_000;
+
+/// Result of a position for the side to move.
+///
+/// The cursed wins and blessed losses are the wins and losses which can't be completed before
+/// being drawn by the fifty-move rule.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss = -2,
+    BlessedLoss = -1,
+    Draw = 0,
+    CursedWin = 1,
+    Win = 2,
+}
+
+impl Wdl {
+    pub(crate) fn from_value(value: i32) -> Option<Self> {
+        match value {
+            -2 => Some(Wdl::Loss),
+            -1 => Some(Wdl::BlessedLoss),
+            0 => Some(Wdl::Draw),
+            1 => Some(Wdl::CursedWin),
+            2 => Some(Wdl::Win),
+            _ => None,
+        }
+    }
+
+    /// The result for the other side
+    pub fn opposite(self) -> Self {
+        Self::from_value(-(self as i32)).unwrap()
+    }
+
+    /// Score of the result for the side to move, reached at `ply` plies from the root
+    pub fn score(self, ply: usize) -> ScoreType {
+        match self {
+            Wdl::Win => TABLEBASE_WIN_SCORE - ply as ScoreType,
+            Wdl::Loss => -TABLEBASE_WIN_SCORE + ply as ScoreType,
+            // Draws by the fifty-move rule, which are still better than real draws
+            Wdl::CursedWin => 1,
+            Wdl::BlessedLoss => -1,
+            Wdl::Draw => 0,
+        }
+    }
+}
+
+pub trait Tablebase: Send + Sync {
+    /// Largest number of pieces, kings included, of the positions found in the tables
+    fn max_pieces(&self) -> u32;
+
+    /// Returns the result of the position for the side to move, or None if it isn't in the tables
+    fn probe_wdl(&self, game: &ChessGame) -> Option<Wdl>;
+
+    /// Returns the move which leads to the best result with the shortest path, with the result of
+    /// the position for the side to move, or None if the position isn't in the tables
+    fn best_move(&self, game: &ChessGame) -> Option<(Move, Wdl)>;
+}
+
+/// Returns the number of pieces of the game, kings included
+pub fn piece_count(game: &ChessGame) -> u32 {
+    [Pawn, Knight, Bishop, Rook, Queen, King]
+        .into_iter()
+        .map(|piece| (game.pieces(piece, true) | game.pieces(piece, false)).count_ones())
+        .sum()
+}
@@ -0,0 +1,734 @@
+//! Distance-to-mate tablebases, generated offline by the `generate_tablebases` binary.
+//!
+//! Each material of at most `MAX_PIECES` pieces has a table, which stores the number of plies to
+//! the mate with the best play of both sides, for every position and both sides to move. The
+//! tables are built by retrograde analysis (see the `generator` module) with the moves of the
+//! search and their underpromotions: the castling rights and the en passant captures are ignored.
+//!
+//! Only one table is stored for the two colourings of a material: the one with the strongest side
+//! playing white (`KQvKR`, not `KRvKQ`). The white king is moved by symmetry to the a1-d1-d4
+//! triangle, or to the files a to d when there are pawns.
+//!
+//! File format (`<material>.dtm`): the magic bytes `CDTM`, the length of the name of the material
+//! and its bytes, the number of positions (u32, little-endian), and the values of the positions
+//! encoded in runs: the length of the run (LEB128) followed by the value (one byte). A value is 0
+//! for a draw, 255 for an illegal position, and otherwise the number of plies to the mate plus
+//! one: the side to move wins when this number is odd, and loses when it is even.
+
+use crate::tablebase::{Tablebase, Wdl};
+use model::chess_type::Type::{self, Bishop, King, Knight, Pawn, Queen, Rook};
+use model::game::ChessGame;
+use model::moves::Move;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+pub mod generator;
+
+pub const EXTENSION: &str = "dtm";
+
+/// Largest number of pieces of the tables, kings included
+pub const MAX_PIECES: u32 = 4;
+
+const MAGIC: &[u8; 4] = b"CDTM";
+
+const DRAW: u8 = 0;
+const ILLEGAL: u8 = 255;
+
+/// Pieces other than the kings, from the strongest to the weakest
+const PIECES: [Type; 5] = [Queen, Rook, Bishop, Knight, Pawn];
+
+/// Squares of the a1-d1-d4 triangle
+const TRIANGLE: [usize; 10] = [0, 1, 2, 3, 9, 10, 11, 18, 19, 27];
+
+/// Result of a position for the side to move, with the number of plies to the mate
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Dtm {
+    Win(u32),
+    Draw,
+    Loss(u32),
+}
+
+impl Dtm {
+    fn from_value(value: u8) -> Option<Self> {
+        match value {
+            ILLEGAL => None,
+            DRAW => Some(Dtm::Draw),
+            value => {
+                let plies = value as u32 - 1;
+                Some(if plies % 2 == 1 {
+                    Dtm::Win(plies)
+                } else {
+                    Dtm::Loss(plies)
+                })
+            }
+        }
+    }
+
+    fn value(self) -> u8 {
+        match self {
+            Dtm::Draw => DRAW,
+            Dtm::Win(plies) | Dtm::Loss(plies) => plies as u8 + 1,
+        }
+    }
+
+    pub fn wdl(self) -> Wdl {
+        match self {
+            Dtm::Win(_) => Wdl::Win,
+            Dtm::Draw => Wdl::Draw,
+            Dtm::Loss(_) => Wdl::Loss,
+        }
+    }
+
+    /// Result of the position from which the move leading to this one was played
+    pub fn before_move(self) -> Self {
+        match self {
+            Dtm::Win(plies) => Dtm::Loss(plies + 1),
+            Dtm::Draw => Dtm::Draw,
+            Dtm::Loss(plies) => Dtm::Win(plies + 1),
+        }
+    }
+
+    /// Orders the results: the fastest wins first, and the slowest losses last
+    fn rank(self) -> (i32, i64) {
+        match self {
+            Dtm::Win(plies) => (1, -(plies as i64)),
+            Dtm::Draw => (0, 0),
+            Dtm::Loss(plies) => (-1, plies as i64),
+        }
+    }
+}
+
+/// Pieces of each side other than the kings, counted by type in the order of `PIECES`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Material {
+    counts: [[u8; 5]; 2],
+}
+
+impl Material {
+    /// Parses a name such as `KRPvKR`
+    pub fn parse(name: &str) -> Option<Self> {
+        let (white, black) = name.split_once('v')?;
+        let mut counts = [[0; 5]; 2];
+        for (side, pieces) in [white, black].into_iter().enumerate() {
+            let pieces = pieces.strip_prefix('K')?;
+            for c in pieces.chars() {
+                let piece = "QRBNP".find(c)?;
+                counts[side][piece] += 1;
+            }
+        }
+        Some(Self { counts })
+    }
+
+    pub fn of_game(game: &ChessGame) -> Self {
+        let mut counts = [[0; 5]; 2];
+        for (side, white) in [true, false].into_iter().enumerate() {
+            for (i, piece) in PIECES.into_iter().enumerate() {
+                counts[side][i] = game.pieces(piece, white).count_ones() as u8;
+            }
+        }
+        Self { counts }
+    }
+
+    pub fn name(&self) -> String {
+        let side = |counts: &[u8; 5]| -> String {
+            let pieces: String = "QRBNP"
+                .chars()
+                .zip(counts)
+                .flat_map(|(c, &n)| std::iter::repeat_n(c, n as usize))
+                .collect();
+            format!("K{pieces}")
+        };
+        format!("{}v{}", side(&self.counts[0]), side(&self.counts[1]))
+    }
+
+    /// The same material with the colours swapped
+    pub fn swapped(&self) -> Self {
+        Self {
+            counts: [self.counts[1], self.counts[0]],
+        }
+    }
+
+    /// True if white is the strongest side, which is how the tables are stored
+    pub fn is_canonical(&self) -> bool {
+        let strength = |counts: &[u8; 5]| (counts.iter().sum::<u8>(), *counts);
+        strength(&self.counts[0]) >= strength(&self.counts[1])
+    }
+
+    pub fn canonical(&self) -> Self {
+        if self.is_canonical() {
+            *self
+        } else {
+            self.swapped()
+        }
+    }
+
+    pub fn piece_count(&self) -> u32 {
+        2 + self.counts.iter().flatten().map(|&n| n as u32).sum::<u32>()
+    }
+
+    fn has_pawns(&self) -> bool {
+        self.counts[0][4] + self.counts[1][4] > 0
+    }
+
+    /// Returns all the materials with 2 to `max_pieces` pieces, white being the strongest side
+    pub fn all(max_pieces: u32) -> Vec<Self> {
+        let mut materials = vec![Self {
+            counts: [[0; 5]; 2],
+        }];
+        let mut last = materials.clone();
+        for _ in 2..max_pieces {
+            let mut next: Vec<Self> = vec![];
+            for material in &last {
+                for side in 0..2 {
+                    for piece in 0..5 {
+                        let mut counts = material.counts;
+                        counts[side][piece] += 1;
+                        let added = Self { counts }.canonical();
+                        if !next.contains(&added) {
+                            next.push(added);
+                        }
+                    }
+                }
+            }
+            materials.extend_from_slice(&next);
+            last = next;
+        }
+        materials
+    }
+
+    /// Materials reached by a capture or a promotion
+    pub fn successors(&self) -> Vec<Self> {
+        let mut successors: Vec<Self> = vec![];
+        for side in 0..2 {
+            for piece in 0..5 {
+                if self.counts[side][piece] == 0 {
+                    continue;
+                }
+                let mut captured = self.counts;
+                captured[side][piece] -= 1;
+                let mut changed = vec![captured];
+                if PIECES[piece] == Pawn {
+                    // The pawn promotes to a queen, a rook, a bishop or a knight
+                    for promotion in 0..4 {
+                        let mut promoted = captured;
+                        promoted[side][promotion] += 1;
+                        changed.push(promoted);
+                    }
+                }
+                for counts in changed {
+                    let material = Self { counts }.canonical();
+                    if !successors.contains(&material) {
+                        successors.push(material);
+                    }
+                }
+            }
+        }
+        successors
+    }
+
+    /// Pieces in the order of the index: the white king, the black king, the white pieces and the
+    /// black pieces
+    fn pieces(&self) -> impl Iterator<Item = (Type, bool)> + '_ {
+        let others = [true, false]
+            .into_iter()
+            .enumerate()
+            .flat_map(move |(side, white)| {
+                PIECES
+                    .into_iter()
+                    .zip(self.counts[side])
+                    .flat_map(move |(piece, n)| std::iter::repeat_n((piece, white), n as usize))
+            });
+        [(King, true), (King, false)].into_iter().chain(others)
+    }
+
+    /// Number of squares of the white king after the symmetries
+    fn king_squares(&self) -> usize {
+        if self.has_pawns() {
+            32
+        } else {
+            TRIANGLE.len()
+        }
+    }
+
+    /// Number of positions of the table
+    pub fn size(&self) -> usize {
+        2 * self.king_squares() * 64usize.pow(self.piece_count() - 1)
+    }
+
+    /// Index of a position which has this material. The symmetric positions have the same index.
+    fn index(&self, game: &ChessGame, white_to_play: bool) -> usize {
+        // Symmetries which move the white king to its part of the board
+        let king = game.pieces(King, true).trailing_zeros() as usize;
+        let mirror_file = king % 8 > 3;
+        let squares = if self.has_pawns() {
+            self.transformed_squares(game, mirror_file, false, false)
+        } else {
+            let mirror_rank = king / 8 > 3;
+            let file = if mirror_file { 7 - king % 8 } else { king % 8 };
+            let rank = if mirror_rank { 7 - king / 8 } else { king / 8 };
+            let squares = self.transformed_squares(game, mirror_file, mirror_rank, rank > file);
+            if rank == file {
+                // The king is on the diagonal, which doesn't tell whether to transpose
+                let transposed = self.transformed_squares(game, mirror_file, mirror_rank, true);
+                squares.min(transposed)
+            } else {
+                squares
+            }
+        };
+
+        let king = squares[0];
+        let king_index = if self.has_pawns() {
+            (king / 8) * 4 + king % 8
+        } else {
+            TRIANGLE.iter().position(|&square| square == king).unwrap()
+        };
+        let side_to_move = if white_to_play { 0 } else { 1 };
+        squares[1..self.piece_count() as usize].iter().fold(
+            side_to_move * self.king_squares() + king_index,
+            |index, &square| index * 64 + square,
+        )
+    }
+
+    /// Squares of the pieces after a symmetry, in the order of `pieces`. The identical pieces are
+    /// sorted by square.
+    fn transformed_squares(
+        &self,
+        game: &ChessGame,
+        mirror_file: bool,
+        mirror_rank: bool,
+        transpose: bool,
+    ) -> [usize; MAX_PIECES as usize] {
+        let mut squares = [0; MAX_PIECES as usize];
+        let mut previous = None;
+        let mut bitboard = 0;
+        for ((piece, white), square) in self.pieces().zip(&mut squares) {
+            if previous != Some((piece, white)) {
+                previous = Some((piece, white));
+                bitboard = squares_of(game.pieces(piece, white))
+                    .map(|square| transform(square, mirror_file, mirror_rank, transpose))
+                    .fold(0u64, |bitboard, square| bitboard | 1 << square);
+            }
+            *square = bitboard.trailing_zeros() as usize;
+            bitboard &= bitboard - 1;
+        }
+        squares
+    }
+
+    /// Returns the position of an index, or None if it is illegal or if its symmetric positions
+    /// have another index
+    fn position(&self, index: usize) -> Option<(ChessGame, bool)> {
+        let (squares, white_to_play) = self.squares(index);
+        let mut game = self.game(&squares)?;
+        game.set_white_to_play(white_to_play);
+        // The king of the side which just played can't be in check
+        (!game.is_in_check(!white_to_play) && self.index(&game, white_to_play) == index)
+            .then_some((game, white_to_play))
+    }
+
+    /// Returns the squares of the pieces of an index, in the order of `pieces`, and the side to move
+    fn squares(&self, mut index: usize) -> (Vec<usize>, bool) {
+        let mut squares = vec![0; self.piece_count() as usize];
+        for square in squares[1..].iter_mut().rev() {
+            *square = index % 64;
+            index /= 64;
+        }
+        let king_index = index % self.king_squares();
+        squares[0] = if self.has_pawns() {
+            (king_index / 4) * 8 + king_index % 4
+        } else {
+            TRIANGLE[king_index]
+        };
+        (squares, index / self.king_squares() == 0)
+    }
+
+    /// Places the pieces on the given squares, in the order of `pieces`. Returns None if two pieces
+    /// are on the same square or if a pawn is on the first or the last rank.
+    fn game(&self, squares: &[usize]) -> Option<ChessGame> {
+        let mut bitboards = [0u64; 6];
+        let mut whites = 0;
+        let mut occupied = 0;
+        for ((piece, white), &square) in self.pieces().zip(squares) {
+            let bit = 1 << square;
+            if occupied & bit != 0 || (piece == Pawn && !(8..56).contains(&square)) {
+                return None;
+            }
+            occupied |= bit;
+            bitboards[piece_code(piece) as usize] |= bit;
+            if white {
+                whites |= bit;
+            }
+        }
+        let [pawns, bishops, knights, rooks, queens, kings] = bitboards;
+        let mut game = ChessGame::new(whites, pawns, bishops, knights, rooks, queens, kings, 0);
+        game.block_castling();
+        Some(game)
+    }
+}
+
+/// Index of the piece in the bitboards of `ChessGame::new`
+fn piece_code(piece: Type) -> u8 {
+    match piece {
+        Pawn => 0,
+        Bishop => 1,
+        Knight => 2,
+        Rook => 3,
+        Queen => 4,
+        King => 5,
+    }
+}
+
+fn squares_of(mut bitboard: u64) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        (bitboard != 0).then(|| {
+            let square = bitboard.trailing_zeros() as usize;
+            bitboard &= bitboard - 1;
+            square
+        })
+    })
+}
+
+fn transform(square: usize, mirror_file: bool, mirror_rank: bool, transpose: bool) -> usize {
+    let mut file = square % 8;
+    let mut rank = square / 8;
+    if mirror_file {
+        file = 7 - file;
+    }
+    if mirror_rank {
+        rank = 7 - rank;
+    }
+    if transpose {
+        std::mem::swap(&mut file, &mut rank);
+    }
+    rank * 8 + file
+}
+
+/// The same position with the colours swapped and the board flipped vertically
+fn flipped(game: &ChessGame) -> ChessGame {
+    let bitboard = |piece| (game.pieces(piece, true) | game.pieces(piece, false)).swap_bytes();
+    let blacks = [Pawn, Bishop, Knight, Rook, Queen, King]
+        .into_iter()
+        .fold(0, |blacks, piece| blacks | game.pieces(piece, false));
+    let mut flipped = ChessGame::new(
+        blacks.swap_bytes(),
+        bitboard(Pawn),
+        bitboard(Bishop),
+        bitboard(Knight),
+        bitboard(Rook),
+        bitboard(Queen),
+        bitboard(King),
+        0,
+    );
+    flipped.block_castling();
+    flipped.set_white_to_play(!game.white_to_play());
+    flipped
+}
+
+/// The values of all the positions of a material
+pub struct Table {
+    material: Material,
+    values: Vec<u8>,
+}
+
+impl Table {
+    pub fn material(&self) -> Material {
+        self.material
+    }
+
+    /// Largest number of plies to a mate of the table
+    pub fn max_plies(&self) -> u32 {
+        self.values
+            .iter()
+            .filter(|&&value| value != ILLEGAL)
+            .map(|&value| value.saturating_sub(1) as u32)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Result of a position which has the material of the table, with the same colours
+    fn probe(&self, game: &ChessGame) -> Option<Dtm> {
+        Dtm::from_value(self.values[self.material.index(game, game.white_to_play())])
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let name = self.material.name();
+        let mut data = MAGIC.to_vec();
+        data.push(name.len() as u8);
+        data.extend_from_slice(name.as_bytes());
+        data.extend_from_slice(&(self.values.len() as u32).to_le_bytes());
+        for run in self.values.chunk_by(|a, b| a == b) {
+            let mut length = run.len();
+            while length >= 0x80 {
+                data.push((length & 0x7F) as u8 | 0x80);
+                length >>= 7;
+            }
+            data.push(length as u8);
+            data.push(run[0]);
+        }
+        std::fs::write(path, data)
+    }
+
+    pub fn read(path: &Path) -> io::Result<Self> {
+        Self::parse(&std::fs::read(path)?).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid tablebase file {}", path.display()),
+            )
+        })
+    }
+
+    fn parse(data: &[u8]) -> Option<Self> {
+        let data = data.strip_prefix(MAGIC)?;
+        let (&name_length, data) = data.split_first()?;
+        let (name, data) = data.split_at_checked(name_length as usize)?;
+        let material = Material::parse(std::str::from_utf8(name).ok()?)?;
+        let (size, mut data) = data.split_first_chunk::<4>()?;
+        let size = u32::from_le_bytes(*size) as usize;
+        if size != material.size() {
+            return None;
+        }
+
+        let mut values = Vec::with_capacity(size);
+        while !data.is_empty() {
+            let mut length = 0;
+            let mut shift = 0;
+            loop {
+                let (&byte, rest) = data.split_first()?;
+                data = rest;
+                length |= ((byte & 0x7F) as usize).checked_shl(shift)?;
+                shift += 7;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+            }
+            let (&value, rest) = data.split_first()?;
+            data = rest;
+            if values.len() + length > size {
+                return None;
+            }
+            values.extend(std::iter::repeat_n(value, length));
+        }
+        (values.len() == size).then_some(Self { material, values })
+    }
+}
+
+/// Probes a position in the tables given by `table_of`, swapping its colours if needed
+fn probe<'a>(
+    table_of: impl Fn(&Material) -> Option<&'a Table>,
+    game: &ChessGame,
+) -> Option<Dtm> {
+    let material = Material::of_game(game);
+    if material.is_canonical() {
+        table_of(&material)?.probe(game)
+    } else {
+        table_of(&material.swapped())?.probe(&flipped(game))
+    }
+}
+
+/// A table file, read at its first probe
+struct TableFile {
+    path: PathBuf,
+    table: OnceLock<Option<Table>>,
+}
+
+/// The tables of a set of directories
+pub struct DtmTablebase {
+    files: HashMap<Material, TableFile>,
+    max_pieces: u32,
+}
+
+impl DtmTablebase {
+    /// Finds the tables of the given directories, separated by `:` (or `;` on Windows)
+    pub fn open(paths: &str) -> io::Result<Self> {
+        let mut files = HashMap::new();
+        for directory in std::env::split_paths(paths) {
+            for entry in std::fs::read_dir(directory)? {
+                let path = entry?.path();
+                if path.extension().and_then(|extension| extension.to_str()) != Some(EXTENSION) {
+                    continue;
+                }
+                let Some(material) = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(Material::parse)
+                else {
+                    continue;
+                };
+                files.insert(
+                    material,
+                    TableFile {
+                        path,
+                        table: OnceLock::new(),
+                    },
+                );
+            }
+        }
+
+        let max_pieces = files
+            .keys()
+            .map(Material::piece_count)
+            .max()
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("No DTM tables found in {paths}"),
+                )
+            })?;
+        Ok(Self { files, max_pieces })
+    }
+
+    fn table(&self, material: &Material) -> Option<&Table> {
+        let file = self.files.get(material)?;
+        file.table
+            .get_or_init(|| Table::read(&file.path).ok())
+            .as_ref()
+    }
+
+    /// Returns the result of the position with the number of plies to the mate, or None if the
+    /// position isn't in the tables
+    pub fn probe_dtm(&self, game: &ChessGame) -> Option<Dtm> {
+        if game.castling_rights().contains(&true) || game.en_passant_square().is_some() {
+            return None;
+        }
+        probe(|material| self.table(material), game)
+    }
+}
+
+impl Tablebase for DtmTablebase {
+    fn max_pieces(&self) -> u32 {
+        self.max_pieces
+    }
+
+    fn probe_wdl(&self, game: &ChessGame) -> Option<Wdl> {
+        self.probe_dtm(game).map(Dtm::wdl)
+    }
+
+    /// Plays the fastest mate when winning, and the slowest one when losing
+    fn best_move(&self, game: &ChessGame) -> Option<(Move, Wdl)> {
+        let mut best: Option<(Move, Dtm)> = None;
+        let moves = game.legal_moves(game.white_to_play());
+        for m in moves.into_iter().flat_map(|m| game.with_underpromotions(m)) {
+            let mut new_game = *game;
+            new_game.apply_move_unsafe(&m);
+            // The tables ignore the en passant captures, which a double push may allow
+            let dtm = probe(|material| self.table(material), &new_game)?.before_move();
+            if best.is_none_or(|(_, best_dtm)| dtm.rank() > best_dtm.rank()) {
+                best = Some((m, dtm));
+            }
+        }
+        best.map(|(m, dtm)| (m, dtm.wdl()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tablebase::dtm::generator::generate;
+    use crate::tablebase::dtm::{flipped, Dtm, DtmTablebase, Material, Table, EXTENSION};
+    use crate::tablebase::{Tablebase, Wdl};
+    use model::game::ChessGame;
+    use model::moves::Move;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_material() {
+        let material = Material::parse("KRPvKQ").unwrap();
+        assert_eq!("KRPvKQ", material.name());
+        assert_eq!(5, material.piece_count());
+        assert!(material.is_canonical());
+        assert_eq!("KQvKRP", material.swapped().name());
+        assert!(!material.swapped().is_canonical());
+        assert!(Material::parse("KQvKR").unwrap().is_canonical());
+        assert!(!Material::parse("KNvKB").unwrap().is_canonical());
+        assert_eq!(None, Material::parse("KQK"));
+        assert_eq!(None, Material::parse("QvK"));
+
+        let game = ChessGame::from_fen("8/8/4k3/3q4/8/2K5/3P4/8 w - - 0 1");
+        assert_eq!("KPvKQ", Material::of_game(&game).name());
+
+        let successors: Vec<String> = Material::parse("KPvKN")
+            .unwrap()
+            .successors()
+            .iter()
+            .map(Material::name)
+            .collect();
+        assert_eq!(
+            vec!["KNvK", "KQvKN", "KRvKN", "KBvKN", "KNvKN", "KPvK"],
+            successors
+        );
+
+        // KvK, 5 materials with 3 pieces, and 15 + 15 materials with 4 pieces
+        let all = Material::all(4);
+        assert_eq!(1 + 5 + 30, all.len());
+        assert!(all.iter().all(Material::is_canonical));
+    }
+
+    #[test]
+    fn test_index() {
+        for name in ["KQvK", "KPvK", "KRvKN", "KNNvK"] {
+            let material = Material::parse(name).unwrap();
+            let mut legal = 0;
+            for index in (0..material.size()).step_by(7) {
+                if let Some((game, white_to_play)) = material.position(index) {
+                    assert_eq!(index, material.index(&game, white_to_play));
+                    legal += 1;
+                }
+            }
+            assert!(legal > 0);
+        }
+
+        // The symmetric positions have the same index
+        let material = Material::parse("KRvKN").unwrap();
+        let index = material.index(
+            &ChessGame::from_fen("8/8/4k3/8/2n5/8/1R6/K7 w - - 0 1"),
+            true,
+        );
+        for fen in [
+            "8/8/3k4/8/5n2/8/6R1/7K w - - 0 1",
+            "K7/1R6/8/2n5/8/4k3/8/8 w - - 0 1",
+        ] {
+            assert_eq!(index, material.index(&ChessGame::from_fen(fen), true));
+        }
+        // The same position with the colours swapped
+        let game = flipped(&ChessGame::from_fen("k7/1r6/8/2N5/8/4K3/8/8 b - - 0 1"));
+        assert_eq!(index, material.index(&game, game.white_to_play()));
+    }
+
+    #[test]
+    fn test_file() {
+        let mut tables = HashMap::new();
+        generate(&Material::parse("KQvK").unwrap(), &mut tables);
+        assert_eq!(2, tables.len());
+
+        let directory = std::env::temp_dir().join(format!("dtm_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        for table in tables.values() {
+            let path = directory.join(format!("{}.{EXTENSION}", table.material().name()));
+            table.write(&path).unwrap();
+            let read = Table::read(&path).unwrap();
+            assert_eq!(table.material(), read.material());
+            assert!(table.values == read.values);
+        }
+        assert!(Table::parse(b"CDTM\x04KQvK\x00").is_none());
+
+        let tablebase = DtmTablebase::open(directory.to_str().unwrap()).unwrap();
+        assert_eq!(3, tablebase.max_pieces());
+
+        // The queen mates in h8
+        let game = ChessGame::from_fen("k7/8/1K6/8/8/8/7Q/8 w - - 0 1");
+        assert_eq!(Some(Dtm::Win(1)), tablebase.probe_dtm(&game));
+        let (m, wdl) = tablebase.best_move(&game).unwrap();
+        assert_eq!(Wdl::Win, wdl);
+        assert_eq!(Move::from_str("h2", "h8", true), m);
+        // The same position with the colours swapped
+        let game = ChessGame::from_fen("8/7q/8/8/8/1k6/8/K7 b - - 0 1");
+        assert_eq!(Some(Dtm::Win(1)), tablebase.probe_dtm(&game));
+        assert_eq!(Wdl::Win, tablebase.probe_wdl(&game).unwrap());
+        // There is no table of KRvK
+        let game = ChessGame::from_fen("k7/8/1K6/8/8/8/7R/8 w - - 0 1");
+        assert_eq!(None, tablebase.probe_dtm(&game));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+        assert!(DtmTablebase::open(directory.to_str().unwrap()).is_err());
+    }
+}
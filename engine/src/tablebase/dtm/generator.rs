@@ -0,0 +1,336 @@
+//! Generation of the distance-to-mate tables by retrograde analysis.
+//!
+//! The tables of the materials reached by a capture or a promotion are generated first. The moves
+//! of every position are then played once: the checkmates and the stalemates are resolved, the
+//! moves which lead to the other tables are resolved with these tables, and the moves within the
+//! material are counted. The positions are then resolved ply after ply by unmaking the moves of
+//! the positions resolved at the previous ply: the positions from which a lost position can be
+//! reached are won, and the positions whose moves all lead to won positions are lost. The positions
+//! which are never resolved are draws.
+
+use crate::tablebase::dtm::{probe, squares_of, Dtm, Material, Table, ILLEGAL};
+use model::chess_type::Type::Pawn;
+use model::game::ChessGame;
+use model::moves_container::SimpleMovesContainer;
+use std::collections::HashMap;
+
+/// Generates the table of a material and the tables that it depends on, unless they are already
+/// in `tables`
+pub fn generate(material: &Material, tables: &mut HashMap<Material, Table>) {
+    let material = material.canonical();
+    if tables.contains_key(&material) {
+        return;
+    }
+    for successor in material.successors() {
+        generate(&successor, tables);
+    }
+
+    let mut generator = Generator::new(material, tables);
+    let mut plies: u32 = 1;
+    while (plies as usize) < generator.pending.len()
+        || !generator.resolved_at_previous_ply.is_empty()
+    {
+        generator.resolve(plies);
+        plies += 1;
+    }
+    let table = generator.into_table();
+    tables.insert(material, table);
+}
+
+/// Positions resolved at a ply thanks to the tables of the other materials
+#[derive(Default)]
+struct Pending {
+    /// Positions with a capture or a promotion which leads to a lost position
+    wins: Vec<usize>,
+    /// Positions whose captures and promotions all lead to won positions, the longest being
+    /// resolved at the previous ply
+    captures_won: Vec<usize>,
+}
+
+struct Generator<'a> {
+    material: Material,
+    tables: &'a HashMap<Material, Table>,
+    values: Vec<u8>,
+    resolved: Vec<bool>,
+    /// Number of moves of each position which aren't known to lead to won positions, the captures
+    /// and the promotions counting as one
+    remaining: Vec<u8>,
+    /// Positions resolved by the other tables, by ply
+    pending: Vec<Pending>,
+    resolved_at_previous_ply: Vec<usize>,
+}
+
+impl<'a> Generator<'a> {
+    /// Plays the moves of all the positions
+    fn new(material: Material, tables: &'a HashMap<Material, Table>) -> Self {
+        let size = material.size();
+        let mut generator = Self {
+            material,
+            tables,
+            values: vec![ILLEGAL; size],
+            resolved: vec![true; size],
+            remaining: vec![0; size],
+            pending: vec![],
+            resolved_at_previous_ply: vec![],
+        };
+        for index in 0..size {
+            if let Some((game, white_to_play)) = material.position(index) {
+                generator.play_moves(index, &game, white_to_play);
+            }
+        }
+        generator
+    }
+
+    fn play_moves(&mut self, index: usize, game: &ChessGame, white_to_play: bool) {
+        let moves: Vec<_> = game
+            .legal_moves(white_to_play)
+            .into_iter()
+            .flat_map(|m| game.with_underpromotions(m))
+            .collect();
+        self.values[index] = Dtm::Draw.value();
+        if moves.is_empty() {
+            if game.is_in_check(white_to_play) {
+                self.values[index] = Dtm::Loss(0).value();
+                self.resolved_at_previous_ply.push(index);
+            }
+            return;
+        }
+
+        let mut children = vec![];
+        let mut captures = false;
+        let mut shortest_loss = None;
+        let mut longest_win = Some(0);
+        for m in moves {
+            let mut new_game = *game;
+            new_game.apply_move_unsafe(&m);
+            if Material::of_game(&new_game) == self.material {
+                children.push(self.material.index(&new_game, !white_to_play));
+                continue;
+            }
+            captures = true;
+            match probe(|material| self.tables.get(material), &new_game) {
+                Some(Dtm::Loss(plies)) => {
+                    shortest_loss = Some(shortest_loss.map_or(plies, |n: u32| n.min(plies)));
+                }
+                Some(Dtm::Win(plies)) => longest_win = longest_win.map(|n: u32| n.max(plies)),
+                _ => longest_win = None,
+            }
+        }
+        // Several moves may lead to the same position, up to a symmetry
+        children.sort_unstable();
+        children.dedup();
+
+        self.resolved[index] = false;
+        self.remaining[index] = children.len() as u8 + captures as u8;
+        if let Some(plies) = shortest_loss {
+            self.pending_at(plies as usize + 1).wins.push(index);
+        } else if let (true, Some(plies)) = (captures, longest_win) {
+            self.pending_at(plies as usize + 1).captures_won.push(index);
+        }
+    }
+
+    fn pending_at(&mut self, plies: usize) -> &mut Pending {
+        if self.pending.len() <= plies {
+            self.pending.resize_with(plies + 1, Pending::default);
+        }
+        &mut self.pending[plies]
+    }
+
+    /// Resolves the positions won or lost in `plies` plies
+    fn resolve(&mut self, plies: u32) {
+        let previous = std::mem::take(&mut self.resolved_at_previous_ply);
+        for index in previous {
+            let lost = matches!(Dtm::from_value(self.values[index]), Some(Dtm::Loss(_)));
+            for predecessor in self.predecessors(index) {
+                if lost {
+                    self.resolve_win(predecessor, plies);
+                } else {
+                    self.resolve_child_won(predecessor, plies);
+                }
+            }
+        }
+
+        let pending = self
+            .pending
+            .get_mut(plies as usize)
+            .map(std::mem::take)
+            .unwrap_or_default();
+        for index in pending.wins {
+            self.resolve_win(index, plies);
+        }
+        for index in pending.captures_won {
+            self.resolve_child_won(index, plies);
+        }
+    }
+
+    fn resolve_win(&mut self, index: usize, plies: u32) {
+        if !self.resolved[index] {
+            self.set(index, Dtm::Win(plies));
+        }
+    }
+
+    fn resolve_child_won(&mut self, index: usize, plies: u32) {
+        if !self.resolved[index] {
+            self.remaining[index] -= 1;
+            if self.remaining[index] == 0 {
+                self.set(index, Dtm::Loss(plies));
+            }
+        }
+    }
+
+    fn set(&mut self, index: usize, dtm: Dtm) {
+        self.values[index] = dtm.value();
+        self.resolved[index] = true;
+        self.resolved_at_previous_ply.push(index);
+    }
+
+    /// Returns the positions of the material from which a move leads to the position of an index
+    fn predecessors(&self, index: usize) -> Vec<usize> {
+        let (squares, white_to_play) = self.material.squares(index);
+        let game = self.material.game(&squares).unwrap();
+        // The side which just played
+        let white = !white_to_play;
+        let occupied = squares
+            .iter()
+            .fold(0u64, |occupied, square| occupied | 1 << square);
+
+        // The pieces other than the pawns come back by the moves which don't capture
+        let mut container = SimpleMovesContainer::new();
+        game.update_move_container(&mut container, white);
+        let mut moves: Vec<(usize, usize)> = container
+            .moves
+            .iter()
+            .map(|m| (m.from as usize, m.to as usize))
+            .filter(|&(from, to)| {
+                occupied & 1 << to == 0 && game.pieces(Pawn, white) & 1 << from == 0
+            })
+            .collect();
+        // The pawns come back by one square, or two from the fourth rank
+        for square in squares_of(game.pieces(Pawn, white)) {
+            let (behind, twice_behind, double_push) = if white {
+                (square - 8, square.wrapping_sub(16), square / 8 == 3)
+            } else {
+                (square + 8, square + 16, square / 8 == 4)
+            };
+            if !(8..56).contains(&behind) || occupied & 1 << behind != 0 {
+                continue;
+            }
+            moves.push((square, behind));
+            if double_push && occupied & 1 << twice_behind == 0 {
+                moves.push((square, twice_behind));
+            }
+        }
+
+        let mut predecessors: Vec<usize> = moves
+            .into_iter()
+            .filter_map(|(from, to)| {
+                let mut squares = squares.clone();
+                let piece = squares.iter().position(|&square| square == from).unwrap();
+                squares[piece] = to;
+                let predecessor = self.material.game(&squares)?;
+                // The side to move of the position can't be in check before the move
+                (!predecessor.is_in_check(white_to_play))
+                    .then(|| self.material.index(&predecessor, white))
+            })
+            .collect();
+        predecessors.sort_unstable();
+        predecessors.dedup();
+        predecessors
+    }
+
+    fn into_table(self) -> Table {
+        Table {
+            material: self.material,
+            values: self.values,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::endgame::kpk;
+    use crate::tablebase::dtm::generator::generate;
+    use crate::tablebase::dtm::{probe, Dtm, Material, Table};
+    use model::chess_type::Type::{King, Pawn};
+    use model::game::ChessGame;
+    use std::collections::HashMap;
+
+    /// Checks that the result of every position is the best result of its moves
+    fn assert_consistent(material: &Material, tables: &HashMap<Material, Table>) {
+        let result = |game: &ChessGame| probe(|material| tables.get(material), game).unwrap();
+        for index in 0..material.size() {
+            let Some((game, white_to_play)) = material.position(index) else {
+                continue;
+            };
+            let moves: Vec<_> = game
+                .legal_moves(white_to_play)
+                .into_iter()
+                .flat_map(|m| game.with_underpromotions(m))
+                .collect();
+            let expected = if moves.is_empty() && game.is_in_check(white_to_play) {
+                Dtm::Loss(0)
+            } else {
+                moves
+                    .iter()
+                    .map(|m| {
+                        let mut new_game = game;
+                        new_game.apply_move_unsafe(m);
+                        result(&new_game).before_move()
+                    })
+                    .max_by_key(|dtm| dtm.rank())
+                    .unwrap_or(Dtm::Draw)
+            };
+            assert_eq!(expected, result(&game), "{}", game.to_fen());
+        }
+    }
+
+    #[test]
+    fn test_king_and_queen() {
+        let mut tables = HashMap::new();
+        generate(&Material::parse("KQvK").unwrap(), &mut tables);
+        let result = |fen: &str| probe(|material| tables.get(material), &ChessGame::from_fen(fen));
+
+        assert_eq!(Some(Dtm::Win(1)), result("k7/8/1K6/8/8/8/7Q/8 w - - 0 1"));
+        assert_eq!(Some(Dtm::Loss(0)), result("k6Q/8/1K6/8/8/8/8/8 b - - 0 1"));
+        assert_eq!(Some(Dtm::Draw), result("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1"));
+        // The king takes the queen
+        assert_eq!(Some(Dtm::Draw), result("k7/1Q6/8/8/8/8/8/7K b - - 0 1"));
+        // The same positions with the colours swapped
+        assert_eq!(Some(Dtm::Win(1)), result("8/7q/8/8/8/1k6/8/K7 b - - 0 1"));
+        assert_eq!(Some(Dtm::Loss(0)), result("8/8/8/8/8/1k6/8/K6q w - - 0 1"));
+
+        // The longest mate with a queen takes 10 moves
+        let table = &tables[&Material::parse("KQvK").unwrap()];
+        assert_eq!(20, table.max_plies());
+        assert_eq!(0, tables[&Material::parse("KvK").unwrap()].max_plies());
+        assert_consistent(&Material::parse("KQvK").unwrap(), &tables);
+    }
+
+    #[test]
+    fn test_king_and_pawn() {
+        let material = Material::parse("KPvK").unwrap();
+        let mut tables = HashMap::new();
+        generate(&material, &mut tables);
+        assert_consistent(&material, &tables);
+
+        // The bitbase of the endgame evaluation agrees with the table, including the positions where
+        // the promotion to a queen stalemates the weak king and only an underpromotion wins
+        for index in 0..material.size() {
+            let Some((game, white_to_play)) = material.position(index) else {
+                continue;
+            };
+            let dtm = probe(|material| tables.get(material), &game).unwrap();
+            let square = |piece, white| game.pieces(piece, white).trailing_zeros() as usize;
+            let bitbase = kpk::probe(
+                square(King, true),
+                square(Pawn, true),
+                square(King, false),
+                white_to_play,
+            );
+            assert_eq!(bitbase, dtm != Dtm::Draw, "{}", game.to_fen());
+        }
+        let result = |fen: &str| probe(|material| tables.get(material), &ChessGame::from_fen(fen));
+        // b8=R wins, b8=Q stalemates
+        assert!(matches!(result("8/1P6/k7/8/1K6/8/8/8 w - - 0 1"), Some(Dtm::Win(_))));
+    }
+}
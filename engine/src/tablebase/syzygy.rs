@@ -0,0 +1,753 @@
+//! Syzygy endgame tablebases.
+//!
+//! Each material has a WDL file (`.rtbw`), which stores the result of its positions, and a DTZ
+//! file (`.rtbz`), which stores the distance to the next capture or pawn move that keeps the
+//! result ("distance to zeroing"). The tables don't store the positions whose best move is a
+//! capture, so the probes search the captures first, as done by the reference implementation.
+//!
+//! The files are found in directories given like the `PATH` variable (`/a/syzygy:/b/syzygy`), and
+//! are read in memory when they are probed for the first time.
+//!
+//! Limits: the positions with castling rights are not probed, and as the move generator only
+//! promotes to queens, the underpromotions are never advised.
+
+use crate::tablebase::{piece_count, Tablebase, Wdl};
+use model::chess_type::Type::Pawn;
+use model::game::ChessGame;
+use model::moves::Move;
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use table::{Material, Table, TableKind, TableValue};
+
+mod table;
+
+/// The WDL and DTZ files of a material
+struct TableFiles {
+    material: Material,
+    wdl_path: Option<PathBuf>,
+    dtz_path: Option<PathBuf>,
+    /// Tables loaded at their first probe, or None if they can't be read
+    wdl: OnceLock<Option<Table>>,
+    dtz: OnceLock<Option<Table>>,
+}
+
+impl TableFiles {
+    fn table(&self, kind: TableKind) -> Option<&Table> {
+        let (path, table) = match kind {
+            TableKind::Wdl => (&self.wdl_path, &self.wdl),
+            TableKind::Dtz => (&self.dtz_path, &self.dtz),
+        };
+        table
+            .get_or_init(|| {
+                let data = std::fs::read(path.as_ref()?).ok()?;
+                Table::parse(data, kind, &self.material)
+            })
+            .as_ref()
+    }
+}
+
+pub struct SyzygyTablebase {
+    /// Files of the tables, by name of their material
+    tables: HashMap<String, TableFiles>,
+    max_pieces: u32,
+}
+
+impl SyzygyTablebase {
+    /// Finds the tables of the given directories, separated by `:` (or `;` on Windows)
+    pub fn open(paths: &str) -> io::Result<Self> {
+        let mut tables: HashMap<String, TableFiles> = HashMap::new();
+        for directory in std::env::split_paths(paths) {
+            for entry in std::fs::read_dir(directory)? {
+                let path = entry?.path();
+                let kind = match path.extension().and_then(|extension| extension.to_str()) {
+                    Some(extension) if extension == TableKind::Wdl.extension() => TableKind::Wdl,
+                    Some(extension) if extension == TableKind::Dtz.extension() => TableKind::Dtz,
+                    _ => continue,
+                };
+                let Some(material) = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(Material::parse)
+                else {
+                    continue;
+                };
+                let files = tables.entry(material.name()).or_insert_with(|| TableFiles {
+                    material,
+                    wdl_path: None,
+                    dtz_path: None,
+                    wdl: OnceLock::new(),
+                    dtz: OnceLock::new(),
+                });
+                match kind {
+                    TableKind::Wdl => files.wdl_path = Some(path),
+                    TableKind::Dtz => files.dtz_path = Some(path),
+                }
+            }
+        }
+
+        let max_pieces = tables
+            .values()
+            .filter(|files| files.wdl_path.is_some())
+            .map(|files| files.material.piece_count() as u32)
+            .max()
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("No Syzygy tables found in {paths}"),
+                )
+            })?;
+        Ok(Self { tables, max_pieces })
+    }
+
+    /// Returns the number of materials which have tables
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    /// Reads the value of the position in a table, without searching the captures
+    fn probe_table(&self, kind: TableKind, game: &ChessGame, wdl: Wdl) -> Option<TableValue> {
+        let material = Material::of_game(game);
+        let (files, flipped) = match self.tables.get(&material.name()) {
+            Some(files) => (files, false),
+            None => (self.tables.get(&material.swapped().name())?, true),
+        };
+        files
+            .table(kind)?
+            .probe(game, flipped, wdl as i32)
+    }
+
+    fn probe_wdl_table(&self, game: &ChessGame) -> Option<Wdl> {
+        // Two kings are always a draw, and have no table
+        if piece_count(game) == 2 {
+            return Some(Wdl::Draw);
+        }
+        match self.probe_table(TableKind::Wdl, game, Wdl::Draw)? {
+            TableValue::Value(value) => Wdl::from_value(value),
+            TableValue::ChangeSideToMove => None,
+        }
+    }
+
+    /// Returns the result of the position, searching the captures (and the pawn moves with
+    /// `zeroing_moves`), whose results are not stored in the tables. The boolean is true when the
+    /// best move is one of those moves.
+    fn search(&self, game: &ChessGame, zeroing_moves: bool) -> Option<(Wdl, bool)> {
+        let moves = game.legal_moves(game.white_to_play());
+        let mut best = Wdl::Loss;
+        let mut searched = 0;
+        for m in &moves {
+            if !(is_capture(game, m) || zeroing_moves && is_pawn_move(game, m)) {
+                continue;
+            }
+            searched += 1;
+            let mut new_game = *game;
+            new_game.apply_move_unsafe(m);
+            let value = self.search(&new_game, false)?.0.opposite();
+            if value > best {
+                best = value;
+                if value == Wdl::Win {
+                    return Some((value, true));
+                }
+            }
+        }
+
+        // When all the moves are captures, the value of the table can't be trusted
+        let all_searched = searched > 0 && searched == moves.len();
+        let value = if all_searched {
+            best
+        } else {
+            self.probe_wdl_table(game)?
+        };
+        if best >= value {
+            Some((best, best > Wdl::Draw || all_searched))
+        } else {
+            Some((value, false))
+        }
+    }
+
+    /// Returns the distance to zeroing of the position in plies, positive if the side to move
+    /// wins and negative if it loses. The distances over 100 are the cursed wins and blessed
+    /// losses.
+    pub fn probe_dtz(&self, game: &ChessGame) -> Option<i32> {
+        if game.castling_rights().contains(&true) {
+            return None;
+        }
+        let (wdl, zeroing) = self.search(game, true)?;
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+        if zeroing {
+            return Some(dtz_before_zeroing(wdl));
+        }
+
+        let sign = (wdl as i32).signum();
+        match self.probe_table(TableKind::Dtz, game, wdl)? {
+            TableValue::Value(dtz) => {
+                let cursed = matches!(wdl, Wdl::CursedWin | Wdl::BlessedLoss);
+                Some((dtz + 100 * cursed as i32) * sign)
+            }
+            TableValue::ChangeSideToMove => {
+                // The table stores the other side to move: the distance is the best one of the
+                // moves which keep the result
+                let mut min_dtz = i32::MAX;
+                for m in game.legal_moves(game.white_to_play()) {
+                    let zeroing = is_capture(game, &m) || is_pawn_move(game, &m);
+                    let mut new_game = *game;
+                    new_game.apply_move_unsafe(&m);
+                    let mut dtz = if zeroing {
+                        -dtz_before_zeroing(self.search(&new_game, false)?.0)
+                    } else {
+                        -self.probe_dtz(&new_game)?
+                    };
+                    if dtz == 1 && is_mate(&new_game) {
+                        min_dtz = 1;
+                    }
+                    if !zeroing {
+                        dtz += dtz.signum();
+                    }
+                    if dtz < min_dtz && dtz.signum() == sign {
+                        min_dtz = dtz;
+                    }
+                }
+                Some(if min_dtz == i32::MAX { -1 } else { min_dtz })
+            }
+        }
+    }
+}
+
+impl Tablebase for SyzygyTablebase {
+    fn max_pieces(&self) -> u32 {
+        self.max_pieces
+    }
+
+    fn probe_wdl(&self, game: &ChessGame) -> Option<Wdl> {
+        if game.castling_rights().contains(&true) {
+            return None;
+        }
+        self.search(game, false).map(|(wdl, _)| wdl)
+    }
+
+    /// Plays the move with the best result, and among them the shortest distance to zeroing when
+    /// winning, or the longest one when losing
+    fn best_move(&self, game: &ChessGame) -> Option<(Move, Wdl)> {
+        let wdl = self.probe_wdl(game)?;
+        let mut best: Option<(Move, (i32, i32))> = None;
+        for m in game.legal_moves(game.white_to_play()) {
+            let mut new_game = *game;
+            new_game.apply_move_unsafe(&m);
+            let mut dtz = if is_capture(game, &m) || is_pawn_move(game, &m) {
+                dtz_before_zeroing(self.probe_wdl(&new_game)?.opposite())
+            } else {
+                let dtz = -self.probe_dtz(&new_game)?;
+                dtz + dtz.signum()
+            };
+            if dtz == 2 && is_mate(&new_game) {
+                dtz = 1;
+            }
+            let rank = (dtz.signum(), -dtz);
+            if best.is_none_or(|(_, best_rank)| rank > best_rank) {
+                best = Some((m, rank));
+            }
+        }
+        best.map(|(m, _)| (m, wdl))
+    }
+}
+
+/// Distance to zeroing of a position whose best move is a capture or a pawn move
+fn dtz_before_zeroing(wdl: Wdl) -> i32 {
+    match wdl {
+        Wdl::Win => 1,
+        Wdl::CursedWin => 101,
+        Wdl::Draw => 0,
+        Wdl::BlessedLoss => -101,
+        Wdl::Loss => -1,
+    }
+}
+
+fn is_pawn_move(game: &ChessGame, m: &Move) -> bool {
+    game.type_at_index(m.from) == Some(Pawn)
+}
+
+/// True if the move takes a piece, en passant included
+fn is_capture(game: &ChessGame, m: &Move) -> bool {
+    game.type_at_index(m.to).is_some() || (is_pawn_move(game, m) && m.from % 8 != m.to % 8)
+}
+
+fn is_mate(game: &ChessGame) -> bool {
+    let white_to_play = game.white_to_play();
+    game.is_in_check(white_to_play) && game.legal_moves(white_to_play).is_empty()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use model::utils::index_to_chesspos;
+    use std::path::Path;
+
+    /// Values of a sub-table of a synthetic file
+    pub(super) enum Values {
+        /// The same value for all the positions
+        Single(u8),
+        /// The value of each index, compressed with a code of fixed length
+        Coded(Vec<u8>),
+    }
+
+    /// Block size of the synthetic files, as a power of 2
+    const BLOCK_SIZE_LOG: u8 = 5;
+    /// Span of the sparse index of the synthetic files, as a power of 2
+    const SPAN_LOG: u8 = 5;
+
+    /// Builds a file in the Syzygy format. `pieces` are the codes of the pieces in the order of
+    /// their encoding, used for all the sub-tables, which are given by file then by side to move
+    /// with their flags.
+    pub(super) fn build_file(
+        kind: TableKind,
+        sides: usize,
+        has_pawns: bool,
+        pieces: &[u8],
+        sub_tables: &[(u8, Values)],
+    ) -> Vec<u8> {
+        let mut data = match kind {
+            TableKind::Wdl => vec![0x71, 0xE8, 0x23, 0x5D],
+            TableKind::Dtz => vec![0xD7, 0x66, 0x0C, 0xA5],
+        };
+        data.push((sides == 2) as u8 | (has_pawns as u8) << 1);
+        let files = if has_pawns { 4 } else { 1 };
+        for _ in 0..files {
+            // The leading group is encoded first
+            data.push(0);
+            data.extend(pieces.iter().map(|piece| piece | piece << 4));
+        }
+        if data.len() % 2 == 1 {
+            data.push(0);
+        }
+
+        let per_block = |values: &[u8]| -> usize {
+            (1 << BLOCK_SIZE_LOG) * 8 / symbol_bits(values)
+        };
+        for (flags, values) in sub_tables {
+            match values {
+                Values::Single(value) => data.extend([flags | 0x80, *value]),
+                Values::Coded(values) => {
+                    let bits = symbol_bits(values);
+                    let symbols = 1 << bits;
+                    data.extend([*flags, BLOCK_SIZE_LOG, SPAN_LOG, 1]);
+                    data.extend((values.len().div_ceil(per_block(values)) as u32).to_le_bytes());
+                    data.extend([bits as u8, bits as u8]);
+                    data.extend(0u16.to_le_bytes());
+                    data.extend((symbols as u16).to_le_bytes());
+                    // Every symbol is a leaf, with its value on the left
+                    for symbol in 0..symbols {
+                        data.extend([symbol as u8, 0xF0 | (symbol >> 8) as u8, 0xFF]);
+                    }
+                    if symbols % 2 == 1 {
+                        data.push(0);
+                    }
+                }
+            }
+        }
+        if kind == TableKind::Dtz && data.len() % 2 == 1 {
+            data.push(0);
+        }
+
+        let coded = || {
+            sub_tables.iter().filter_map(|(_, values)| match values {
+                Values::Coded(values) => Some(values),
+                Values::Single(_) => None,
+            })
+        };
+        let span = 1 << SPAN_LOG;
+        for values in coded() {
+            let per_block = per_block(values);
+            for k in 0..values.len().div_ceil(span) {
+                let index = k * span + span / 2;
+                data.extend(((index / per_block) as u32).to_le_bytes());
+                data.extend(((index % per_block) as u16).to_le_bytes());
+            }
+        }
+        for values in coded() {
+            let per_block = per_block(values);
+            for chunk in values.chunks(per_block) {
+                data.extend(((chunk.len() - 1) as u16).to_le_bytes());
+            }
+            // Padding
+            data.extend(((per_block - 1) as u16).to_le_bytes());
+        }
+        for (_, values) in sub_tables {
+            data.resize(data.len().next_multiple_of(64), 0);
+            if let Values::Coded(values) = values {
+                let bits = symbol_bits(values);
+                for chunk in values.chunks(per_block(values)) {
+                    let mut block = vec![0u8; 1 << BLOCK_SIZE_LOG];
+                    for (i, &value) in chunk.iter().enumerate() {
+                        for bit in 0..bits {
+                            if value >> (bits - 1 - bit) & 1 == 1 {
+                                let position = i * bits + bit;
+                                block[position / 8] |= 0x80 >> (position % 8);
+                            }
+                        }
+                    }
+                    data.extend(block);
+                }
+            }
+        }
+        data
+    }
+
+    fn symbol_bits(values: &[u8]) -> usize {
+        let max = *values.iter().max().unwrap_or(&0) as usize;
+        (usize::BITS - max.leading_zeros()).max(1) as usize
+    }
+
+    /// Returns a new empty directory for the files of a test
+    pub(crate) fn test_directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("syzygy_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    /// Writes KQvK tables in which white always wins, with a distance to zeroing of 7 plies when
+    /// white is to move
+    pub(crate) fn write_winning_kqk(directory: &Path) {
+        let pieces = [6, 5, 14];
+        let wdl = build_file(
+            TableKind::Wdl,
+            2,
+            false,
+            &pieces,
+            &[(0, Values::Single(4)), (0, Values::Single(0))],
+        );
+        std::fs::write(directory.join("KQvK.rtbw"), wdl).unwrap();
+        let dtz = build_file(TableKind::Dtz, 1, false, &pieces, &[(0, Values::Single(3))]);
+        std::fs::write(directory.join("KQvK.rtbz"), dtz).unwrap();
+    }
+
+    fn chebyshev(a: usize, b: usize) -> usize {
+        (a / 8).abs_diff(b / 8).max((a % 8).abs_diff(b % 8))
+    }
+
+    /// Builds the game of the pieces, given by type, color and square
+    fn game_of(pieces: &[(model::chess_type::Type, bool, usize)]) -> ChessGame {
+        let mut game = ChessGame::empty();
+        for &(piece, white, square) in pieces {
+            game.set_piece(piece, white, index_to_chesspos(square as i8).as_str());
+        }
+        game.block_castling();
+        game
+    }
+
+    /// Returns the game with white to play, then with black to play
+    fn both_sides(game: ChessGame) -> [ChessGame; 2] {
+        [true, false].map(|white_to_play| {
+            let mut game = game;
+            game.set_white_to_play(white_to_play);
+            game
+        })
+    }
+
+    /// Writes a table whose values are given by `oracle` for all the positions of `positions`,
+    /// which must be symmetric. Fails if two positions with different values share an index.
+    fn write_oracle_table(
+        directory: &Path,
+        name: &str,
+        pieces: &[u8],
+        table_size: usize,
+        positions: &[ChessGame],
+        oracle: impl Fn(&ChessGame) -> u8,
+    ) {
+        let material = Material::parse(name).unwrap();
+        let has_pawns = name.contains('P');
+        let files = if has_pawns { 4 } else { 1 };
+        let layout: Vec<(u8, Values)> = (0..2 * files).map(|_| (0, Values::Single(0))).collect();
+        let layout = build_file(TableKind::Wdl, 2, has_pawns, pieces, &layout);
+        let layout = Table::parse(layout, TableKind::Wdl, &material).unwrap();
+
+        let mut values = vec![vec![None; table_size]; 2 * files];
+        for game in positions {
+            let (side, file, index) = layout.index(game, false).unwrap();
+            let value = oracle(game);
+            let stored = &mut values[2 * file + side][index as usize];
+            assert!(
+                stored.is_none_or(|stored| stored == value),
+                "Collision at index {index} for {}",
+                game.to_fen()
+            );
+            *stored = Some(value);
+        }
+        let sub_tables: Vec<(u8, Values)> = values
+            .into_iter()
+            .map(|values| (0, Values::Coded(values.into_iter().map(Option::unwrap_or_default).collect())))
+            .collect();
+        let file = build_file(TableKind::Wdl, 2, has_pawns, pieces, &sub_tables);
+        std::fs::write(directory.join(format!("{name}.rtbw")), file).unwrap();
+    }
+
+    #[test]
+    fn test_pawnless_table() {
+        use model::chess_type::Type::{King, Queen};
+
+        // The values depend on distances, which don't change with the symmetries of the board
+        let oracle = |game: &ChessGame| {
+            let square = |piece, white| game.pieces(piece, white).trailing_zeros() as usize;
+            let black_king = square(King, false);
+            let distance = if game.white_to_play() {
+                chebyshev(square(King, true), black_king)
+            } else {
+                chebyshev(square(Queen, true), black_king)
+            };
+            (distance % 5) as u8
+        };
+        let mut positions = vec![];
+        for white_king in 0..64 {
+            for queen in (0..64).filter(|&queen| queen != white_king) {
+                for black_king in (0..64).filter(|&king| chebyshev(king, white_king) > 1) {
+                    if black_king != queen {
+                        let game = game_of(&[
+                            (King, true, white_king),
+                            (Queen, true, queen),
+                            (King, false, black_king),
+                        ]);
+                        positions.extend(both_sides(game));
+                    }
+                }
+            }
+        }
+
+        let directory = test_directory("pawnless");
+        write_oracle_table(&directory, "KQvK", &[6, 5, 14], 31332, &positions, oracle);
+        let tablebase = SyzygyTablebase::open(directory.to_str().unwrap()).unwrap();
+        assert_eq!(3, tablebase.max_pieces());
+        assert_eq!(1, tablebase.len());
+
+        for game in positions.iter().step_by(7) {
+            let expected = Wdl::from_value(oracle(game) as i32 - 2);
+            assert_eq!(expected, tablebase.probe_wdl_table(game), "{}", game.to_fen());
+
+            // The same position with the colors swapped is found in the same table
+            let mut flipped = ChessGame::empty();
+            for square in 0..64 {
+                if let Some(piece) = game.type_at_index(square) {
+                    let white = game.pieces(piece, true) & (1 << square) != 0;
+                    flipped.set_piece(piece, !white, index_to_chesspos(square ^ 56).as_str());
+                }
+            }
+            flipped.block_castling();
+            flipped.set_white_to_play(!game.white_to_play());
+            assert_eq!(expected, tablebase.probe_wdl_table(&flipped));
+        }
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_identical_pieces() {
+        use model::chess_type::Type::{King, Knight};
+
+        let oracle = |game: &ChessGame| {
+            let kings = chebyshev(
+                game.pieces(King, true).trailing_zeros() as usize,
+                game.pieces(King, false).trailing_zeros() as usize,
+            );
+            let knights = game.pieces(Knight, true);
+            let first = knights.trailing_zeros() as usize;
+            let second = 63 - knights.leading_zeros() as usize;
+            ((kings + chebyshev(first, second) + game.white_to_play() as usize) % 5) as u8
+        };
+        // The knights are on a few squares only, to keep the test short
+        let knight_squares = [0, 9, 18, 27, 12, 33, 47, 50, 61];
+        let mut positions = vec![];
+        for white_king in 0..64 {
+            for black_king in (0..64).filter(|&king| chebyshev(king, white_king) > 1) {
+                for (i, &first) in knight_squares.iter().enumerate() {
+                    for &second in &knight_squares[i + 1..] {
+                        if [white_king, black_king].iter().any(|&k| k == first || k == second) {
+                            continue;
+                        }
+                        let game = game_of(&[
+                            (King, true, white_king),
+                            (King, false, black_king),
+                            (Knight, true, first),
+                            (Knight, true, second),
+                        ]);
+                        positions.extend(both_sides(game));
+                    }
+                }
+            }
+        }
+
+        let directory = test_directory("identical");
+        write_oracle_table(&directory, "KNNvK", &[6, 14, 2, 2], 462 * 1891, &positions, oracle);
+        let tablebase = SyzygyTablebase::open(directory.to_str().unwrap()).unwrap();
+        assert_eq!(4, tablebase.max_pieces());
+        for game in positions.iter().step_by(11) {
+            let expected = Wdl::from_value(oracle(game) as i32 - 2);
+            assert_eq!(expected, tablebase.probe_wdl_table(game));
+        }
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_pawn_table() {
+        use model::chess_type::Type::King;
+
+        let oracle = |game: &ChessGame| {
+            let square = |piece, white| game.pieces(piece, white).trailing_zeros() as usize;
+            let pawn = square(Pawn, true);
+            let value = if game.white_to_play() {
+                pawn / 8 + chebyshev(square(King, true), square(King, false))
+            } else {
+                chebyshev(pawn, square(King, false))
+            };
+            (value % 5) as u8
+        };
+        let mut positions = vec![];
+        for pawn in 8..56 {
+            for white_king in (0..64).filter(|&king| king != pawn) {
+                for black_king in (0..64).filter(|&king| chebyshev(king, white_king) > 1) {
+                    if black_king != pawn {
+                        let game = game_of(&[
+                            (Pawn, true, pawn),
+                            (King, true, white_king),
+                            (King, false, black_king),
+                        ]);
+                        positions.extend(both_sides(game));
+                    }
+                }
+            }
+        }
+
+        let directory = test_directory("pawns");
+        write_oracle_table(&directory, "KPvK", &[1, 6, 14], 6 * 63 * 62, &positions, oracle);
+        let tablebase = SyzygyTablebase::open(directory.to_str().unwrap()).unwrap();
+        for game in positions.iter().step_by(5) {
+            let expected = Wdl::from_value(oracle(game) as i32 - 2);
+            assert_eq!(expected, tablebase.probe_wdl_table(game));
+        }
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_probes() {
+        let directory = test_directory("probes");
+        write_winning_kqk(&directory);
+        let tablebase = SyzygyTablebase::open(directory.to_str().unwrap()).unwrap();
+
+        // Two kings are a draw, and the other materials are unknown
+        let kings = ChessGame::from_fen("8/8/3k4/8/8/8/8/K7 w - - 0 1");
+        assert_eq!(Some(Wdl::Draw), tablebase.probe_wdl(&kings));
+        let rook = ChessGame::from_fen("8/8/3k4/8/8/8/8/KR6 w - - 0 1");
+        assert_eq!(None, tablebase.probe_wdl(&rook));
+
+        // The queen of d2 is attacked by the black king, which draws by taking it
+        let game = ChessGame::from_fen("8/8/8/8/8/2k5/3Q4/K7 b - - 0 1");
+        assert_eq!(Some(Wdl::Draw), tablebase.probe_wdl(&game));
+        assert_eq!(Some(0), tablebase.probe_dtz(&game));
+        let [white, black] = both_sides(ChessGame::from_fen("8/8/8/8/8/2k5/8/K2Q4 b - - 0 1"));
+        assert_eq!(Some(Wdl::Loss), tablebase.probe_wdl(&black));
+        assert_eq!(Some(Wdl::Win), tablebase.probe_wdl(&white));
+        // The table stores 3 moves for white, so 7 plies, and black is one ply further
+        assert_eq!(Some(7), tablebase.probe_dtz(&white));
+        assert_eq!(Some(-8), tablebase.probe_dtz(&black));
+
+        // White must not put its queen next to the black king
+        let (m, wdl) = tablebase.best_move(&white).unwrap();
+        assert_eq!(Wdl::Win, wdl);
+        let mut new_game = white;
+        new_game.apply_move_unsafe(&m);
+        assert_eq!(Some(Wdl::Loss), tablebase.probe_wdl(&new_game));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    /// Directory of real tables of the Syzygy set, which aren't written by `build_file`
+    const REAL_TABLES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tablebases/syzygy");
+
+    #[test]
+    #[ignore = "needs the real KQvK and KRvK tables in engine/tablebases/syzygy"]
+    fn test_real_tables() {
+        use crate::tablebase::dtm::generator::generate;
+        use crate::tablebase::dtm::{Dtm, DtmTablebase, Material, EXTENSION};
+        use model::chess_type::Type::{King, Queen, Rook};
+        use std::collections::HashMap;
+
+        let tablebase = SyzygyTablebase::open(REAL_TABLES).unwrap();
+        assert_eq!(3, tablebase.max_pieces());
+
+        // The queen mates in h8
+        let game = ChessGame::from_fen("k7/8/1K6/8/8/8/7Q/8 w - - 0 1");
+        assert_eq!(Some(Wdl::Win), tablebase.probe_wdl(&game));
+        assert_eq!(Some(1), tablebase.probe_dtz(&game));
+        // The black king takes the rook
+        let game = ChessGame::from_fen("8/8/8/8/8/2k5/3R4/K7 b - - 0 1");
+        assert_eq!(Some(Wdl::Draw), tablebase.probe_wdl(&game));
+        assert_eq!(Some(0), tablebase.probe_dtz(&game));
+
+        // The results agree with the tables of distance to mate of the engine. As the winning side
+        // has no zeroing move, the distance to zeroing is the distance to mate, up to the rounding
+        // of the tables which store moves instead of plies.
+        let directory = test_directory("real");
+        let mut tables = HashMap::new();
+        for name in ["KQvK", "KRvK"] {
+            generate(&Material::parse(name).unwrap(), &mut tables);
+        }
+        for table in tables.values() {
+            let name = table.material().name();
+            table.write(&directory.join(format!("{name}.{EXTENSION}"))).unwrap();
+        }
+        let dtm_tablebase = DtmTablebase::open(directory.to_str().unwrap()).unwrap();
+
+        let mut positions = vec![];
+        for piece in [Queen, Rook] {
+            for white_king in 0..64 {
+                for square in (0..64).filter(|&square| square != white_king) {
+                    for black_king in (0..64).filter(|&king| chebyshev(king, white_king) > 1) {
+                        if black_king != square {
+                            let game = game_of(&[
+                                (King, true, white_king),
+                                (piece, true, square),
+                                (King, false, black_king),
+                            ]);
+                            positions.extend(both_sides(game));
+                        }
+                    }
+                }
+            }
+        }
+        for game in positions.iter().step_by(13) {
+            // The king of the side which just played can't be in check
+            if game.is_in_check(!game.white_to_play()) {
+                continue;
+            }
+            let fen = game.to_fen();
+            let dtm = dtm_tablebase.probe_dtm(game).unwrap();
+            assert_eq!(Some(dtm.wdl()), tablebase.probe_wdl(game), "{fen}");
+            let dtz = tablebase.probe_dtz(game).unwrap();
+            match dtm {
+                Dtm::Draw => assert_eq!(0, dtz, "{fen}"),
+                // The mated positions are not probed by the search
+                Dtm::Loss(0) => {}
+                Dtm::Win(plies) => {
+                    assert!(dtz > 0 && dtz.unsigned_abs().abs_diff(plies) <= 1, "{fen}")
+                }
+                Dtm::Loss(plies) => {
+                    assert!(dtz < 0 && dtz.unsigned_abs().abs_diff(plies) <= 1, "{fen}")
+                }
+            }
+        }
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_no_tables() {
+        let directory = test_directory("empty");
+        assert!(SyzygyTablebase::open(directory.to_str().unwrap()).is_err());
+        assert!(SyzygyTablebase::open("/does/not/exist").is_err());
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,1033 @@
+//! Decoding of the Syzygy files.
+//!
+//! A file is made of one sub-table for each side to move (only one for the DTZ tables and the
+//! symmetric materials) and, with pawns, for each file of the leading pawn. A position is mapped
+//! to an index in its sub-table, using the symmetries of the board, and the values of the indices
+//! are compressed with a canonical Huffman code of symbols which each expand to a pair of
+//! symbols ("recursive pairing").
+//!
+//! The decoding follows the reference implementation of the format, by Ronald de Man.
+
+use model::chess_type::Type::{self, Bishop, King, Knight, Pawn, Queen, Rook};
+use model::game::ChessGame;
+use once_cell::sync::Lazy;
+
+/// Maximum number of pieces of a table
+const MAX_PIECES: usize = 7;
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+
+// Flags of the sub-tables
+const FLAG_STM: u8 = 1;
+const FLAG_MAPPED: u8 = 2;
+const FLAG_WIN_PLIES: u8 = 4;
+const FLAG_LOSS_PLIES: u8 = 8;
+const FLAG_WIDE: u8 = 16;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(super) enum TableKind {
+    Wdl,
+    Dtz,
+}
+
+impl TableKind {
+    pub(super) fn extension(self) -> &'static str {
+        match self {
+            TableKind::Wdl => "rtbw",
+            TableKind::Dtz => "rtbz",
+        }
+    }
+
+    fn magic(self) -> [u8; 4] {
+        match self {
+            TableKind::Wdl => WDL_MAGIC,
+            TableKind::Dtz => DTZ_MAGIC,
+        }
+    }
+}
+
+/// Code of a piece in the tables: 1 to 6 for the white pawn to king, plus 8 for black
+fn piece_code(piece: Type, white: bool) -> u8 {
+    let code = match piece {
+        Pawn => 1,
+        Knight => 2,
+        Bishop => 3,
+        Rook => 4,
+        Queen => 5,
+        King => 6,
+    };
+    if white {
+        code
+    } else {
+        code | 8
+    }
+}
+
+/// Material of a table, read from its name such as `KRPvKR`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct Material {
+    /// Number of pieces of each type, indexed by code, for the first side of the name then for the
+    /// second one
+    counts: [[u8; 7]; 2],
+}
+
+impl Material {
+    pub(super) fn parse(name: &str) -> Option<Self> {
+        let (first, second) = name.split_once('v')?;
+        let mut counts = [[0u8; 7]; 2];
+        for (side, pieces) in [first, second].into_iter().enumerate() {
+            for c in pieces.chars() {
+                let code = match c {
+                    'P' => 1,
+                    'N' => 2,
+                    'B' => 3,
+                    'R' => 4,
+                    'Q' => 5,
+                    'K' => 6,
+                    _ => return None,
+                };
+                counts[side][code] = counts[side][code].saturating_add(1);
+            }
+            if counts[side][6] != 1 {
+                return None;
+            }
+        }
+        let material = Self { counts };
+        (material.piece_count() <= MAX_PIECES).then_some(material)
+    }
+
+    /// Returns the material of the game, with white as the first side
+    pub(super) fn of_game(game: &ChessGame) -> Self {
+        let mut counts = [[0; 7]; 2];
+        for (side, white) in [true, false].into_iter().enumerate() {
+            for piece in [Pawn, Knight, Bishop, Rook, Queen, King] {
+                counts[side][piece_code(piece, true) as usize] =
+                    game.pieces(piece, white).count_ones() as u8;
+            }
+        }
+        Self { counts }
+    }
+
+    /// The same material, with the sides swapped
+    pub(super) fn swapped(&self) -> Self {
+        Self {
+            counts: [self.counts[1], self.counts[0]],
+        }
+    }
+
+    /// Name of the material, with the pieces of each side sorted from the king to the pawns
+    pub(super) fn name(&self) -> String {
+        let side = |counts: &[u8; 7]| -> String {
+            [(6, 'K'), (5, 'Q'), (4, 'R'), (3, 'B'), (2, 'N'), (1, 'P')]
+                .into_iter()
+                .flat_map(|(code, c)| std::iter::repeat_n(c, counts[code] as usize))
+                .collect()
+        };
+        format!("{}v{}", side(&self.counts[0]), side(&self.counts[1]))
+    }
+
+    pub(super) fn piece_count(&self) -> usize {
+        self.counts.iter().flatten().map(|&n| n as usize).sum()
+    }
+
+    fn is_symmetric(&self) -> bool {
+        self.counts[0] == self.counts[1]
+    }
+
+    fn has_pawns(&self) -> bool {
+        self.counts[0][1] + self.counts[1][1] > 0
+    }
+
+    /// True if a side has a single piece of a type other than the king
+    fn has_unique_pieces(&self) -> bool {
+        self.counts
+            .iter()
+            .any(|counts| counts[1..6].contains(&1))
+    }
+
+    /// Numbers of pawns of the leading side, which is the side with fewer pawns (but at least
+    /// one), and of the other side
+    fn pawn_counts(&self) -> [u8; 2] {
+        let (first, second) = (self.counts[0][1], self.counts[1][1]);
+        if second == 0 || (first > 0 && second >= first) {
+            [first, second]
+        } else {
+            [second, first]
+        }
+    }
+}
+
+/// Tables of the encoding of the positions to indices
+struct Encoding {
+    /// Squares of the pawns, sorted from the edges and the lowest ranks (47) to the center
+    map_pawns: [usize; 64],
+    /// Squares below the a1-h8 diagonal, from 0 to 27
+    map_b1h1h7: [usize; 64],
+    /// Squares of the a1-d1-d4 triangle, from 0 to 9 (the diagonal squares are the last ones)
+    map_a1d1d4: [usize; 64],
+    /// The 462 positions of two kings, when the first one is in the a1-d1-d4 triangle
+    map_kk: [[usize; 64]; 10],
+    /// `binomial[k][n]`: the number of ways to choose `k` squares among `n`
+    binomial: [[u64; 64]; MAX_PIECES],
+    /// First index of the positions of the leading pawns for each square of the first one
+    lead_pawn_index: [[u64; 64]; MAX_PIECES],
+    /// Numbers of positions of the leading pawns, for each file of the first one
+    lead_pawns_size: [[u64; 4]; MAX_PIECES],
+}
+
+/// Rank minus file: negative below the a1-h8 diagonal
+fn off_diagonal(square: usize) -> i32 {
+    (square / 8) as i32 - (square % 8) as i32
+}
+
+static ENCODING: Lazy<Encoding> = Lazy::new(encoding);
+
+#[allow(clippy::needless_range_loop)]
+fn encoding() -> Encoding {
+    let mut map_b1h1h7 = [0; 64];
+    let mut code = 0;
+    for square in 0..64 {
+        if off_diagonal(square) < 0 {
+            map_b1h1h7[square] = code;
+            code += 1;
+        }
+    }
+
+    let mut map_a1d1d4 = [usize::MAX; 64];
+    let mut diagonal = vec![];
+    let mut code = 0;
+    for square in (0..=27).filter(|square| square % 8 <= 3) {
+        if off_diagonal(square) < 0 {
+            map_a1d1d4[square] = code;
+            code += 1;
+        } else if off_diagonal(square) == 0 {
+            diagonal.push(square);
+        }
+    }
+    for square in diagonal {
+        map_a1d1d4[square] = code;
+        code += 1;
+    }
+
+    let mut map_kk = [[usize::MAX; 64]; 10];
+    let mut both_on_diagonal = vec![];
+    let mut code = 0;
+    for (index, first) in (0..10).filter_map(|index| {
+        let first = (0..64).find(|&square| map_a1d1d4[square] == index)?;
+        Some((index, first))
+    }) {
+        for second in 0..64 {
+            let adjacent = (first / 8).abs_diff(second / 8) <= 1
+                && (first % 8).abs_diff(second % 8) <= 1;
+            if adjacent || (off_diagonal(first) == 0 && off_diagonal(second) > 0) {
+                continue;
+            }
+            if off_diagonal(first) == 0 && off_diagonal(second) == 0 {
+                both_on_diagonal.push((index, second));
+            } else {
+                map_kk[index][second] = code;
+                code += 1;
+            }
+        }
+    }
+    for (index, second) in both_on_diagonal {
+        map_kk[index][second] = code;
+        code += 1;
+    }
+
+    let mut binomial = [[0; 64]; MAX_PIECES];
+    binomial[0][0] = 1;
+    for n in 1..64 {
+        for k in 0..MAX_PIECES.min(n + 1) {
+            binomial[k][n] = if k > 0 { binomial[k - 1][n - 1] } else { 0 }
+                + if k < n { binomial[k][n - 1] } else { 0 };
+        }
+    }
+
+    let mut map_pawns = [0; 64];
+    let mut lead_pawn_index = [[0; 64]; MAX_PIECES];
+    let mut lead_pawns_size = [[0; 4]; MAX_PIECES];
+    let mut available: usize = 47;
+    for lead_pawns in 1..MAX_PIECES - 1 {
+        for file in 0..4 {
+            let mut index = 0;
+            for rank in 1..7 {
+                let square = 8 * rank + file;
+                if lead_pawns == 1 {
+                    map_pawns[square] = available;
+                    map_pawns[square ^ 7] = available - 1;
+                    available = available.saturating_sub(2);
+                }
+                lead_pawn_index[lead_pawns][square] = index;
+                index += binomial[lead_pawns - 1][map_pawns[square]];
+            }
+            lead_pawns_size[lead_pawns][file] = index;
+        }
+    }
+
+    Encoding {
+        map_pawns,
+        map_b1h1h7,
+        map_a1d1d4,
+        map_kk,
+        binomial,
+        lead_pawn_index,
+        lead_pawns_size,
+    }
+}
+
+fn read_u16_le(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn read_u32_le(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// Reads a big endian word of the compressed data, padded with zeros after the end of the file
+fn read_u32_be(data: &[u8], at: usize) -> u32 {
+    let mut bytes = [0; 4];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = data.get(at + i).copied().unwrap_or(0);
+    }
+    u32::from_be_bytes(bytes)
+}
+
+/// A sub-table of a file. The positions of its fields are offsets in the data of the file.
+#[derive(Clone, Debug, Default)]
+struct PairsData {
+    flags: u8,
+    max_symbol_length: u8,
+    /// Minimum length of the symbols, or the value of all the positions with `FLAG_SINGLE_VALUE`
+    min_symbol_length: u8,
+    block_size: usize,
+    /// Distance between the indices of the entries of the sparse index
+    span: usize,
+    blocks: usize,
+    /// Symbols of lowest value for each length of symbol
+    lowest_symbol: usize,
+    /// Left and right symbols of each symbol, on 12 bits each
+    tree: usize,
+    /// Number of values minus one of each block
+    block_lengths: usize,
+    block_lengths_size: usize,
+    /// Blocks and offsets of the values of every `span` indices
+    sparse_index: usize,
+    sparse_index_size: usize,
+    /// Start of the compressed blocks
+    blocks_data: usize,
+    /// Lowest code of each length of symbol, left-aligned on 64 bits
+    base: Vec<u64>,
+    /// Number of values minus one represented by each symbol
+    symbol_lengths: Vec<u8>,
+    /// Codes of the pieces, in the order of their encoding
+    pieces: [u8; MAX_PIECES],
+    /// Multiplier of the index of each group of pieces
+    group_index: [u64; MAX_PIECES + 1],
+    /// Number of pieces of each group, followed by a zero
+    group_length: [usize; MAX_PIECES + 1],
+    /// Offsets of the value maps of the DTZ tables, for each result
+    map_index: [usize; 4],
+}
+
+/// Result of the lookup of a position in a table
+pub(super) enum TableValue {
+    /// The WDL value or the DTZ in plies (with the sign of the result)
+    Value(i32),
+    /// The DTZ table only stores the positions with the other side to move
+    ChangeSideToMove,
+}
+
+/// A WDL or DTZ file, loaded in memory
+pub(super) struct Table {
+    data: Vec<u8>,
+    kind: TableKind,
+    piece_count: usize,
+    has_pawns: bool,
+    has_unique_pieces: bool,
+    symmetric: bool,
+    pawn_counts: [u8; 2],
+    /// Number of sub-tables for each file
+    sides: usize,
+    /// Sub-tables, indexed by side then by file
+    pairs: Vec<Vec<PairsData>>,
+    /// Offset of the value maps of the DTZ tables
+    map: usize,
+}
+
+impl Table {
+    /// Parses the file of a table of the given material, returning None if it is invalid
+    pub(super) fn parse(data: Vec<u8>, kind: TableKind, material: &Material) -> Option<Self> {
+        if data.get(0..4)? != kind.magic() {
+            return None;
+        }
+        let has_pawns = material.has_pawns();
+        let flags = *data.get(4)?;
+        if (flags & 2 != 0) != has_pawns {
+            return None;
+        }
+        let sides = if kind == TableKind::Wdl && flags & 1 != 0 {
+            2
+        } else {
+            1
+        };
+        let files = if has_pawns { 4 } else { 1 };
+
+        let mut table = Self {
+            data,
+            kind,
+            piece_count: material.piece_count(),
+            has_pawns,
+            has_unique_pieces: material.has_unique_pieces(),
+            symmetric: material.is_symmetric(),
+            pawn_counts: material.pawn_counts(),
+            sides,
+            pairs: vec![vec![PairsData::default(); files]; sides],
+            map: 0,
+        };
+        table.parse_layout()?;
+        Some(table)
+    }
+
+    fn parse_layout(&mut self) -> Option<()> {
+        let files = self.pairs[0].len();
+        let both_pawns = self.has_pawns && self.pawn_counts[1] > 0;
+        let mut at = 5;
+
+        for file in 0..files {
+            let order_byte = |i: usize| self.data.get(at + i).copied();
+            let first = order_byte(0)?;
+            let second = if both_pawns { order_byte(1)? } else { 0xFF };
+            let orders = [
+                [first & 0xF, second & 0xF],
+                [first >> 4, second >> 4],
+            ];
+            at += 1 + both_pawns as usize;
+            for k in 0..self.piece_count {
+                let byte = *self.data.get(at)?;
+                for side in 0..self.sides {
+                    self.pairs[side][file].pieces[k] = if side == 1 { byte >> 4 } else { byte & 0xF };
+                }
+                at += 1;
+            }
+            for (side, order) in orders.iter().enumerate().take(self.sides) {
+                self.set_groups(side, file, *order)?;
+            }
+        }
+        at += at & 1;
+
+        for file in 0..files {
+            for side in 0..self.sides {
+                at = self.set_sizes(side, file, at)?;
+            }
+        }
+
+        if self.kind == TableKind::Dtz {
+            at = self.set_dtz_map(at)?;
+        }
+
+        for file in 0..files {
+            for side in 0..self.sides {
+                let pairs = &mut self.pairs[side][file];
+                pairs.sparse_index = at;
+                at += pairs.sparse_index_size * 6;
+            }
+        }
+        for file in 0..files {
+            for side in 0..self.sides {
+                let pairs = &mut self.pairs[side][file];
+                pairs.block_lengths = at;
+                at += pairs.block_lengths_size * 2;
+            }
+        }
+        for file in 0..files {
+            for side in 0..self.sides {
+                at = (at + 0x3F) & !0x3F;
+                let pairs = &mut self.pairs[side][file];
+                pairs.blocks_data = at;
+                at += pairs.blocks * pairs.block_size;
+            }
+        }
+        (at <= self.data.len()).then_some(())
+    }
+
+    /// Splits the pieces in the groups which are encoded together, and computes the multiplier of
+    /// the index of each group. The groups are encoded in the order given by the file.
+    fn set_groups(&mut self, side: usize, file: usize, order: [u8; 2]) -> Option<()> {
+        let encoding = &*ENCODING;
+        let piece_count = self.piece_count;
+        let has_pawns = self.has_pawns;
+        let both_pawns = has_pawns && self.pawn_counts[1] > 0;
+        let mut first_length: i32 = if has_pawns {
+            0
+        } else if self.has_unique_pieces {
+            3
+        } else {
+            2
+        };
+        let unique = self.has_unique_pieces;
+        let pairs = &mut self.pairs[side][file];
+
+        let mut n = 0;
+        pairs.group_length[0] = 1;
+        for i in 1..piece_count {
+            first_length -= 1;
+            if first_length > 0 || pairs.pieces[i] == pairs.pieces[i - 1] {
+                pairs.group_length[n] += 1;
+            } else {
+                n += 1;
+                pairs.group_length[n] = 1;
+            }
+        }
+        n += 1;
+        pairs.group_length[n] = 0;
+
+        let mut next = if both_pawns { 2 } else { 1 };
+        let mut free_squares = 64
+            - pairs.group_length[0]
+            - if both_pawns { pairs.group_length[1] } else { 0 };
+        let mut index: u64 = 1;
+        let mut k = 0;
+        while next < n || k == order[0] as usize || k == order[1] as usize {
+            if k == order[0] as usize {
+                pairs.group_index[0] = index;
+                index *= if has_pawns {
+                    *encoding.lead_pawns_size.get(pairs.group_length[0])?.get(file)?
+                } else if unique {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] as usize {
+                pairs.group_index[1] = index;
+                index *= encoding.binomial.get(pairs.group_length[1])?[48 - pairs.group_length[0]];
+            } else {
+                pairs.group_index[next] = index;
+                index *= encoding.binomial.get(pairs.group_length[next])?[free_squares];
+                free_squares = free_squares.checked_sub(pairs.group_length[next])?;
+                next += 1;
+            }
+            k += 1;
+        }
+        pairs.group_index[n] = index;
+        Some(())
+    }
+
+    /// Reads the parameters of the compression of a sub-table, and returns the offset which
+    /// follows them
+    fn set_sizes(&mut self, side: usize, file: usize, mut at: usize) -> Option<usize> {
+        let data = &self.data;
+        let pairs = &mut self.pairs[side][file];
+        pairs.flags = *data.get(at)?;
+        at += 1;
+        if pairs.flags & FLAG_SINGLE_VALUE != 0 {
+            pairs.min_symbol_length = *data.get(at)?;
+            return Some(at + 1);
+        }
+
+        let groups = pairs.group_length.iter().position(|&length| length == 0)?;
+        let table_size = pairs.group_index[groups];
+
+        pairs.block_size = 1usize.checked_shl(*data.get(at)? as u32)?;
+        pairs.span = 1usize.checked_shl(*data.get(at + 1)? as u32)?;
+        pairs.sparse_index_size = (table_size as usize).div_ceil(pairs.span);
+        let padding = *data.get(at + 2)? as usize;
+        pairs.blocks = read_u32_le(data, at + 3)? as usize;
+        pairs.block_lengths_size = pairs.blocks + padding;
+        pairs.max_symbol_length = *data.get(at + 7)?;
+        pairs.min_symbol_length = *data.get(at + 8)?;
+        at += 9;
+        pairs.lowest_symbol = at;
+        if pairs.max_symbol_length < pairs.min_symbol_length
+            || pairs.min_symbol_length == 0
+            || pairs.max_symbol_length > 64
+        {
+            return None;
+        }
+
+        // The codes of the canonical Huffman code are ordered such that the longer symbols have a
+        // lower value: base[i] is the lowest code of length `min_symbol_length + i`
+        let lengths = (pairs.max_symbol_length - pairs.min_symbol_length + 1) as usize;
+        let lowest = |i: usize| read_u16_le(data, at + 2 * i).map(u64::from);
+        pairs.base = vec![0; lengths];
+        for i in (0..lengths - 1).rev() {
+            pairs.base[i] = (pairs.base[i + 1]
+                .wrapping_add(lowest(i)?)
+                .wrapping_sub(lowest(i + 1)?))
+                / 2;
+        }
+        for (i, base) in pairs.base.iter_mut().enumerate() {
+            let shift = 64 - i as u32 - pairs.min_symbol_length as u32;
+            *base = base.checked_shl(shift).unwrap_or(0);
+        }
+        at += 2 * lengths;
+
+        let symbols = read_u16_le(data, at)? as usize;
+        at += 2;
+        pairs.tree = at;
+        if data.len() < at + 3 * symbols {
+            return None;
+        }
+        pairs.symbol_lengths = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for symbol in 0..symbols {
+            if !visited[symbol] {
+                let length = symbol_length(data, pairs, symbol, &mut visited)?;
+                pairs.symbol_lengths[symbol] = length;
+            }
+        }
+        Some(at + 3 * symbols + (symbols & 1))
+    }
+
+    /// Reads the maps from the values of the DTZ tables to the distances, and returns the offset
+    /// which follows them
+    fn set_dtz_map(&mut self, mut at: usize) -> Option<usize> {
+        self.map = at;
+        for file in 0..self.pairs[0].len() {
+            let flags = self.pairs[0][file].flags;
+            if flags & FLAG_MAPPED == 0 {
+                continue;
+            }
+            for i in 0..4 {
+                if flags & FLAG_WIDE != 0 {
+                    at += at & 1;
+                    self.pairs[0][file].map_index[i] = (at - self.map) / 2 + 1;
+                    at += 2 * read_u16_le(&self.data, at)? as usize + 2;
+                } else {
+                    self.pairs[0][file].map_index[i] = at - self.map + 1;
+                    at += *self.data.get(at)? as usize + 1;
+                }
+            }
+        }
+        Some(at + (at & 1))
+    }
+
+    /// Returns the value of the index in a sub-table
+    fn decompress(&self, pairs: &PairsData, index: u64) -> Option<u32> {
+        if pairs.flags & FLAG_SINGLE_VALUE != 0 {
+            return Some(pairs.min_symbol_length as u32);
+        }
+        let data = &self.data;
+        let block_length = |block: usize| -> Option<i64> {
+            if block >= pairs.block_lengths_size {
+                return None;
+            }
+            read_u16_le(data, pairs.block_lengths + 2 * block).map(i64::from)
+        };
+
+        // The sparse index gives the block and the offset of the values at every `span` indices,
+        // from which the block of the index is found
+        let k = (index / pairs.span as u64) as usize;
+        if k >= pairs.sparse_index_size {
+            return None;
+        }
+        let mut block = read_u32_le(data, pairs.sparse_index + 6 * k)? as usize;
+        let mut offset = read_u16_le(data, pairs.sparse_index + 6 * k + 4)? as i64;
+        offset += (index % pairs.span as u64) as i64 - (pairs.span / 2) as i64;
+        while offset < 0 {
+            block = block.checked_sub(1)?;
+            offset += block_length(block)? + 1;
+        }
+        while offset > block_length(block)? {
+            offset -= block_length(block)? + 1;
+            block += 1;
+        }
+        if block >= pairs.blocks {
+            return None;
+        }
+
+        // Decodes the symbols of the block until the one which contains the offset
+        let mut at = pairs.blocks_data + block * pairs.block_size;
+        let mut buffer =
+            ((read_u32_be(data, at) as u64) << 32) | read_u32_be(data, at + 4) as u64;
+        at += 8;
+        let mut buffer_size: usize = 64;
+        let min_length = pairs.min_symbol_length as usize;
+        let mut symbol;
+        loop {
+            let mut length = 0;
+            while buffer < pairs.base[length] {
+                length += 1;
+                if length >= pairs.base.len() {
+                    return None;
+                }
+            }
+            symbol = ((buffer - pairs.base[length]) >> (64 - length - min_length)) as usize;
+            symbol = (symbol as u16)
+                .wrapping_add(read_u16_le(data, pairs.lowest_symbol + 2 * length)?)
+                as usize;
+            let symbol_length = *pairs.symbol_lengths.get(symbol)? as i64;
+            if offset < symbol_length + 1 {
+                break;
+            }
+            offset -= symbol_length + 1;
+            length += min_length;
+            buffer = buffer.checked_shl(length as u32).unwrap_or(0);
+            buffer_size = buffer_size.checked_sub(length)?;
+            if buffer_size <= 32 {
+                buffer_size += 32;
+                buffer |= (read_u32_be(data, at) as u64) << (64 - buffer_size);
+                at += 4;
+            }
+        }
+
+        // Expands the pairs of symbols until the value of the offset
+        while pairs.symbol_lengths[symbol] != 0 {
+            let (left, right) = tree_node(data, pairs, symbol)?;
+            let left_length = *pairs.symbol_lengths.get(left)? as i64;
+            if offset < left_length + 1 {
+                symbol = left;
+            } else {
+                offset -= left_length + 1;
+                symbol = right;
+            }
+            if symbol >= pairs.symbol_lengths.len() {
+                return None;
+            }
+        }
+        tree_node(data, pairs, symbol).map(|(left, _)| left as u32)
+    }
+
+    /// Returns the value of the position in the table, which must have its material with white as
+    /// the first side of the name when `flipped` is false, or as the second side otherwise.
+    ///
+    /// `wdl` is the result of the position, needed to read the DTZ tables.
+    pub(super) fn probe(&self, game: &ChessGame, flipped: bool, wdl: i32) -> Option<TableValue> {
+        let (side_to_move, file, index) = self.index(game, flipped)?;
+        let pairs = &self.pairs[side_to_move % self.sides][file];
+
+        // The DTZ tables only store one side to move
+        if self.kind == TableKind::Dtz
+            && (self.has_pawns || !self.symmetric)
+            && (pairs.flags & FLAG_STM) as usize != side_to_move
+        {
+            return Some(TableValue::ChangeSideToMove);
+        }
+
+        let value = self.decompress(pairs, index)? as i32;
+        Some(TableValue::Value(match self.kind {
+            TableKind::Wdl => value - 2,
+            TableKind::Dtz => self.dtz_value(pairs, value, wdl)?,
+        }))
+    }
+
+    /// Maps a position to its sub-table, given by the side to move and the file of the leading
+    /// pawn, and to its index in this sub-table
+    pub(super) fn index(&self, game: &ChessGame, flipped: bool) -> Option<(usize, usize, u64)> {
+        let encoding = &*ENCODING;
+        let white_to_play = game.white_to_play();
+
+        // The tables of the symmetric materials only store the positions with white to move
+        let flip = flipped || (self.symmetric && !white_to_play);
+        let flip_color = if flip { 8 } else { 0 };
+        let flip_squares = if flip { 56 } else { 0 };
+        let side_to_move = (flip ^ !white_to_play) as usize;
+
+        let mut squares = [0usize; MAX_PIECES];
+        let mut pieces = [0u8; MAX_PIECES];
+        let mut size = 0;
+        let mut lead_pawns = 0;
+        let mut lead_pawns_count = 0;
+        let mut file = 0;
+
+        // The tables with pawns are split by the file of the leading pawn
+        if self.has_pawns {
+            let code = self.pairs[0][0].pieces[0] ^ flip_color;
+            lead_pawns = game.pieces(Pawn, code < 8);
+            let mut bitboard = lead_pawns;
+            while bitboard != 0 {
+                squares[size] = bitboard.trailing_zeros() as usize ^ flip_squares;
+                size += 1;
+                bitboard &= bitboard - 1;
+            }
+            lead_pawns_count = size;
+            let lead = (0..lead_pawns_count).max_by_key(|&i| encoding.map_pawns[squares[i]])?;
+            squares.swap(0, lead);
+            file = (squares[0] % 8).min(7 - squares[0] % 8);
+        }
+
+        let pairs = &self.pairs[side_to_move % self.sides][file];
+
+        let mut bitboard = [Pawn, Knight, Bishop, Rook, Queen, King]
+            .into_iter()
+            .map(|piece| game.pieces(piece, true) | game.pieces(piece, false))
+            .fold(0, |all, pieces| all | pieces)
+            ^ lead_pawns;
+        while bitboard != 0 {
+            if size >= MAX_PIECES {
+                return None;
+            }
+            let square = bitboard.trailing_zeros() as usize;
+            let piece = game.type_at_index(square as i8)?;
+            let white = game.pieces(piece, true) & (1u64 << square) != 0;
+            squares[size] = square ^ flip_squares;
+            pieces[size] = piece_code(piece, white) ^ flip_color;
+            size += 1;
+            bitboard &= bitboard - 1;
+        }
+        if size != self.piece_count {
+            return None;
+        }
+
+        // Sorts the pieces in the order of the encoding of the table
+        for i in lead_pawns_count..size.saturating_sub(1) {
+            if let Some(j) = (i + 1..size).find(|&j| pairs.pieces[i] == pieces[j]) {
+                pieces.swap(i, j);
+                squares.swap(i, j);
+            }
+        }
+
+        // The first piece is mapped to the a1-d8 half of the board
+        if squares[0] % 8 > 3 {
+            squares.iter_mut().take(size).for_each(|square| *square ^= 7);
+        }
+
+        let mut index;
+        if self.has_pawns {
+            index = encoding.lead_pawn_index[lead_pawns_count][squares[0]];
+            squares[1..lead_pawns_count].sort_by_key(|&square| encoding.map_pawns[square]);
+            for (i, &square) in squares.iter().enumerate().take(lead_pawns_count).skip(1) {
+                index += encoding.binomial[i][encoding.map_pawns[square]];
+            }
+        } else {
+            // Without pawns, the first piece is mapped to the a1-d1-d4 triangle, and the first
+            // piece of the leading group outside of the a1-h8 diagonal below the diagonal
+            if squares[0] / 8 > 3 {
+                squares.iter_mut().take(size).for_each(|square| *square ^= 56);
+            }
+            for i in 0..pairs.group_length[0] {
+                let off = off_diagonal(squares[i]);
+                if off == 0 {
+                    continue;
+                }
+                if off > 0 {
+                    for square in squares.iter_mut().take(size).skip(i) {
+                        *square = ((*square >> 3) | (*square << 3)) & 63;
+                    }
+                }
+                break;
+            }
+            index = if self.has_unique_pieces {
+                encode_unique_pieces(encoding, &squares)? as u64
+            } else {
+                let kings = *encoding.map_kk.get(encoding.map_a1d1d4[squares[0]])?;
+                let code = kings[squares[1]];
+                (code != usize::MAX).then_some(code as u64)?
+            };
+        }
+
+        // Encodes the other groups, whose squares are counted among the free squares
+        index *= pairs.group_index[0];
+        let mut start = pairs.group_length[0];
+        let mut remaining_pawns = self.has_pawns && self.pawn_counts[1] > 0;
+        let mut next = 1;
+        while pairs.group_length[next] != 0 {
+            let length = pairs.group_length[next];
+            squares[start..start + length].sort_unstable();
+            let mut n = 0;
+            for i in 0..length {
+                let square = squares[start + i];
+                let adjust = squares[..start].iter().filter(|&&s| square > s).count();
+                let free = (square - adjust).checked_sub(8 * remaining_pawns as usize)?;
+                n += encoding.binomial[i + 1][free];
+            }
+            remaining_pawns = false;
+            index += n * pairs.group_index[next];
+            start += length;
+            next += 1;
+        }
+
+        Some((side_to_move, file, index))
+    }
+
+    /// Converts the value of a DTZ table to a number of plies
+    fn dtz_value(&self, pairs: &PairsData, mut value: i32, wdl: i32) -> Option<i32> {
+        const WDL_MAP: [usize; 5] = [1, 3, 0, 2, 0];
+        let map_index = pairs.map_index[*WDL_MAP.get((wdl + 2) as usize)?];
+        if pairs.flags & FLAG_MAPPED != 0 {
+            value = if pairs.flags & FLAG_WIDE != 0 {
+                read_u16_le(&self.data, self.map + 2 * (map_index + value as usize))? as i32
+            } else {
+                *self.data.get(self.map + map_index + value as usize)? as i32
+            };
+        }
+        // The distances are stored in moves, unless the flags tell otherwise
+        if (wdl == 2 && pairs.flags & FLAG_WIN_PLIES == 0)
+            || (wdl == -2 && pairs.flags & FLAG_LOSS_PLIES == 0)
+            || wdl == 1
+            || wdl == -1
+        {
+            value *= 2;
+        }
+        Some(value + 1)
+    }
+}
+
+/// Left and right symbols of the pair of a symbol, or the value of a leaf on the left
+fn tree_node(data: &[u8], pairs: &PairsData, symbol: usize) -> Option<(usize, usize)> {
+    let bytes = data.get(pairs.tree + 3 * symbol..pairs.tree + 3 * symbol + 3)?;
+    let left = (((bytes[1] & 0xF) as usize) << 8) | bytes[0] as usize;
+    let right = ((bytes[2] as usize) << 4) | (bytes[1] >> 4) as usize;
+    Some((left, right))
+}
+
+/// Computes the number of values minus one represented by a symbol, and the ones of the symbols
+/// of its pair
+fn symbol_length(
+    data: &[u8],
+    pairs: &mut PairsData,
+    symbol: usize,
+    visited: &mut [bool],
+) -> Option<u8> {
+    visited[symbol] = true;
+    let (left, right) = tree_node(data, pairs, symbol)?;
+    if right == 0xFFF {
+        return Some(0);
+    }
+    for child in [left, right] {
+        if !*visited.get(child)? {
+            pairs.symbol_lengths[child] = symbol_length(data, pairs, child, visited)?;
+        }
+    }
+    Some(
+        pairs.symbol_lengths[left]
+            .wrapping_add(pairs.symbol_lengths[right])
+            .wrapping_add(1),
+    )
+}
+
+/// Encodes the squares of the three first pieces, when they are all different, with the first one
+/// in the a1-d1-d4 triangle
+fn encode_unique_pieces(encoding: &Encoding, squares: &[usize; MAX_PIECES]) -> Option<usize> {
+    let [s0, s1, s2] = [squares[0], squares[1], squares[2]];
+    let adjust1 = (s1 > s0) as usize;
+    let adjust2 = (s2 > s0) as usize + (s2 > s1) as usize;
+    let rank = |square: usize| square / 8;
+
+    Some(if off_diagonal(s0) != 0 {
+        (encoding.map_a1d1d4[s0] * 63 + (s1 - adjust1)) * 62 + s2 - adjust2
+    } else if off_diagonal(s1) != 0 {
+        (6 * 63 + rank(s0) * 28 + encoding.map_b1h1h7[s1]) * 62 + s2 - adjust2
+    } else if off_diagonal(s2) != 0 {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + rank(s0) * 7 * 28
+            + (rank(s1).checked_sub(adjust1)?) * 28
+            + encoding.map_b1h1h7[s2]
+    } else {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + 4 * 7 * 28
+            + rank(s0) * 7 * 6
+            + (rank(s1).checked_sub(adjust1)?) * 6
+            + rank(s2).checked_sub(adjust2)?
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encoding_tables() {
+        let encoding = &*ENCODING;
+
+        // The 462 positions of the kings are numbered without gaps
+        let mut codes: Vec<usize> = encoding
+            .map_kk
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|&code| code != usize::MAX)
+            .collect();
+        codes.sort();
+        assert_eq!((0..462).collect::<Vec<_>>(), codes);
+
+        let mut codes: Vec<usize> = (0..64)
+            .map(|square| encoding.map_a1d1d4[square])
+            .filter(|&code| code != usize::MAX)
+            .collect();
+        codes.sort();
+        assert_eq!((0..10).collect::<Vec<_>>(), codes);
+
+        assert_eq!(48 * 47 / 2, encoding.binomial[2][48]);
+        assert_eq!(1, encoding.binomial[0][10]);
+        assert_eq!(47, encoding.map_pawns[8]);
+        assert_eq!(46, encoding.map_pawns[15]);
+        assert_eq!(6, encoding.lead_pawns_size[1][0]);
+    }
+
+    #[test]
+    fn test_decompress() {
+        // Three symbols: the leaves 0 (code 00) and 1 (code 01), and the pair [0, 1] (code 1).
+        // The values [0, 1, 1, 0, 1] are the codes 1, 01 and 1.
+        let mut data = vec![];
+        data.extend([2, 0, 0, 0]); // Lowest symbols of the lengths 1 and 2
+        data.extend([0, 0xF0, 0xFF, 1, 0xF0, 0xFF, 0, 0x10, 0]); // Tree
+        data.extend(4u16.to_le_bytes()); // Block lengths
+        data.extend(0u32.to_le_bytes()); // Sparse index
+        data.extend(4u16.to_le_bytes());
+        data.extend([0b1011_0000, 0, 0, 0, 0, 0, 0, 0]);
+
+        let mut pairs = PairsData {
+            max_symbol_length: 2,
+            min_symbol_length: 1,
+            block_size: 8,
+            span: 8,
+            blocks: 1,
+            lowest_symbol: 0,
+            tree: 4,
+            block_lengths: 13,
+            block_lengths_size: 1,
+            sparse_index: 15,
+            sparse_index_size: 1,
+            blocks_data: 21,
+            base: vec![1 << 63, 0],
+            symbol_lengths: vec![0; 3],
+            ..Default::default()
+        };
+        let mut visited = vec![false; 3];
+        for symbol in 0..3 {
+            if !visited[symbol] {
+                pairs.symbol_lengths[symbol] =
+                    symbol_length(&data, &mut pairs, symbol, &mut visited).unwrap();
+            }
+        }
+        assert_eq!(vec![0, 0, 1], pairs.symbol_lengths);
+
+        let table = Table {
+            data,
+            kind: TableKind::Wdl,
+            piece_count: 3,
+            has_pawns: false,
+            has_unique_pieces: true,
+            symmetric: false,
+            pawn_counts: [0, 0],
+            sides: 1,
+            pairs: vec![],
+            map: 0,
+        };
+        let values: Vec<u32> = (0..5).map(|i| table.decompress(&pairs, i).unwrap()).collect();
+        assert_eq!(vec![0, 1, 1, 0, 1], values);
+    }
+
+    #[test]
+    fn test_material() {
+        let material = Material::parse("KRPvKR").unwrap();
+        assert_eq!(5, material.piece_count());
+        assert!(material.has_pawns());
+        assert!(material.has_unique_pieces());
+        assert_eq!("KRvKRP", material.swapped().name());
+        assert_eq!([1, 0], material.pawn_counts());
+
+        assert!(Material::parse("KRvR").is_none());
+        assert!(Material::parse("KXvK").is_none());
+        assert!(Material::parse("KQQvKR").unwrap().has_unique_pieces());
+        assert!(!Material::parse("KNNvK").unwrap().has_unique_pieces());
+
+        let game = ChessGame::from_fen("8/8/8/8/3k4/8/1PR5/4K3 w - - 0 1");
+        assert_eq!("KRPvK", Material::of_game(&game).name());
+    }
+}
@@ -12,12 +12,7 @@ struct PuzzleAssert {
 }
 
 /// Given a puzzle, asserts that the engine finds all the best move.
-fn solve_puzzle(
-    mut engine: impl Engine,
-    mut game: ChessGame,
-    white_to_play: bool,
-    expected_answers: &[PuzzleAssert],
-) {
+fn solve_puzzle(mut engine: impl Engine, mut game: ChessGame, expected_answers: &[PuzzleAssert]) {
     game.block_castling();
     game.display();
 
@@ -26,10 +21,7 @@ fn solve_puzzle(
         puzzle_continuation,
     } in expected_answers
     {
-        let SearchResult {
-            score: _,
-            best_move,
-        } = engine.find_best_move(game, white_to_play);
+        let SearchResult { best_move, .. } = engine.find_best_move(game);
 
         // Asserts that the engine is correct
         assert_eq!(Some(*expected_best_move), best_move);
@@ -56,7 +48,6 @@ fn puzzle_1() {
     solve_puzzle(
         AlphaBetaEngine::new(6, 0),
         game,
-        true,
         &[
             // Rooks sacrifices, forces the king in h7 to captures the rook in g7
             PuzzleAssert {
@@ -78,7 +69,6 @@ fn practice_back_rank_mate_1() {
     solve_puzzle(
         AlphaBetaEngine::new(6, 0),
         game,
-        true,
         &[PuzzleAssert {
             expected_best_move: Move::from_str("e7", "e8", true),
             puzzle_continuation: None,
@@ -92,7 +82,6 @@ fn practice_back_rank_mate_2() {
     solve_puzzle(
         AlphaBetaEngine::new(6, 0),
         game,
-        true,
         &[
             PuzzleAssert {
                 expected_best_move: Move::from_str("e1", "e8", true),
@@ -120,7 +109,6 @@ fn practice_back_rank_mate_3() {
     solve_puzzle(
         IterativeDeepeningEngine::new(10, 0),
         game,
-        true,
         &[
             PuzzleAssert {
                 expected_best_move: Move::from_str("f3", "f7", true),
@@ -144,7 +132,6 @@ fn practice_hook_2_mate_in_3() {
     solve_puzzle(
         AlphaBetaEngine::new(7, 0),
         game,
-        true,
         &[
             PuzzleAssert {
                 expected_best_move: Move::from_str("e7", "g7", true),
@@ -172,7 +159,6 @@ fn practice_en_passant_mate_in_1() {
     solve_puzzle(
         AlphaBetaEngine::new(7, 0),
         game,
-        true,
         &[
             PuzzleAssert {
                 expected_best_move: Move::from_str("d5", "e6", true),
@@ -181,3 +167,55 @@ fn practice_en_passant_mate_in_1() {
         ],
     )
 }
+
+/// Plays the game with the engine on both sides, and asserts that the side to move mates within
+/// `max_moves` moves
+fn assert_mates(mut engine: impl Engine, fen: &str, max_moves: usize) {
+    let mut game = ChessGame::from_fen(fen);
+    game.block_castling();
+    let white_to_play = game.white_to_play();
+    for _ in 0..2 * max_moves {
+        let white = game.white_to_play();
+        let legal_moves = game.legal_moves(white);
+        if legal_moves.is_empty() {
+            assert!(game.is_in_check(white), "stalemate");
+            assert_eq!(white_to_play, !white, "the wrong side is mated");
+            return;
+        }
+        let SearchResult { best_move, .. } = engine.find_best_move(game);
+        // The move of the engine is replaced by the generated one, which has the right flags
+        let m = legal_moves
+            .into_iter()
+            .find(|m| Some(*m) == best_move)
+            .expect("The engine plays legal moves");
+        game.apply_move_unsafe(&m);
+    }
+    game.display();
+    panic!("No mate in {max_moves} moves");
+}
+
+// The longest mates of these endgames take 10, 16 and 33 moves
+#[test]
+fn endgame_king_and_queen_mates() {
+    assert_mates(AlphaBetaEngine::new(7, 2), "8/8/8/4k3/8/8/8/3QK3 w - - 0 1", 10);
+    assert_mates(AlphaBetaEngine::new(7, 2), "3qk3/8/8/8/4K3/8/8/8 b - - 0 1", 10);
+}
+
+#[test]
+fn endgame_king_and_rook_mates() {
+    assert_mates(AlphaBetaEngine::new(7, 2), "8/8/8/4k3/8/8/8/R3K3 w - - 0 1", 16);
+}
+
+#[test]
+fn endgame_bishop_and_knight_mates() {
+    // The king is already in the corner of the bishop, but the mate is still too far for a fixed
+    // depth: the engine only finds it with the deeper searches of the iterative deepening
+    assert_mates(IterativeDeepeningEngine::new(10, 2), "8/8/8/8/3B4/1K6/8/1k1N4 w - - 0 1", 33);
+}
+
+#[test]
+fn endgame_king_and_pawn_promotes() {
+    // The pawn can only promote if the king keeps the opposition
+    assert_mates(AlphaBetaEngine::new(7, 2), "4k3/8/4K3/4P3/8/8/8/8 w - - 0 1", 20);
+    assert_mates(AlphaBetaEngine::new(7, 2), "8/8/8/8/4p3/4k3/8/4K3 b - - 0 1", 20);
+}
@@ -0,0 +1,235 @@
+use model::game::ChessGame;
+use model::moves::Move;
+
+/// A position of a test suite in the Extended Position Description format, such as
+/// `6k1/4Rppp/8/8/8/8/5PPP/6K1 w - - bm Re8#; dm 1; id "back rank";`.
+///
+/// Only the operations used by tactical suites are read: `bm` (best moves), `am` (moves to avoid),
+/// `dm` (direct mate in a number of moves) and `id`. The other ones are ignored.
+#[derive(Debug)]
+pub struct EpdPosition {
+    pub game: ChessGame,
+    pub id: Option<String>,
+    pub best_moves: Vec<Move>,
+    pub avoid_moves: Vec<Move>,
+    pub mate_in: Option<usize>,
+}
+
+/// Splits the operations of an EPD line into their opcode and operands. Operands between quotes
+/// may contain spaces and semicolons.
+fn split_operations(text: &str) -> Result<Vec<(String, Vec<String>)>, String> {
+    let mut operations = vec![];
+    let mut words: Vec<String> = vec![];
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ';' => {
+                if !words.is_empty() {
+                    let opcode = words.remove(0);
+                    operations.push((opcode, std::mem::take(&mut words)));
+                }
+            }
+            '"' => {
+                let mut word = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("Unterminated string".to_string()),
+                    }
+                }
+                words.push(word);
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == ';' || c == '"' {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                words.push(word);
+            }
+        }
+    }
+    if !words.is_empty() {
+        return Err(format!(
+            "Missing semicolon after the operation {}",
+            words[0]
+        ));
+    }
+    Ok(operations)
+}
+
+impl EpdPosition {
+    /// Parses a line of an EPD file. The castling field is only used to forbid castling when it
+    /// is `-`, since the model doesn't keep separate castling rights for each side.
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut fields = line.trim().splitn(5, char::is_whitespace);
+        let board = fields.next().unwrap_or_default();
+        let side = fields.next().ok_or("Missing side to move")?;
+        let castling = fields.next().ok_or("Missing castling rights")?;
+        let en_passant = fields.next().ok_or("Missing en passant square")?;
+        let operations = split_operations(fields.next().unwrap_or_default())?;
+
+        let valid_board = board.split('/').count() == 8
+            && board.chars().all(|c| "pnbrqkPNBRQK12345678/".contains(c));
+        if !valid_board {
+            return Err(format!("Invalid board: {board}"));
+        }
+        let white_to_play = match side {
+            "w" => true,
+            "b" => false,
+            _ => return Err(format!("Invalid side to move: {side}")),
+        };
+        let mut game = ChessGame::from_fen(&format!("{board} {side} {castling} {en_passant} 0 1"));
+        if game.is_finished() {
+            return Err("Both kings are needed".to_string());
+        }
+        if castling == "-" {
+            game.block_castling();
+        }
+
+        let mut position = Self {
+            game,
+            id: None,
+            best_moves: vec![],
+            avoid_moves: vec![],
+            mate_in: None,
+        };
+        for (opcode, operands) in operations {
+            match opcode.as_str() {
+                "bm" | "am" => {
+                    let moves = operands
+                        .iter()
+                        .map(|san| {
+                            game.from_san(san, white_to_play)
+                                .ok_or(format!("Invalid move for {opcode}: {san}"))
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if opcode == "bm" {
+                        position.best_moves = moves;
+                    } else {
+                        position.avoid_moves = moves;
+                    }
+                }
+                "dm" => {
+                    let moves = operands.first().and_then(|n| n.parse().ok());
+                    position.mate_in = Some(moves.ok_or("Invalid number of moves for dm")?);
+                }
+                "id" => position.id = operands.first().cloned(),
+                _ => {}
+            }
+        }
+        Ok(position)
+    }
+
+    /// Returns true if the move found by an engine solves the position: it must be one of the
+    /// best moves, and none of the moves to avoid. For mates, the engine must also have seen a
+    /// mate of the side to move in at most `mate_in` moves, given by `found_mate`.
+    pub fn is_solved(&self, found: Option<Move>, found_mate: Option<usize>) -> bool {
+        let Some(found) = found else {
+            return false;
+        };
+        let mate_found = match (self.mate_in, found_mate) {
+            (None, _) => true,
+            (Some(mate_in), Some(moves)) => moves <= mate_in,
+            (Some(_), None) => false,
+        };
+        (self.best_moves.is_empty() || self.best_moves.contains(&found))
+            && !self.avoid_moves.contains(&found)
+            && mate_found
+    }
+}
+
+/// Parses the lines of an EPD file, skipping the empty lines and the comments starting with `#`.
+/// The errors are returned with their line number.
+pub fn parse_suite(text: &str) -> Vec<Result<EpdPosition, String>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| EpdPosition::parse(line).map_err(|e| format!("line {}: {e}", i + 1)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::epd::{parse_suite, split_operations, EpdPosition};
+    use model::moves::Move;
+
+    #[test]
+    fn test_split_operations() {
+        let operations = split_operations(r#"bm Nf3 e4; id "WAC.001; test"; c0 "a b";"#).unwrap();
+        assert_eq!(3, operations.len());
+        assert_eq!("bm", operations[0].0);
+        assert_eq!(vec!["Nf3", "e4"], operations[0].1);
+        assert_eq!(vec!["WAC.001; test"], operations[1].1);
+        assert_eq!(vec!["a b"], operations[2].1);
+
+        assert!(split_operations("bm Nf3").is_err());
+        assert!(split_operations(r#"id "WAC;"#).is_err());
+    }
+
+    #[test]
+    fn test_parse() {
+        let position =
+            EpdPosition::parse(r#"6k1/4Rppp/8/8/8/8/5PPP/6K1 w - - bm Re8#; dm 1; id "mate";"#)
+                .unwrap();
+        assert!(position.game.white_to_play());
+        assert_eq!(Some("mate".to_string()), position.id);
+        assert_eq!(vec![Move::from_str("e7", "e8", true)], position.best_moves);
+        assert_eq!(Some(1), position.mate_in);
+
+        let position =
+            EpdPosition::parse("r3k3/8/8/8/8/8/8/4K3 b q - am Ra1+ Kd7; hmvc 0;").unwrap();
+        assert!(!position.game.white_to_play());
+        assert_eq!(None, position.id);
+        assert_eq!(
+            vec![
+                Move::from_str("a8", "a1", false),
+                Move::from_str("e8", "d7", false)
+            ],
+            position.avoid_moves
+        );
+
+        assert!(EpdPosition::parse("6k1/8/8/8/8/8/8/6K1 w - - bm Re8;").is_err());
+        assert!(EpdPosition::parse("6k1/8/8/8/8/8/8/6K1 x - - id \"a\";").is_err());
+        assert!(EpdPosition::parse("6k1/8/8/8/8/8/8/6X1 w - -").is_err());
+        assert!(EpdPosition::parse("8/8/8/8/8/8/8/6K1 w - -").is_err());
+        assert!(EpdPosition::parse("6k1/8/8/8/8/8/8/6K1").is_err());
+    }
+
+    #[test]
+    fn test_is_solved() {
+        let position = EpdPosition::parse("r3k3/8/8/8/8/8/8/4K3 b q - bm Ra1+; am Kd7;").unwrap();
+        assert!(position.is_solved(Some(Move::from_str("a8", "a1", false)), None));
+        assert!(!position.is_solved(Some(Move::from_str("e8", "d7", false)), None));
+        assert!(!position.is_solved(None, None));
+
+        let position = EpdPosition::parse("r3k3/8/8/8/8/8/8/4K3 b q - am Kd7;").unwrap();
+        assert!(position.is_solved(Some(Move::from_str("a8", "a2", false)), None));
+
+        let position =
+            EpdPosition::parse("6k1/4Rppp/8/8/8/8/5PPP/6K1 w - - bm Re8#; dm 1;").unwrap();
+        assert!(position.is_solved(Some(Move::from_str("e7", "e8", true)), Some(1)));
+        assert!(!position.is_solved(Some(Move::from_str("e7", "e8", true)), None));
+        // A longer mate doesn't solve the position
+        assert!(!position.is_solved(Some(Move::from_str("e7", "e8", true)), Some(2)));
+    }
+
+    #[test]
+    fn test_parse_suite() {
+        let suite = parse_suite(include_str!("../suites/mates.epd"));
+        assert_eq!(4, suite.len());
+        assert!(suite.iter().all(Result::is_ok));
+
+        let suite = parse_suite("# comment\n\n6k1/8/8/8/8/8/8/6K1 w - - bm Re8;\n");
+        assert_eq!(1, suite.len());
+        assert_eq!(
+            Some("line 3: Invalid move for bm: Re8"),
+            suite[0].as_ref().err().map(String::as_str)
+        );
+    }
+}
@@ -0,0 +1,106 @@
+//! Runs an engine over a suite of tactical positions in the EPD format, such as WAC or ECM, and
+//! reports the solved positions with the time and the nodes of the searches.
+//!
+//! Usage: `epd_runner <suite file> [options]`, with the options:
+//!
+//! - `--engine <kind>`: `alphabeta` or `iterative` (default: `iterative`)
+//! - `--depth <n>`, `--extra-depth <n>`: depth of the searches, and extra depth for the captures
+//!   (default: 6 and 0)
+//! - `--time <ms>`: time limit of each search, with the `iterative` engine. The deepening stops
+//!   after the first iteration which ends after the limit.
+//! - `--uci <command>`: external UCI engine to use instead, searching to `--depth` or for `--time`
+//!
+//! A position is solved when the engine plays one of its best moves (`bm`) and none of the moves
+//! to avoid (`am`). For direct mates (`dm`), the engine must also announce a mate in at most the
+//! given number of moves.
+
+use crate::epd::{parse_suite, EpdPosition};
+use cli_options::{create_engine, parse_options};
+use engine::alpha_beta::mate_moves;
+use std::time::{Duration, Instant};
+
+mod epd;
+
+fn describe(position: &EpdPosition) -> String {
+    let game = &position.game;
+    let moves = |moves: &[model::moves::Move]| {
+        moves
+            .iter()
+            .map(|m| game.to_san(m))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let mut expected = vec![];
+    if !position.best_moves.is_empty() {
+        expected.push(format!("bm {}", moves(&position.best_moves)));
+    }
+    if !position.avoid_moves.is_empty() {
+        expected.push(format!("am {}", moves(&position.avoid_moves)));
+    }
+    if let Some(mate_in) = position.mate_in {
+        expected.push(format!("dm {mate_in}"));
+    }
+    expected.join(", ")
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(path) = args.first() else {
+        eprintln!("Usage: epd_runner <suite file> [--engine <kind>] [--depth <n>] [--extra-depth <n>] [--time <ms>]");
+        std::process::exit(1);
+    };
+    let options = parse_options(&args[1..]);
+    let text = std::fs::read_to_string(path).expect("Can't read the suite file");
+
+    let mut solved = 0;
+    let mut total = 0;
+    let mut total_time = Duration::ZERO;
+    let mut total_nodes = 0;
+    for (i, position) in parse_suite(&text).into_iter().enumerate() {
+        let position = match position {
+            Ok(position) => position,
+            Err(e) => {
+                eprintln!("Skipped {e}");
+                continue;
+            }
+        };
+
+        let mut engine = create_engine(&options);
+        let start = Instant::now();
+        let result = engine.find_best_move(position.game);
+        let time = start.elapsed();
+
+        // Only the mates of the side to move count
+        let found_mate = mate_moves(result.score)
+            .filter(|_| (result.score > 0) == position.game.white_to_play());
+        let is_solved = position.is_solved(result.best_move, found_mate);
+        let found = result
+            .best_move
+            .map_or("none".to_string(), |m| position.game.to_san(&m));
+        let id = position.id.clone().unwrap_or(format!("#{}", i + 1));
+        println!(
+            "{:<8} {id:<30} found {found:<8} expected {:<20} {:>8.3} s {:>12} nodes",
+            if is_solved { "solved" } else { "FAILED" },
+            describe(&position),
+            time.as_secs_f64(),
+            result.nodes
+        );
+
+        total += 1;
+        solved += is_solved as usize;
+        total_time += time;
+        total_nodes += result.nodes;
+    }
+
+    if total == 0 {
+        eprintln!("No position in the suite");
+        std::process::exit(1);
+    }
+    println!();
+    println!("Solved        : {solved} / {total}");
+    println!(
+        "Average time  : {:.3} s",
+        total_time.as_secs_f64() / total as f64
+    );
+    println!("Average nodes : {}", total_nodes / total as u64);
+}
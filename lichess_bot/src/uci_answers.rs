@@ -1,3 +1,6 @@
+use engine::engine::SearchResult;
+use model::chess_type::Type::{Bishop, Knight, Rook};
+use model::chess_type::{ScoreType, PAWN_SCORE};
 use model::moves::Move;
 use model::utils::index_to_chesspos;
 
@@ -7,7 +10,38 @@ pub(crate) enum UciAnswer {
     Initialize,
     Debug(String),
     EngineReady,
-    BestMove(Move)
+    BestMove(Move),
+    /// Result of a MultiPV search: one `info` line per analysed line, followed by the best move.
+    Analysis {
+        lines: Vec<SearchResult>,
+        white_to_move: bool,
+    },
+}
+
+/// Formats a move as expected by the UCI protocol (e.g. `e2e4`, or `e7e8n` for an underpromotion)
+fn format_move(mv: &Move) -> String {
+    let underpromotion = match mv.underpromotion {
+        Some(Knight) => "n",
+        Some(Bishop) => "b",
+        Some(Rook) => "r",
+        _ => "",
+    };
+    format!(
+        "{}{}{underpromotion}",
+        index_to_chesspos(mv.from),
+        index_to_chesspos(mv.to)
+    )
+}
+
+/// Converts a score of the engine (from white's point of view) into centipawns from the point of
+/// view of the engine, as expected by the UCI protocol.
+fn score_to_centipawns(score: ScoreType, white_to_move: bool) -> ScoreType {
+    let centipawns = score * 100 / PAWN_SCORE;
+    if white_to_move {
+        centipawns
+    } else {
+        -centipawns
+    }
 }
 
 
@@ -18,13 +52,28 @@ impl UciAnswer {
     pub(crate) fn into_formatted(self) -> (Option<String>, Option<String>) {
         match self {
             UciAnswer::None => (None, None),
-            UciAnswer::Initialize => (Some("id name Chessean \n id author Arthur Bricq \nuciok".to_string()), None),
+            UciAnswer::Initialize => (Some("id name Chessean \n id author Arthur Bricq \noption name MultiPV type spin default 1 min 1 max 256\noption name OwnBook type check default false\noption name BookFile type string default <empty>\noption name SyzygyPath type string default <empty>\nuciok".to_string()), None),
             UciAnswer::Debug(message) => (None, Some(message)),
             UciAnswer::EngineReady => (Some("readyok".to_string()), None),
-            UciAnswer::BestMove(mv) => {
-                let from = index_to_chesspos(mv.from);
-                let to = index_to_chesspos(mv.to);
-                (Some(format!("bestmove {from}{to}")), None)
+            UciAnswer::BestMove(mv) => (Some(format!("bestmove {}", format_move(&mv))), None),
+            UciAnswer::Analysis { lines, white_to_move } => {
+                let mut output: Vec<String> = lines
+                    .iter()
+                    .enumerate()
+                    .map(|(i, line)| {
+                        let pv: Vec<String> = line.pv.iter().map(format_move).collect();
+                        format!(
+                            "info multipv {} score cp {} pv {}",
+                            i + 1,
+                            score_to_centipawns(line.score, white_to_move),
+                            pv.join(" ")
+                        )
+                    })
+                    .collect();
+                if let Some(best_move) = lines.first().and_then(|line| line.best_move) {
+                    output.push(format!("bestmove {}", format_move(&best_move)));
+                }
+                (Some(output.join("\n")), None)
             }
         }
     }
@@ -1,15 +1,24 @@
 use crate::uci_answers::UciAnswer;
+use engine::book::OpeningBook;
 use engine::engine::{Engine, SearchResult};
 use engine::iterative_deepening::IterativeDeepeningEngine;
+use engine::tablebase::syzygy::SyzygyTablebase;
+use model::chess_type::Type::{Bishop, Knight, Rook};
 use model::game::ChessGame;
 use model::moves::Move;
 use model::utils::ChessPosition;
-use vampirc_uci::{UciMessage, UciMove, UciSquare};
+use std::sync::Arc;
+use vampirc_uci::{UciMessage, UciMove, UciPiece, UciSquare};
 
 pub(crate) struct UciPlayer {
     game: ChessGame,
     solver: IterativeDeepeningEngine,
-    white_to_move: bool,
+    /// Number of lines to analyse, set with the UCI option `MultiPV`
+    multi_pv: usize,
+    /// Opening book, loaded with the UCI option `BookFile`
+    book: Option<OpeningBook>,
+    /// Whether the moves of the book are played, set with the UCI option `OwnBook`
+    own_book: bool,
 }
 
 impl UciPlayer {
@@ -17,7 +26,9 @@ impl UciPlayer {
         Self {
             game: Default::default(),
             solver: IterativeDeepeningEngine::new(7, 0),
-            white_to_move: true,
+            multi_pv: 1,
+            book: None,
+            own_book: false,
         }
     }
 
@@ -30,6 +41,7 @@ impl UciPlayer {
                 self.set_game_to_default();
                 UciAnswer::None
             }
+            UciMessage::SetOption { name, value } => self.set_option(&name, value),
             UciMessage::Position {
                 startpos,
                 fen,
@@ -44,7 +56,14 @@ impl UciPlayer {
                 }
 
                 self.play_moves(moves);
-                UciAnswer::BestMove(self.find_best_move())
+                if self.multi_pv > 1 {
+                    UciAnswer::Analysis {
+                        lines: self.solver.find_best_moves(self.game, self.multi_pv),
+                        white_to_move: self.game.white_to_play(),
+                    }
+                } else {
+                    UciAnswer::BestMove(self.find_best_move())
+                }
             }
             UciMessage::Go { .. } => {
                 // TODO handle settings ?
@@ -54,23 +73,78 @@ impl UciPlayer {
         }
     }
 
+    fn set_option(&mut self, name: &str, value: Option<String>) -> UciAnswer {
+        match (name, value.as_deref().map(str::trim)) {
+            ("MultiPV", Some(count)) => match count.parse::<usize>() {
+                Ok(count) if count > 0 => {
+                    self.multi_pv = count;
+                    UciAnswer::None
+                }
+                _ => UciAnswer::Debug(format!("Invalid value for MultiPV: {count}")),
+            },
+            ("OwnBook", Some(enabled)) => {
+                self.own_book = enabled == "true";
+                UciAnswer::None
+            }
+            ("BookFile", None | Some("") | Some("<empty>")) => {
+                self.book = None;
+                UciAnswer::None
+            }
+            ("BookFile", Some(path)) => match OpeningBook::open(path) {
+                Ok(book) => {
+                    self.book = Some(book);
+                    UciAnswer::None
+                }
+                Err(e) => {
+                    self.book = None;
+                    UciAnswer::Debug(format!("Can't open the book {path}: {e}"))
+                }
+            },
+            ("SyzygyPath", None | Some("") | Some("<empty>")) => {
+                self.solver.set_tablebase(None);
+                UciAnswer::None
+            }
+            ("SyzygyPath", Some(paths)) => match SyzygyTablebase::open(paths) {
+                Ok(tablebase) => {
+                    self.solver.set_tablebase(Some(Arc::new(tablebase)));
+                    UciAnswer::None
+                }
+                Err(e) => {
+                    self.solver.set_tablebase(None);
+                    UciAnswer::Debug(format!("Can't open the tablebases {paths}: {e}"))
+                }
+            },
+            (name, value) => {
+                UciAnswer::Debug(format!("Unsupported option: {name} = {value:?}"))
+            }
+        }
+    }
+
     fn set_game_to_default(&mut self) {
         self.game = ChessGame::standard_game();
-        self.white_to_move = true;
     }
 
     fn play_moves(&mut self, moves: Vec<UciMove>) {
         for mv in moves {
-            let mv = uci_move_to_move(mv, self.white_to_move);
+            let mv = uci_move_to_move(mv, self.game.white_to_play());
             self.game.apply_move_unsafe(&mv);
-            self.white_to_move = !self.white_to_move;
         }
     }
 
     fn find_best_move(&mut self) -> Move {
+        // The book is used as long as the game is in it
+        if self.own_book {
+            let book_move = self
+                .book
+                .as_mut()
+                .and_then(|book| book.choose_move(&self.game));
+            if let Some(book_move) = book_move {
+                return book_move;
+            }
+        }
+
         // Once all the moves are applied, response with the best move
-        let SearchResult { score: _, best_move } =
-            self.solver.find_best_move(self.game, self.white_to_move);
+        let SearchResult { best_move, .. } = self.solver.find_best_move(self.game);
         // TODO error handling should be better than this
         best_move.unwrap()
     }
@@ -95,15 +169,23 @@ fn uci_move_to_move(uci_move: UciMove, is_white: bool) -> Move {
         to: uci_square_to_chess_position(uci_move.to),
         is_white,
         quality: Default::default(), // Default quality; modify if needed
+        underpromotion: match uci_move.promotion {
+            Some(UciPiece::Knight) => Some(Knight),
+            Some(UciPiece::Bishop) => Some(Bishop),
+            Some(UciPiece::Rook) => Some(Rook),
+            _ => None,
+        },
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::uci_player::UciPlayer;
+    use model::chess_type::Type::Knight;
     use vampirc_uci::parse;
     use model::utils::index_to_chesspos;
     use crate::uci_answers::UciAnswer;
+    use engine::iterative_deepening::IterativeDeepeningEngine;
 
     #[test]
     fn test_simple_position() {
@@ -129,4 +211,106 @@ mod tests {
 
     }
 
+    #[test]
+    fn test_side_to_move_from_fen() {
+        let mut uci_player = UciPlayer::new();
+        uci_player.solver = IterativeDeepeningEngine::new(3, 0);
+        let commands =
+            parse("position fen rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+        let last_answer = commands
+            .into_iter()
+            .map(|m| uci_player.handle_message(m))
+            .last()
+            .expect("No answer");
+
+        match last_answer {
+            UciAnswer::BestMove(m) => assert!(uci_player.game.is_black_at(m.from)),
+            _ => panic!("Expecting a best move, got: {:?}", last_answer),
+        }
+    }
+
+    #[test]
+    fn test_underpromotion() {
+        let mut uci_player = UciPlayer::new();
+        uci_player.solver = IterativeDeepeningEngine::new(2, 0);
+        for message in parse("position fen 8/5P1k/8/8/8/8/8/K7 w - - 0 1 moves f7f8n") {
+            uci_player.handle_message(message);
+        }
+        assert_eq!(Some(Knight), uci_player.game.type_at_index(61));
+    }
+
+    #[test]
+    fn test_opening_book() {
+        let mut uci_player = UciPlayer::new();
+        uci_player.solver = IterativeDeepeningEngine::new(3, 0);
+        let book = concat!(env!("CARGO_MANIFEST_DIR"), "/../engine/books/fixture.bin");
+        let commands = parse(&format!(
+            "setoption name OwnBook value true\nsetoption name BookFile value {book}\nposition startpos"
+        ));
+        let last_answer = commands
+            .into_iter()
+            .map(|m| uci_player.handle_message(m))
+            .last()
+            .expect("No answer");
+        match last_answer {
+            UciAnswer::BestMove(m) => {
+                let m = format!("{}{}", index_to_chesspos(m.from), index_to_chesspos(m.to));
+                assert!(m == "e2e4" || m == "d2d4", "{m} is not in the book");
+            }
+            _ => panic!("Expecting a best move, got: {:?}", last_answer),
+        }
+
+        // Out of the book, the engine searches the position
+        let commands = parse("position startpos moves e2e4 c7c5 g1f3");
+        let last_answer = commands
+            .into_iter()
+            .map(|m| uci_player.handle_message(m))
+            .last()
+            .expect("No answer");
+        match last_answer {
+            UciAnswer::BestMove(m) => assert!(uci_player.game.is_black_at(m.from)),
+            _ => panic!("Expecting a best move, got: {:?}", last_answer),
+        }
+
+        // An invalid book is reported
+        let commands = parse("setoption name BookFile value /does/not/exist.bin");
+        let answer = uci_player.handle_message(commands.into_iter().next().unwrap());
+        assert!(matches!(answer, UciAnswer::Debug(_)));
+        assert!(uci_player.book.is_none());
+    }
+
+    #[test]
+    fn test_syzygy_path_option() {
+        let mut uci_player = UciPlayer::new();
+        let commands = parse("setoption name SyzygyPath value /does/not/exist");
+        let answer = uci_player.handle_message(commands.into_iter().next().unwrap());
+        assert!(matches!(answer, UciAnswer::Debug(_)));
+
+        let commands = parse("setoption name SyzygyPath value <empty>");
+        let answer = uci_player.handle_message(commands.into_iter().next().unwrap());
+        assert!(matches!(answer, UciAnswer::None));
+    }
+
+    #[test]
+    fn test_multi_pv_option() {
+        let mut uci_player = UciPlayer::new();
+        uci_player.solver = IterativeDeepeningEngine::new(3, 0);
+
+        let commands = parse("setoption name MultiPV value 3\nposition startpos moves e2e4");
+        let last_answer = commands
+            .into_iter()
+            .map(|m| uci_player.handle_message(m))
+            .last()
+            .expect("No answer");
+
+        let (output, _) = last_answer.into_formatted();
+        let output = output.expect("Expecting an analysis");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(4, lines.len());
+        assert!(lines[0].starts_with("info multipv 1 score cp "));
+        assert!(lines[1].starts_with("info multipv 2 score cp "));
+        assert!(lines[2].starts_with("info multipv 3 score cp "));
+        assert!(lines[3].starts_with("bestmove "));
+    }
+
 }
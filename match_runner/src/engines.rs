@@ -0,0 +1,122 @@
+use engine::alpha_beta::AlphaBetaEngine;
+use engine::engine::Engine;
+use engine::iterative_deepening::IterativeDeepeningEngine;
+use std::path::Path;
+use uci_client::{SearchLimit, UciEngineClient};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EngineKind {
+    AlphaBeta,
+    IterativeDeepening,
+    /// External UCI engine, started with the given command
+    Uci(String),
+}
+
+/// Configuration of an engine taking part in a match, written as `<kind>:<depth>[:<extra depth>]`
+/// where the kind is `alphabeta` or `iterative`, for instance `alphabeta:5:2`, or as
+/// `uci:<depth>:<command>` for an external UCI engine searching at the given depth.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EngineConfig {
+    pub kind: EngineKind,
+    pub depth: usize,
+    pub extra_depth: usize,
+}
+
+impl EngineConfig {
+    pub fn parse(text: &str) -> Option<Self> {
+        if let Some(uci) = text.strip_prefix("uci:") {
+            let (depth, command) = uci.split_once(':')?;
+            let depth = depth.parse().ok()?;
+            if depth == 0 || command.is_empty() {
+                return None;
+            }
+            return Some(Self {
+                kind: EngineKind::Uci(command.to_string()),
+                depth,
+                extra_depth: 0,
+            });
+        }
+
+        let mut parts = text.split(':');
+        let kind = match parts.next()? {
+            "alphabeta" => EngineKind::AlphaBeta,
+            "iterative" => EngineKind::IterativeDeepening,
+            _ => return None,
+        };
+        let depth = parts.next()?.parse().ok()?;
+        let extra_depth = match parts.next() {
+            Some(extra_depth) => extra_depth.parse().ok()?,
+            None => 0,
+        };
+        if parts.next().is_some() || depth == 0 {
+            return None;
+        }
+        Some(Self {
+            kind,
+            depth,
+            extra_depth,
+        })
+    }
+
+    /// Name of the engine in the reports and in the PGN files
+    pub fn name(&self) -> String {
+        let kind = match &self.kind {
+            EngineKind::AlphaBeta => "alphabeta",
+            EngineKind::IterativeDeepening => "iterative",
+            EngineKind::Uci(command) => {
+                let name = Path::new(command)
+                    .file_name()
+                    .map_or(command.clone(), |name| name.to_string_lossy().to_string());
+                return format!("{name}:{}", self.depth);
+            }
+        };
+        format!("{kind}:{}:{}", self.depth, self.extra_depth)
+    }
+
+    /// Creates a new instance of the engine, with empty tables. External engines are started
+    /// again, which fails if the command is not a UCI engine.
+    pub fn create(&self) -> Box<dyn Engine> {
+        match &self.kind {
+            EngineKind::AlphaBeta => Box::new(AlphaBetaEngine::new(self.depth, self.extra_depth)),
+            EngineKind::IterativeDeepening => {
+                Box::new(IterativeDeepeningEngine::new(self.depth, self.extra_depth))
+            }
+            EngineKind::Uci(command) => {
+                let mut client = UciEngineClient::spawn(command.as_str(), &[])
+                    .unwrap_or_else(|e| panic!("Can't start the engine {command}: {e}"));
+                client.set_limit(SearchLimit::Depth(self.depth));
+                Box::new(client)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engines::{EngineConfig, EngineKind};
+
+    #[test]
+    fn test_parse() {
+        let config = EngineConfig::parse("alphabeta:5:2").unwrap();
+        assert_eq!(EngineKind::AlphaBeta, config.kind);
+        assert_eq!(5, config.depth);
+        assert_eq!(2, config.extra_depth);
+        assert_eq!("alphabeta:5:2", config.name());
+
+        let config = EngineConfig::parse("iterative:4").unwrap();
+        assert_eq!(EngineKind::IterativeDeepening, config.kind);
+        assert_eq!("iterative:4:0", config.name());
+
+        assert!(EngineConfig::parse("minimax:4").is_none());
+        assert!(EngineConfig::parse("alphabeta").is_none());
+        assert!(EngineConfig::parse("alphabeta:0").is_none());
+        assert!(EngineConfig::parse("alphabeta:4:2:1").is_none());
+
+        let config = EngineConfig::parse("uci:6:/usr/bin/stockfish").unwrap();
+        assert_eq!(EngineKind::Uci("/usr/bin/stockfish".to_string()), config.kind);
+        assert_eq!(6, config.depth);
+        assert_eq!("stockfish:6", config.name());
+        assert!(EngineConfig::parse("uci:6").is_none());
+        assert!(EngineConfig::parse("uci:six:stockfish").is_none());
+    }
+}
@@ -0,0 +1,166 @@
+//! Plays a match between two engines, and reports the results with the Elo difference and the
+//! verdict of a sequential probability ratio test.
+//!
+//! Usage: `engine_match [options]`, with the options:
+//!
+//! - `--engine1 <config>`, `--engine2 <config>`: the engines, written as `<kind>:<depth>[:<extra>]`
+//!   where the kind is `alphabeta` or `iterative` (default: `alphabeta:4:2` and `iterative:4:2`),
+//!   or `uci:<depth>:<command>` for an external UCI engine
+//! - `--games <n>`: maximum number of games (default: 20)
+//! - `--openings <file>`: file with one FEN per line. Each opening is played twice, each engine
+//!   playing white once (default: the standard position)
+//! - `--pgn <file>`: file in which the games are written (default: `match.pgn`)
+//! - `--max-moves <n>`: games are drawn after this number of moves (default: 150)
+//! - `--resign-score <cp>`, `--resign-moves <n>`: a game is won when both engines evaluate it beyond
+//!   the score for this number of moves each (default: 1000 and 4)
+//! - `--elo0 <elo>`, `--elo1 <elo>`, `--alpha <p>`, `--beta <p>`: parameters of the SPRT
+//!   (default: 0, 10, 0.05 and 0.05). The match stops when the test is conclusive.
+
+use crate::engines::EngineConfig;
+use crate::play::{play_game, Adjudication, GameResult};
+use crate::stats::{MatchStats, Sprt, SprtVerdict};
+use cli_options::{option, parse_options};
+use model::chess_type::ScoreType;
+use model::game::ChessGame;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+
+mod engines;
+mod pgn;
+mod play;
+mod stats;
+
+fn engine_config(options: &HashMap<String, String>, name: &str, default: &str) -> EngineConfig {
+    let text = options.get(name).map(String::as_str).unwrap_or(default);
+    EngineConfig::parse(text).unwrap_or_else(|| {
+        eprintln!("Invalid engine for --{name}: {text}");
+        std::process::exit(1);
+    })
+}
+
+/// Reads the openings, written in the FEN format
+fn read_openings(path: Option<&String>) -> Vec<ChessGame> {
+    let Some(path) = path else {
+        return vec![ChessGame::standard_game()];
+    };
+    let text = std::fs::read_to_string(path).expect("Can't read the openings file");
+    let openings: Vec<ChessGame> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(ChessGame::from_fen)
+        .collect();
+    assert!(!openings.is_empty(), "The openings file is empty");
+    openings
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = parse_options(&args);
+    let engine1 = engine_config(&options, "engine1", "alphabeta:4:2");
+    let engine2 = engine_config(&options, "engine2", "iterative:4:2");
+    let games: usize = option(&options, "games", 20);
+    let openings = read_openings(options.get("openings"));
+    let pgn_path = options
+        .get("pgn")
+        .cloned()
+        .unwrap_or("match.pgn".to_string());
+    let adjudication = Adjudication {
+        max_plies: 2 * option::<usize>(&options, "max-moves", 150),
+        resign_score: option::<ScoreType>(&options, "resign-score", 1000),
+        resign_plies: 2 * option::<usize>(&options, "resign-moves", 4),
+    };
+    let sprt = Sprt {
+        elo0: option(&options, "elo0", 0.0),
+        elo1: option(&options, "elo1", 10.0),
+        alpha: option(&options, "alpha", 0.05),
+        beta: option(&options, "beta", 0.05),
+    };
+
+    let (name1, name2) = (engine1.name(), engine2.name());
+    let event = format!("{name1} vs {name2}");
+    let mut pgn_file = File::create(&pgn_path).expect("Can't create the PGN file");
+    let mut stats = MatchStats::default();
+    let mut verdict = SprtVerdict::Continue;
+
+    for round in 0..games {
+        let opening = openings[(round / 2) % openings.len()];
+        // The engines swap colors after each game
+        let engine1_white = round % 2 == 0;
+        let mut first = engine1.create();
+        let mut second = engine2.create();
+        let record = if engine1_white {
+            play_game(
+                first.as_mut(),
+                second.as_mut(),
+                (name1.clone(), name2.clone()),
+                opening,
+                &adjudication,
+            )
+        } else {
+            play_game(
+                second.as_mut(),
+                first.as_mut(),
+                (name2.clone(), name1.clone()),
+                opening,
+                &adjudication,
+            )
+        };
+
+        let score = match (record.result, engine1_white) {
+            (GameResult::Draw, _) => 0.5,
+            (GameResult::WhiteWins, true) | (GameResult::BlackWins, false) => 1.0,
+            _ => 0.0,
+        };
+        stats.add(score);
+        pgn_file
+            .write_all(pgn::write_game(&record, &event, round + 1).as_bytes())
+            .expect("Can't write the PGN file");
+
+        println!(
+            "Game {:>4}: {} - {}: {} ({}) | +{} ={} -{}",
+            round + 1,
+            record.white,
+            record.black,
+            record.result.as_pgn(),
+            record.termination.description(),
+            stats.wins,
+            stats.draws,
+            stats.losses
+        );
+
+        verdict = sprt.verdict(&stats);
+        if verdict != SprtVerdict::Continue {
+            break;
+        }
+    }
+
+    println!("-------------------");
+    println!("MATCH RESULT");
+    println!("-------------------");
+    println!("Engines             : {name1} vs {name2}");
+    println!("Games               : {}", stats.games());
+    println!(
+        "W / D / L           : {} / {} / {}",
+        stats.wins, stats.draws, stats.losses
+    );
+    println!("Score               : {:.1}%", 100.0 * stats.score());
+    match stats.elo() {
+        Some((elo, margin)) => println!("Elo difference      : {elo:.1} +/- {margin:.1}"),
+        None => println!("Elo difference      : infinite"),
+    }
+    let (lower, upper) = sprt.bounds();
+    println!(
+        "SPRT ({}, {})       : LLR {:.2} [{lower:.2}, {upper:.2}], {}",
+        sprt.elo0,
+        sprt.elo1,
+        stats.llr(sprt.elo0, sprt.elo1),
+        match verdict {
+            SprtVerdict::AcceptH0 => "H0 accepted",
+            SprtVerdict::AcceptH1 => "H1 accepted",
+            SprtVerdict::Continue => "inconclusive",
+        }
+    );
+    println!("Games written to {pgn_path}");
+}
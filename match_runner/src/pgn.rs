@@ -0,0 +1,83 @@
+use crate::play::{GameRecord, Termination};
+use model::game::ChessGame;
+use model::pgn::{PgnGame, PgnMove};
+
+/// Writes a game in the PGN format, with the tags of the seven tag roster. The starting position
+/// is written in the `FEN` tag when the game doesn't start from the standard position.
+pub fn write_game(record: &GameRecord, event: &str, round: usize) -> String {
+    let mut game = PgnGame::default();
+    game.set_tag("Event", event);
+    game.set_tag("Round", &round.to_string());
+    game.set_tag("White", &record.white);
+    game.set_tag("Black", &record.black);
+    if record.opening != ChessGame::standard_game() {
+        game.set_tag("SetUp", "1");
+        game.set_tag("FEN", &record.opening.to_fen());
+    }
+    game.set_tag(
+        "Termination",
+        match record.termination {
+            Termination::MaxPlies | Termination::Adjudication => "adjudication",
+            Termination::IllegalMove => "rules infraction",
+            _ => "normal",
+        },
+    );
+    game.result = Some(record.result.as_pgn().to_string());
+
+    game.line.moves = record.moves.iter().map(|san| PgnMove::new(san)).collect();
+    // The reason of the end of the game is commented after the last move
+    let description = Some(record.termination.description().to_string());
+    match game.line.moves.last_mut() {
+        Some(last) => last.comment = description,
+        None => game.line.comment = description,
+    }
+    game.to_pgn()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pgn::write_game;
+    use crate::play::{GameRecord, GameResult, Termination};
+    use model::game::ChessGame;
+
+    #[test]
+    fn test_write_game() {
+        let record = GameRecord {
+            white: "alphabeta:4:2".to_string(),
+            black: "iterative:4:2".to_string(),
+            opening: ChessGame::standard_game(),
+            moves: ["f3", "e5", "g4", "Qh4#"].map(String::from).to_vec(),
+            result: GameResult::BlackWins,
+            termination: Termination::Checkmate,
+        };
+        let pgn = write_game(&record, "Test", 3);
+        assert!(pgn.starts_with("[Event \"Test\"]\n[Site \"?\"]\n"));
+        assert!(pgn.contains("[Round \"3\"]\n[White \"alphabeta:4:2\"]\n"));
+        assert!(pgn.contains("[Result \"0-1\"]\n"));
+        assert!(!pgn.contains("FEN"));
+        assert!(pgn.ends_with("\n\n1. f3 e5 2. g4 Qh4# {checkmate} 0-1\n\n"));
+    }
+
+    #[test]
+    fn test_black_to_play_and_long_games() {
+        let fen = "4k3/8/8/8/8/8/8/R3K3 b - - 0 1";
+        let record = GameRecord {
+            white: "a".to_string(),
+            black: "b".to_string(),
+            opening: ChessGame::from_fen(fen),
+            moves: ["Kd7", "Ra7+", "Kc6", "Ra6+"]
+                .iter()
+                .cycle()
+                .take(100)
+                .map(|m| m.to_string())
+                .collect(),
+            result: GameResult::Draw,
+            termination: Termination::MaxPlies,
+        };
+        let pgn = write_game(&record, "Test", 1);
+        assert!(pgn.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/R3K3 b - - 0 1\"]\n"));
+        assert!(pgn.contains("[Termination \"adjudication\"]\n"));
+        assert!(pgn.contains("\n\n1... Kd7 2. Ra7+ Kc6 3. Ra6+ Kd7"));
+        assert!(pgn.lines().all(|line| line.len() <= 80));
+    }
+}
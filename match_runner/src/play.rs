@@ -0,0 +1,274 @@
+use engine::engine::Engine;
+use model::chess_type::ScoreType;
+use model::chess_type::Type::{Bishop, Knight, Pawn, Queen, Rook};
+use model::game::ChessGame;
+use std::collections::HashMap;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+impl GameResult {
+    /// Result as written in PGN files
+    pub fn as_pgn(&self) -> &'static str {
+        match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+        }
+    }
+
+    fn win_for(white: bool) -> Self {
+        if white {
+            GameResult::WhiteWins
+        } else {
+            GameResult::BlackWins
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    Repetition,
+    FiftyMoves,
+    InsufficientMaterial,
+    /// The game reached the maximum number of plies
+    MaxPlies,
+    /// Both engines agreed that one side is winning for several moves
+    Adjudication,
+    /// The engine did not return a legal move
+    IllegalMove,
+}
+
+impl Termination {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Termination::Checkmate => "checkmate",
+            Termination::Stalemate => "stalemate",
+            Termination::Repetition => "threefold repetition",
+            Termination::FiftyMoves => "fifty moves rule",
+            Termination::InsufficientMaterial => "insufficient material",
+            Termination::MaxPlies => "maximum number of moves",
+            Termination::Adjudication => "adjudication",
+            Termination::IllegalMove => "illegal move",
+        }
+    }
+}
+
+/// Rules to stop the games before their natural end
+#[derive(Copy, Clone, Debug)]
+pub struct Adjudication {
+    /// Games are drawn after this number of plies
+    pub max_plies: usize,
+    /// A game is won when both engines evaluate it beyond this score (in centipawns, in favour of
+    /// the same player) for `resign_plies` consecutive plies.
+    pub resign_score: ScoreType,
+    pub resign_plies: usize,
+}
+
+/// A finished game, with its moves in standard algebraic notation
+#[derive(Clone, Debug)]
+pub struct GameRecord {
+    pub white: String,
+    pub black: String,
+    pub opening: ChessGame,
+    pub moves: Vec<String>,
+    pub result: GameResult,
+    pub termination: Termination,
+}
+
+/// Returns true if none of the players can checkmate: only kings, or a single knight or bishop.
+pub fn is_insufficient_material(game: &ChessGame) -> bool {
+    let count = |piece| (game.pieces(piece, true) | game.pieces(piece, false)).count_ones();
+    count(Pawn) + count(Rook) + count(Queen) == 0 && count(Knight) + count(Bishop) <= 1
+}
+
+/// Plays a game between two engines from the given position
+pub fn play_game(
+    white_engine: &mut dyn Engine,
+    black_engine: &mut dyn Engine,
+    names: (String, String),
+    opening: ChessGame,
+    adjudication: &Adjudication,
+) -> GameRecord {
+    let mut game = opening;
+    let mut moves = vec![];
+    // The engines get the moves played since the opening, to detect the repetitions
+    let mut played = vec![];
+    let mut repetitions: HashMap<ChessGame, usize> = HashMap::new();
+    repetitions.insert(game, 1);
+    // Number of plies since the last capture or pawn move
+    let mut halfmove_clock = 0;
+    // Number of consecutive plies evaluated as won by the same player
+    let mut winning_plies = 0;
+    let mut winning_side = true;
+
+    let (result, termination) = loop {
+        let white = game.white_to_play();
+        let legal_moves = game.legal_moves(white);
+        if legal_moves.is_empty() {
+            break if game.is_in_check(white) {
+                (GameResult::win_for(!white), Termination::Checkmate)
+            } else {
+                (GameResult::Draw, Termination::Stalemate)
+            };
+        }
+        if is_insufficient_material(&game) {
+            break (GameResult::Draw, Termination::InsufficientMaterial);
+        }
+        if halfmove_clock >= 100 {
+            break (GameResult::Draw, Termination::FiftyMoves);
+        }
+        if moves.len() >= adjudication.max_plies {
+            break (GameResult::Draw, Termination::MaxPlies);
+        }
+
+        let search = if white {
+            white_engine.find_best_move_in_game(opening, &played)
+        } else {
+            black_engine.find_best_move_in_game(opening, &played)
+        };
+        // The move of the engine is replaced by the generated one, which has the right flags
+        let Some(m) = search
+            .best_move
+            .and_then(|best| legal_moves.into_iter().find(|m| *m == best))
+        else {
+            break (GameResult::win_for(!white), Termination::IllegalMove);
+        };
+
+        let resets_clock =
+            game.type_at_index(m.from) == Some(Pawn) || game.type_at_index(m.to).is_some();
+        moves.push(game.to_san(&m));
+        played.push(m);
+        game.apply_move_unsafe(&m);
+        halfmove_clock = if resets_clock { 0 } else { halfmove_clock + 1 };
+
+        let count = repetitions.entry(game).or_insert(0);
+        *count += 1;
+        if *count >= 3 {
+            break (GameResult::Draw, Termination::Repetition);
+        }
+
+        if search.score.abs() >= adjudication.resign_score {
+            let side = search.score > 0;
+            winning_plies = if winning_plies > 0 && side == winning_side {
+                winning_plies + 1
+            } else {
+                1
+            };
+            winning_side = side;
+            if winning_plies >= adjudication.resign_plies {
+                break (GameResult::win_for(winning_side), Termination::Adjudication);
+            }
+        } else {
+            winning_plies = 0;
+        }
+    };
+
+    GameRecord {
+        white: names.0,
+        black: names.1,
+        opening,
+        moves,
+        result,
+        termination,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::play::{is_insufficient_material, play_game, Adjudication, GameResult, Termination};
+    use engine::alpha_beta::AlphaBetaEngine;
+    use model::game::ChessGame;
+
+    const ADJUDICATION: Adjudication = Adjudication {
+        max_plies: 40,
+        resign_score: 100_000,
+        resign_plies: 4,
+    };
+
+    fn play(fen: &str, adjudication: &Adjudication) -> (Vec<String>, GameResult, Termination) {
+        let mut white = AlphaBetaEngine::new(3, 0);
+        let mut black = AlphaBetaEngine::new(3, 0);
+        let record = play_game(
+            &mut white,
+            &mut black,
+            ("white".to_string(), "black".to_string()),
+            ChessGame::from_fen(fen),
+            adjudication,
+        );
+        (record.moves, record.result, record.termination)
+    }
+
+    #[test]
+    fn test_checkmate() {
+        let (moves, result, termination) = play("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", &ADJUDICATION);
+        assert_eq!(vec!["Ra8#"], moves);
+        assert_eq!(GameResult::WhiteWins, result);
+        assert_eq!(Termination::Checkmate, termination);
+    }
+
+    #[test]
+    fn test_draws() {
+        // Black is stalemated
+        let (moves, result, termination) = play("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", &ADJUDICATION);
+        assert!(moves.is_empty());
+        assert_eq!(GameResult::Draw, result);
+        assert_eq!(Termination::Stalemate, termination);
+
+        // The rook is captured right away
+        let (_, result, termination) = play("8/8/8/8/8/8/2r5/2K1k3 w - - 0 1", &ADJUDICATION);
+        assert_eq!(GameResult::Draw, result);
+        assert_eq!(Termination::InsufficientMaterial, termination);
+
+        // Nobody can make progress with the blocked pawns
+        let adjudication = Adjudication {
+            max_plies: 10,
+            ..ADJUDICATION
+        };
+        let (moves, result, termination) = play("4k3/8/8/p7/P7/8/8/4K3 w - - 0 1", &adjudication);
+        assert_eq!(GameResult::Draw, result);
+        assert!(
+            (termination == Termination::MaxPlies && moves.len() == 10)
+                || termination == Termination::Repetition
+        );
+    }
+
+    #[test]
+    fn test_adjudication() {
+        // White has an extra queen: both engines agree it is winning
+        let adjudication = Adjudication {
+            resign_score: 500,
+            resign_plies: 4,
+            ..ADJUDICATION
+        };
+        let (moves, result, termination) = play(
+            "4k3/pppppppp/8/8/8/8/PPPPPPPP/3QK3 w - - 0 1",
+            &adjudication,
+        );
+        assert_eq!(4, moves.len());
+        assert_eq!(GameResult::WhiteWins, result);
+        assert_eq!(Termination::Adjudication, termination);
+    }
+
+    #[test]
+    fn test_insufficient_material() {
+        assert!(is_insufficient_material(&ChessGame::from_fen(
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1"
+        )));
+        assert!(is_insufficient_material(&ChessGame::from_fen(
+            "4k3/8/8/8/8/8/8/2B1K3 w - - 0 1"
+        )));
+        assert!(!is_insufficient_material(&ChessGame::from_fen(
+            "4k3/8/8/8/8/8/8/1NB1K3 w - - 0 1"
+        )));
+        assert!(!is_insufficient_material(&ChessGame::from_fen(
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"
+        )));
+    }
+}
@@ -0,0 +1,181 @@
+/// Results of the games, from the point of view of the first engine
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MatchStats {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+/// Converts an expected score into an Elo difference
+fn elo(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// Converts an Elo difference into an expected score
+fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+impl MatchStats {
+    /// Adds the result of a game: 1 for a win of the first engine, 0.5 for a draw and 0 for a loss
+    pub fn add(&mut self, score: f64) {
+        if score > 0.5 {
+            self.wins += 1;
+        } else if score < 0.5 {
+            self.losses += 1;
+        } else {
+            self.draws += 1;
+        }
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Mean score of the first engine, between 0 and 1
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+
+    /// Variance of the score of one game
+    fn variance(&self) -> f64 {
+        let s = self.score();
+        (self.wins as f64 * (1.0 - s).powi(2)
+            + self.draws as f64 * (0.5 - s).powi(2)
+            + self.losses as f64 * s.powi(2))
+            / self.games() as f64
+    }
+
+    /// Elo difference between the first and the second engine, with the margin of its 95%
+    /// confidence interval. Returns `None` until the first engine has both won (or drawn) and lost
+    /// (or drawn) points, since the difference is infinite otherwise.
+    pub fn elo(&self) -> Option<(f64, f64)> {
+        let s = self.score();
+        if self.games() == 0 || s <= 0.0 || s >= 1.0 {
+            return None;
+        }
+        let margin = 1.96 * (self.variance() / self.games() as f64).sqrt();
+        let low = (s - margin).max(f64::EPSILON);
+        let high = (s + margin).min(1.0 - f64::EPSILON);
+        Some((elo(s), (elo(high) - elo(low)) / 2.0))
+    }
+
+    /// Log-likelihood ratio of the hypothesis "the Elo difference is `elo1`" against "the Elo
+    /// difference is `elo0`", with the normal approximation of the distribution of the scores.
+    pub fn llr(&self, elo0: f64, elo1: f64) -> f64 {
+        if self.games() == 0 {
+            return 0.0;
+        }
+        let variance = self.variance();
+        if variance == 0.0 {
+            return 0.0;
+        }
+        let s0 = expected_score(elo0);
+        let s1 = expected_score(elo1);
+        self.games() as f64 * (s1 - s0) * (2.0 * self.score() - s0 - s1) / (2.0 * variance)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SprtVerdict {
+    /// The Elo difference is more likely to be `elo0`
+    AcceptH0,
+    /// The Elo difference is more likely to be `elo1`
+    AcceptH1,
+    /// More games are needed
+    Continue,
+}
+
+/// Sequential probability ratio test: the match stops as soon as the results are enough to decide
+/// between `elo0` (H0) and `elo1` (H1), with the error rates `alpha` and `beta`.
+///
+/// https://www.chessprogramming.org/Sequential_Probability_Ratio_Test
+#[derive(Copy, Clone, Debug)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Sprt {
+    /// Lower and upper bounds of the log-likelihood ratio
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    pub fn verdict(&self, stats: &MatchStats) -> SprtVerdict {
+        let llr = stats.llr(self.elo0, self.elo1);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            SprtVerdict::AcceptH1
+        } else if llr <= lower {
+            SprtVerdict::AcceptH0
+        } else {
+            SprtVerdict::Continue
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stats::{MatchStats, Sprt, SprtVerdict};
+
+    #[test]
+    fn test_elo() {
+        let even = MatchStats {
+            wins: 10,
+            draws: 10,
+            losses: 10,
+        };
+        let (elo, margin) = even.elo().unwrap();
+        assert!(elo.abs() < 1e-9);
+        assert!(margin > 0.0);
+
+        // The difference is infinite without any lost point
+        let perfect = MatchStats {
+            wins: 50,
+            draws: 0,
+            losses: 0,
+        };
+        assert!(perfect.elo().is_none());
+
+        // A score of 75% is about 191 Elo
+        let strong = MatchStats {
+            wins: 70,
+            draws: 10,
+            losses: 20,
+        };
+        assert!((strong.elo().unwrap().0 - 190.85).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_sprt() {
+        let sprt = Sprt {
+            elo0: 0.0,
+            elo1: 10.0,
+            alpha: 0.05,
+            beta: 0.05,
+        };
+        let (lower, upper) = sprt.bounds();
+        assert!((upper - 2.944).abs() < 1e-3);
+        assert!((lower + 2.944).abs() < 1e-3);
+
+        let mut stats = MatchStats::default();
+        assert_eq!(SprtVerdict::Continue, sprt.verdict(&stats));
+
+        // A clearly stronger engine is detected
+        stats.wins = 600;
+        stats.draws = 200;
+        stats.losses = 200;
+        assert!(stats.llr(0.0, 10.0) > 0.0);
+        assert_eq!(SprtVerdict::AcceptH1, sprt.verdict(&stats));
+
+        // And so is a weaker one
+        std::mem::swap(&mut stats.wins, &mut stats.losses);
+        assert_eq!(SprtVerdict::AcceptH0, sprt.verdict(&stats));
+    }
+}
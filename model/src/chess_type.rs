@@ -1,4 +1,4 @@
-#[derive(PartialEq, Eq, Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum Type {
     Pawn,
     Bishop,
@@ -10,6 +10,9 @@ pub enum Type {
 
 pub type ScoreType = i64;
 
+/// Value of one pawn in the unit of `ChessGame::score`, used to convert a score into centipawns.
+pub const PAWN_SCORE: ScoreType = 100;
+
 impl Type {
     pub const fn score(&self) -> ScoreType {
         match self {
@@ -0,0 +1,173 @@
+use crate::chess_type::Type;
+use crate::chess_type::Type::{Bishop, King, Knight, Pawn, Rook};
+use crate::game::ChessGame;
+use crate::moves::Move;
+use crate::utils::ChessPosition;
+use std::fmt;
+use std::num::NonZeroU16;
+
+/// Kinds of moves, stored in the 4 upper bits of a `CompactMove`
+const QUIET: u16 = 0;
+const DOUBLE_PUSH: u16 = 1;
+const CASTLE: u16 = 2;
+const EN_PASSANT: u16 = 3;
+/// Promotion to a queen, followed by the underpromotions in the order of `UNDERPROMOTIONS`
+const PROMOTION: u16 = 4;
+const UNDERPROMOTIONS: [Type; 3] = [Knight, Bishop, Rook];
+
+/// A move packed in 16 bits: the origin in the bits 0 to 5, the destination in the bits 6 to 11
+/// and the kind of move (castling, en passant, double push of a pawn or promotion to each piece) in
+/// the bits 12 to 15.
+///
+/// Unlike `Move`, it doesn't keep the ordering quality nor the color of the pieces, so that two
+/// compact moves are equal if and only if they are the same move. Since the origin and the
+/// destination of a move are different, it is never zero and `Option<CompactMove>` also takes 16
+/// bits, which makes it suited to the tables of the engine.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct CompactMove(NonZeroU16);
+
+impl CompactMove {
+    /// Packs a move played in a game, which tells the kind of move
+    pub fn new(game: &ChessGame, m: &Move) -> Self {
+        let piece = game.type_at_index(m.from);
+        let motion = m.to - m.from;
+        let kind = match piece {
+            Some(King) if motion.abs() == 2 => CASTLE,
+            Some(Pawn) if m.to / 8 == 0 || m.to / 8 == 7 => {
+                let underpromotion = m
+                    .underpromotion
+                    .and_then(|piece| UNDERPROMOTIONS.iter().position(|&p| p == piece));
+                PROMOTION + underpromotion.map_or(0, |i| i as u16 + 1)
+            }
+            Some(Pawn) if motion.abs() == 16 => DOUBLE_PUSH,
+            Some(Pawn) if m.from % 8 != m.to % 8 && game.type_at_index(m.to).is_none() => {
+                EN_PASSANT
+            }
+            _ => QUIET,
+        };
+        let bits = m.from as u16 | (m.to as u16) << 6 | kind << 12;
+        Self(NonZeroU16::new(bits).expect("The origin and the destination of a move differ"))
+    }
+
+    /// Reads a move written with `bits`. Returns `None` for 0, which isn't a move.
+    pub fn from_bits(bits: u16) -> Option<Self> {
+        NonZeroU16::new(bits).map(Self)
+    }
+
+    pub fn bits(self) -> u16 {
+        self.0.get()
+    }
+
+    pub fn from(self) -> ChessPosition {
+        (self.bits() & 0x3f) as ChessPosition
+    }
+
+    pub fn to(self) -> ChessPosition {
+        ((self.bits() >> 6) & 0x3f) as ChessPosition
+    }
+
+    fn kind(self) -> u16 {
+        self.bits() >> 12
+    }
+
+    pub fn is_castle(self) -> bool {
+        self.kind() == CASTLE
+    }
+
+    pub fn is_en_passant(self) -> bool {
+        self.kind() == EN_PASSANT
+    }
+
+    pub fn is_double_push(self) -> bool {
+        self.kind() == DOUBLE_PUSH
+    }
+
+    pub fn is_promotion(self) -> bool {
+        (PROMOTION..=PROMOTION + UNDERPROMOTIONS.len() as u16).contains(&self.kind())
+    }
+
+    /// Piece of the promotion when it isn't a queen
+    pub fn underpromotion(self) -> Option<Type> {
+        let index = self.kind().checked_sub(PROMOTION + 1)?;
+        UNDERPROMOTIONS.get(index as usize).copied()
+    }
+
+    /// Unpacks the move, played by the given side
+    pub fn to_move(self, is_white: bool) -> Move {
+        Move {
+            underpromotion: self.underpromotion(),
+            ..Move::new(self.from(), self.to(), is_white)
+        }
+    }
+}
+
+impl From<CompactMove> for u16 {
+    fn from(m: CompactMove) -> Self {
+        m.bits()
+    }
+}
+
+impl fmt::Debug for CompactMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.to_move(true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_type::Type::{Bishop, Knight, Rook};
+    use crate::compact_move::CompactMove;
+    use crate::game::ChessGame;
+    use crate::moves::Move;
+
+    #[test]
+    fn test_kinds_of_moves() {
+        let mut game = ChessGame::from_fen("r3k3/1P6/8/8/4p3/8/3P4/4K2R w K - 0 1");
+        let packed = |game: &ChessGame, from, to, white| {
+            CompactMove::new(game, &Move::from_str(from, to, white))
+        };
+
+        let castle = packed(&game, "e1", "g1", true);
+        assert!(castle.is_castle() && !castle.is_promotion());
+        let king = packed(&game, "e1", "f1", true);
+        assert!(!king.is_castle());
+        assert!(packed(&game, "b7", "a8", true).is_promotion());
+        assert!(packed(&game, "b7", "b8", true).is_promotion());
+        for piece in [Knight, Bishop, Rook] {
+            let m = Move {
+                underpromotion: Some(piece),
+                ..Move::from_str("b7", "a8", true)
+            };
+            let underpromotion = CompactMove::new(&game, &m);
+            assert!(underpromotion.is_promotion() && !underpromotion.is_castle());
+            assert_eq!(Some(piece), underpromotion.underpromotion());
+            assert_eq!(m, underpromotion.to_move(true));
+        }
+        assert_eq!(None, packed(&game, "b7", "b8", true).underpromotion());
+
+        let push = packed(&game, "d2", "d4", true);
+        assert!(push.is_double_push() && !push.is_en_passant());
+        assert!(!packed(&game, "d2", "d3", true).is_double_push());
+        game.apply_move_unsafe(&Move::from_str("d2", "d4", true));
+        let en_passant = packed(&game, "e4", "d3", false);
+        assert!(en_passant.is_en_passant() && !en_passant.is_double_push());
+        assert!(!packed(&game, "e4", "e3", false).is_en_passant());
+    }
+
+    #[test]
+    fn test_conversions() {
+        let game = ChessGame::standard_game();
+        for m in game.legal_moves(true) {
+            let packed = CompactMove::new(&game, &m);
+            assert_eq!(m, packed.to_move(true));
+            assert_eq!((m.from, m.to), (packed.from(), packed.to()));
+            assert_eq!(Some(packed), CompactMove::from_bits(packed.bits()));
+        }
+
+        // e2e4: 12 | 28 << 6 | double push << 12
+        let e4 = CompactMove::new(&game, &Move::from_str("e2", "e4", true));
+        assert_eq!(0x170c, u16::from(e4));
+        assert_eq!(None, CompactMove::from_bits(0));
+        assert_eq!(2, size_of::<Option<CompactMove>>());
+    }
+}
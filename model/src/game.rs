@@ -3,6 +3,24 @@ mod attacks;
 
 mod constructor;
 mod display;
+/// Tapered evaluation with piece-square tables
+mod evaluation;
+/// King safety: pawn shield, pawn storm, open files and attackers near the king
+mod king_safety;
+/// Mobility of the pieces, computed from the attack bitboards
+mod mobility;
+/// Legal moves, and move generation testing: counts the nodes of the tree of legal moves
+mod perft;
+/// Standard algebraic notation of the moves
+mod san;
+/// Moves which can be taken back, without copying the game
+mod undo;
+/// Evaluation of the pawn structure
+pub(crate) mod pawns;
+/// Weights of the evaluation, which can be tuned
+pub mod parameters;
+/// Magic bitboards: attacks of the sliding pieces with a single table lookup
+mod magic;
 /// Computes some bitmask that can be reused efficently at runtime.
 mod precomputation;
 mod moves;
@@ -11,8 +29,13 @@ use super::moves::*;
 use crate::chess_type::Type::{Bishop, King, Knight, Pawn, Queen, Rook};
 use crate::chess_type::{ScoreType, Type};
 use crate::game::attacks::ChessAttacks;
-use crate::moves_container::{MovesContainer, SimpleMovesContainer};
-use crate::utils::{clear_at, is_set, pos_to_index, set_at, ChessPosition, IntoChessPosition};
+use crate::game::parameters::DEFAULT_PARAMETERS;
+use crate::game::pawns::PawnStructure;
+use crate::pawn_hash_table::PawnHashTable;
+use crate::utils::{
+    clear_at, is_set, pieces_for_color, pos_to_index, set_at, ChessPosition, IntoChessPosition,
+};
+pub use undo::UndoInfo;
 
 /// Struct to represent a chess game.
 ///
@@ -23,6 +46,7 @@ use crate::utils::{clear_at, is_set, pos_to_index, set_at, ChessPosition, IntoCh
 ///     1: has black king moved
 ///     2: has white king castled
 ///     3: has black king castled
+///     4: is black to play
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub struct ChessGame {
     pub(crate) whites: u64,
@@ -47,6 +71,23 @@ const FLAG_WHITE_KING_MOVED: i8 = 0;
 const FLAG_BLACK_KING_MOVED: i8 = 1;
 const FLAG_WHITE_KING_CASTLED: i8 = 2;
 const FLAG_BLACK_KING_CASTLED: i8 = 3;
+const FLAG_BLACK_TO_PLAY: i8 = 4;
+const FLAG_WHITE_KING_ROOK_MOVED: i8 = 5;
+const FLAG_WHITE_QUEEN_ROOK_MOVED: i8 = 6;
+const FLAG_BLACK_KING_ROOK_MOVED: i8 = 7;
+const FLAG_BLACK_QUEEN_ROOK_MOVED: i8 = 8;
+
+/// Returns the flag of the rook which starts on this square, lost when the rook leaves it or is
+/// captured on it
+fn rook_moved_flag(at: ChessPosition) -> Option<i8> {
+    match at {
+        7 => Some(FLAG_WHITE_KING_ROOK_MOVED),
+        0 => Some(FLAG_WHITE_QUEEN_ROOK_MOVED),
+        63 => Some(FLAG_BLACK_KING_ROOK_MOVED),
+        56 => Some(FLAG_BLACK_QUEEN_ROOK_MOVED),
+        _ => None,
+    }
+}
 
 impl ChessGame {
     /// Construct a chess game from the integers
@@ -94,6 +135,40 @@ impl ChessGame {
         }
     }
 
+    /// Returns the bitboard of the pieces of the given type and color
+    pub fn pieces(&self, piece: Type, white: bool) -> u64 {
+        let bitboard = match piece {
+            Pawn => self.pawns,
+            Bishop => self.bishops,
+            Knight => self.knights,
+            Rook => self.rooks,
+            Queen => self.queens,
+            King => self.kings,
+        };
+        pieces_for_color!(self.whites, bitboard, white)
+    }
+
+    /// Returns true if white plays the next move
+    pub fn white_to_play(&self) -> bool {
+        !is_set!(self.flags, FLAG_BLACK_TO_PLAY)
+    }
+
+    /// Sets the side which plays the next move
+    pub fn set_white_to_play(&mut self, white_to_play: bool) {
+        if white_to_play {
+            clear_at!(self.flags, FLAG_BLACK_TO_PLAY);
+        } else {
+            set_at!(self.flags, FLAG_BLACK_TO_PLAY);
+        }
+    }
+
+    /// Returns the square on which a pawn can be captured en passant, if the last move was a double
+    /// pawn push
+    pub fn en_passant_square(&self) -> Option<ChessPosition> {
+        (self.en_passant_target != 0)
+            .then(|| self.en_passant_target.trailing_zeros() as ChessPosition)
+    }
+
     /// Returns true if there is a piece at this position
     pub(crate) fn has_piece_at(&self, at: ChessPosition) -> bool {
         is_set!(
@@ -212,15 +287,12 @@ impl ChessGame {
                 return false;
             }
 
-            // Check that there is a rook in the correct position
-            if motion > 0 {
-                if !is_set!(self.rooks, m.from + 3) {
-                    return false;
-                }
-            } else {
-                if !is_set!(self.rooks, m.from - 4) {
-                    return false;
-                }
+            // Check that there is a rook in the correct position, which never moved
+            let rook = if motion > 0 { m.from + 3 } else { m.from - 4 };
+            if !is_set!(self.rooks, rook)
+                || rook_moved_flag(rook).is_none_or(|flag| is_set!(self.flags, flag))
+            {
+                return false;
             }
 
             // Check that the neighbors positions are empty and not attacked
@@ -344,6 +416,13 @@ impl ChessGame {
             // Eventually apply the capture (standard captures on the destination square)
             self.apply_capture(&m);
 
+            // Flags update : a rook leaves its initial square or is captured on it
+            for at in [m.from, m.to] {
+                if let Some(flag) = rook_moved_flag(at) {
+                    set_at!(self.flags, flag);
+                }
+            }
+
             // Apply the move
             match t {
                 Pawn => {
@@ -378,7 +457,12 @@ impl ChessGame {
 
                     // handle the promotion directly here
                     if m.to / 8 == 7 || m.to / 8 == 0 {
-                        set_at!(self.queens, m.to);
+                        match m.underpromotion {
+                            Some(Knight) => set_at!(self.knights, m.to),
+                            Some(Bishop) => set_at!(self.bishops, m.to),
+                            Some(Rook) => set_at!(self.rooks, m.to),
+                            _ => set_at!(self.queens, m.to),
+                        }
                     } else {
                         set_at!(self.pawns, m.to);
                     }
@@ -443,6 +527,9 @@ impl ChessGame {
                 clear_at!(self.whites, m.from);
                 set_at!(self.whites, m.to);
             }
+
+            // The other side plays the next move
+            self.set_white_to_play(!self.white_to_play());
         }
     }
 
@@ -465,38 +552,20 @@ impl ChessGame {
         false
     }
 
+    /// Evaluates the position, in centipawns, from white's point of view.
     pub fn score(&self) -> ScoreType {
-        let mut score = 0;
-
-        score += (self.pawns & self.whites).count_ones() as ScoreType * 1;
-        score += ((self.bishops | self.knights) & self.whites).count_ones() as ScoreType * 3;
-        score += (self.rooks & self.whites).count_ones() as ScoreType * 5;
-        score += (self.queens & self.whites).count_ones() as ScoreType * 10;
-        score += (self.kings & self.whites).count_ones() as ScoreType * 1000;
-
-        score -= ((self.bishops | self.knights) & !self.whites).count_ones() as ScoreType * 3;
-        score -= (self.pawns & !self.whites).count_ones() as ScoreType * 1;
-        score -= (self.rooks & !self.whites).count_ones() as ScoreType * 5;
-        score -= (self.queens & !self.whites).count_ones() as ScoreType * 10;
-        score -= (self.kings & !self.whites).count_ones() as ScoreType * 1000;
-
-        // Castling : we want to favor the castle, which secures the king
-        // if is_set!(self.flags, FLAG_WK_CASTLED) { score += 3; }
-        // if is_set!(self.flags, FLAG_BK_CASTLED) { score -= 3; }
-
-        // This is really the problem: the number of attacked squres takes a lot of time to be found
-        // and reduces the performs by a factor of 28. Is there a better way to do this ?
+        self.score_with_pawn_structure(&self.pawn_structure())
+    }
 
-        // Number of attacked squares
-        // The bigger this ratio is, the less the engine will favor attacking positions.
-        score *= 20;
-        let mut container = SimpleMovesContainer::new();
-        self.update_move_container(&mut container, true);
-        score += container.count() as ScoreType;
-        self.update_move_container(&mut container, false);
-        score -= container.count() as ScoreType;
+    /// Same as `score`, but the evaluation of the pawn structure is cached in the provided table.
+    pub fn score_with_pawn_table(&self, pawn_table: &mut PawnHashTable) -> ScoreType {
+        self.score_with_pawn_structure(&pawn_table.probe(self))
+    }
 
-        score
+    fn score_with_pawn_structure(&self, pawns: &PawnStructure) -> ScoreType {
+        // Material, placement of the pieces, pawn structure, king safety and mobility, interpolated
+        // between middlegame and endgame
+        self.tapered_evaluation(pawns, &DEFAULT_PARAMETERS)
     }
 
     #[allow(dead_code)]
@@ -551,6 +620,7 @@ mod tests {
     use crate::game::ChessGame;
     use crate::moves::Move;
     use crate::utils::pos_to_index;
+    use std::collections::HashSet;
 
     #[test]
     fn test_wrong_knight_move() {
@@ -745,4 +815,21 @@ mod tests {
         // Black pawn should now be on d3 (x=3, y=2)
         assert!(game.is_black_at(pos_to_index(3, 2)), "Black pawn should be on d3 after en passant");
     }
+
+    #[test]
+    fn test_side_to_move() {
+        let mut game = ChessGame::standard_game();
+        assert!(game.white_to_play());
+        game.apply_move_unsafe(&Move::from_str("e2", "e4", true));
+        assert!(!game.white_to_play());
+        game.apply_move_unsafe(&Move::from_str("e7", "e5", false));
+        assert!(game.white_to_play());
+
+        // The same pieces with another side to move are another position
+        let mut black_to_play = ChessGame::standard_game();
+        black_to_play.set_white_to_play(false);
+        assert_ne!(ChessGame::standard_game(), black_to_play);
+        let positions: HashSet<ChessGame> = [ChessGame::standard_game(), black_to_play].into();
+        assert_eq!(2, positions.len());
+    }
 }
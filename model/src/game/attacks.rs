@@ -1,9 +1,7 @@
-use crate::game::precomputation::{
-    KING_ATTACK_MASKS, KNIGHT_ATTACK_MASKS, PAWN_ATTACK_MASKS, SLIDING_ATTACK_MASKS,
-};
+use crate::game::magic::{bishop_attacks, queen_attacks, rook_attacks};
+use crate::game::precomputation::{KING_ATTACK_MASKS, KNIGHT_ATTACK_MASKS, PAWN_ATTACK_MASKS};
 use crate::game::ChessGame;
-use crate::utils::{consume_bits, is_set, pieces_for_color, set_at};
-use std::ops::Range;
+use crate::utils::{consume_bits, pieces_for_color};
 
 pub(super) trait ChessAttacks {
     /// Returns the list of attack squares
@@ -30,71 +28,24 @@ pub(super) trait ChessAttacks {
 }
 
 impl ChessGame {
-    /// Computes the squares attacked by a sliding piece (rook, bishop, or queen)
-    /// on the chessboard.
+    /// Computes the squares attacked by sliding pieces (rooks, bishops or queens).
     ///
-    /// Sliding pieces attack squares along straight paths until obstructed
-    /// by another piece or reaching the edge of the board. This function uses
-    /// precomputed sliding attack masks for each square and direction to determine
-    /// the attacks.
-    ///
-    /// # Arguments
-    ///
-    /// - `pieces`: A bitboard representing the positions of the sliding pieces whose
-    ///   attacks are to be computed.
-    /// - `direction_indices`: A range specifying the indices of directions to consider
-    ///   in the `SLIDING_ATTACK_MASKS`. For example:
-    ///   - `0..4`: Horizontal and vertical directions (rook-like movement).
-    ///   - `4..8`: Diagonal directions (bishop-like movement).
-    ///
-    /// # Returns
-    ///
-    /// A bitboard representing all squares attacked by the sliding pieces.
-    ///
-    /// # Details
-    ///
-    /// The calculation proceeds as follows:
-    /// - For each piece in the bitboard, its position is determined.
-    /// - For each direction in the given range, the attack ray for this direction
-    ///   is retrieved from the precomputed `SLIDING_ATTACK_MASKS`.
-    /// - The attack is calculated iteratively until an occupied square is encountered
-    ///   (blocking the attack in that direction).
-    ///
-    /// This method makes use of bitwise operations for efficient computation.
-    ///
-    /// # Notes
-    ///
-    /// The method assumes a precomputed occupancy bitboard (`self.rooks | self.kings |
-    /// self.queens | self.pawns | self.bishops | self.knights`) to identify blocking pieces.
-    ///
-    /// If a rook is at "c4", its attack squares in horizontal and vertical directions are computed,
-    /// with blocking taken into account appropriately.
+    /// `attacks` returns the squares attacked from one square given the occupancy of the board,
+    /// with a single lookup in the magic bitboard tables. The occupied squares which block the
+    /// pieces are part of the attacked squares.
     fn get_attacked_squares_from_sliding_piece(
         &self,
         pieces: u64,
-        direction_indices: Range<usize>,
+        attacks: fn(usize, u64) -> u64,
     ) -> u64 {
-        let mut attacks = 0;
         let occupancy =
             self.rooks | self.kings | self.queens | self.pawns | self.bishops | self.knights;
 
+        let mut attacked = 0;
         consume_bits!(pieces, sq, {
-            // For each direction
-            for dir in direction_indices.clone() {
-                // Get the attack ray for this direction from the precomputed sliding masks
-                let ray = &SLIDING_ATTACK_MASKS[dir][sq];
-                // Go through all the positions
-                for position in ray {
-                    set_at!(attacks, *position);
-                    // If the square is occupied, end
-                    if is_set!(occupancy, *position) {
-                        break;
-                    }
-                }
-            }
+            attacked |= attacks(sq, occupancy);
         });
-
-        attacks
+        attacked
     }
 }
 
@@ -139,17 +90,17 @@ impl ChessAttacks for ChessGame {
 
     fn get_attacked_squares_rook(&self, white_playing: bool) -> u64 {
         let rook_left = pieces_for_color!(self.whites, self.rooks, white_playing);
-        self.get_attacked_squares_from_sliding_piece(rook_left, 0..4)
+        self.get_attacked_squares_from_sliding_piece(rook_left, rook_attacks)
     }
 
     fn get_attacked_squares_bishop(&self, white_playing: bool) -> u64 {
         let bishops_left = pieces_for_color!(self.whites, self.bishops, white_playing);
-        self.get_attacked_squares_from_sliding_piece(bishops_left, 4..8)
+        self.get_attacked_squares_from_sliding_piece(bishops_left, bishop_attacks)
     }
 
     fn get_attacked_squares_queen(&self, white_playing: bool) -> u64 {
         let queens = pieces_for_color!(self.whites, self.queens, white_playing);
-        self.get_attacked_squares_from_sliding_piece(queens, 0..8)
+        self.get_attacked_squares_from_sliding_piece(queens, queen_attacks)
     }
 }
 
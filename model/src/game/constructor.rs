@@ -1,5 +1,12 @@
-use crate::game::ChessGame;
-use crate::utils::{pos_to_index, set_at, ChessPosition};
+use crate::chess_type::Type::{Bishop, King, Knight, Pawn, Queen, Rook};
+use crate::game::{
+    ChessGame, FLAG_BLACK_KING_MOVED, FLAG_BLACK_KING_ROOK_MOVED, FLAG_BLACK_QUEEN_ROOK_MOVED,
+    FLAG_BLACK_TO_PLAY, FLAG_WHITE_KING_MOVED, FLAG_WHITE_KING_ROOK_MOVED,
+    FLAG_WHITE_QUEEN_ROOK_MOVED,
+};
+use crate::utils::{
+    chesspos_to_index, index_to_chesspos, is_set, pos_to_index, set_at, ChessPosition,
+};
 
 impl ChessGame {
     /// An empty chess game
@@ -174,6 +181,29 @@ impl ChessGame {
             }
         }
 
+        let mut flags = 0;
+        if parts[1] == "b" {
+            set_at!(flags, FLAG_BLACK_TO_PLAY);
+        }
+
+        // A castling which is not in the list is lost, as if its rook had moved
+        for (c, flag) in [
+            ('K', FLAG_WHITE_KING_ROOK_MOVED),
+            ('Q', FLAG_WHITE_QUEEN_ROOK_MOVED),
+            ('k', FLAG_BLACK_KING_ROOK_MOVED),
+            ('q', FLAG_BLACK_QUEEN_ROOK_MOVED),
+        ] {
+            if !parts[2].contains(c) {
+                set_at!(flags, flag);
+            }
+        }
+
+        let mut en_passant_target = 0;
+        if parts[3] != "-" {
+            let target = chesspos_to_index(parts[3]).expect("Invalid FEN: Invalid en passant square");
+            set_at!(en_passant_target, target);
+        }
+
         ChessGame {
             whites,
             pawns,
@@ -182,15 +212,97 @@ impl ChessGame {
             rooks,
             queens,
             kings,
-            flags: 0, // Flags for castling, active color, etc., can be computed if necessary
-            en_passant_target: 0,
+            flags,
+            en_passant_target,
         }
     }
+
+    /// Returns the castling rights, in the order of the FEN notation: white king side, white queen
+    /// side, black king side and black queen side.
+    ///
+    /// Castling is available when neither the king nor the rook has moved, and both are on their
+    /// initial squares.
+    pub fn castling_rights(&self) -> [bool; 4] {
+        let white_king = self.pieces(King, true) == 1 << 4;
+        let black_king = self.pieces(King, false) == 1 << 60;
+        let white_can_castle = white_king && !is_set!(self.flags, FLAG_WHITE_KING_MOVED);
+        let black_can_castle = black_king && !is_set!(self.flags, FLAG_BLACK_KING_MOVED);
+        let white_rooks = self.pieces(Rook, true);
+        let black_rooks = self.pieces(Rook, false);
+        let rook = |rooks: u64, at: ChessPosition, flag: i8| is_set!(rooks, at) && !is_set!(self.flags, flag);
+        [
+            white_can_castle && rook(white_rooks, 7, FLAG_WHITE_KING_ROOK_MOVED),
+            white_can_castle && rook(white_rooks, 0, FLAG_WHITE_QUEEN_ROOK_MOVED),
+            black_can_castle && rook(black_rooks, 63, FLAG_BLACK_KING_ROOK_MOVED),
+            black_can_castle && rook(black_rooks, 56, FLAG_BLACK_QUEEN_ROOK_MOVED),
+        ]
+    }
+
+    /// Writes the position in the Forsyth-Edwards Notation.
+    ///
+    /// The game doesn't store the move counters, which are always written as `0 1`. The castling
+    /// rights are the ones of `castling_rights`.
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+        for row in (0..8).rev() {
+            let mut empty = 0;
+            for col in 0..8 {
+                let at = pos_to_index(col, row);
+                let Some(piece) = self.type_at_index(at) else {
+                    empty += 1;
+                    continue;
+                };
+                if empty > 0 {
+                    fen.push_str(&empty.to_string());
+                    empty = 0;
+                }
+                let c = match piece {
+                    Pawn => 'p',
+                    Knight => 'n',
+                    Bishop => 'b',
+                    Rook => 'r',
+                    Queen => 'q',
+                    King => 'k',
+                };
+                fen.push(if is_set!(self.whites, at) {
+                    c.to_ascii_uppercase()
+                } else {
+                    c
+                });
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if row > 0 {
+                fen.push('/');
+            }
+        }
+
+        fen.push_str(if self.white_to_play() { " w " } else { " b " });
+
+        let mut castling = String::new();
+        for (available, c) in self.castling_rights().into_iter().zip(['K', 'Q', 'k', 'q']) {
+            if available {
+                castling.push(c);
+            }
+        }
+        fen.push_str(if castling.is_empty() { "-" } else { &castling });
+
+        if self.en_passant_target == 0 {
+            fen.push_str(" -");
+        } else {
+            let target = self.en_passant_target.trailing_zeros() as ChessPosition;
+            fen.push_str(&format!(" {}", index_to_chesspos(target)));
+        }
+        fen.push_str(" 0 1");
+        fen
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::moves::Move;
 
     #[test]
     fn test_from_fen_standard_game() {
@@ -208,5 +320,60 @@ mod tests {
 
         fen_game.display()
     }
+
+    #[test]
+    fn test_to_fen() {
+        assert_eq!(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            ChessGame::standard_game().to_fen()
+        );
+
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1";
+        assert_eq!(fen, ChessGame::from_fen(fen).to_fen());
+
+        // Castling rights are lost when the king moves, and the en passant target is written
+        let mut game = ChessGame::standard_game();
+        game.apply_move_unsafe(&Move::from_str("e2", "e4", true));
+        game.apply_move_unsafe(&Move::from_str("e7", "e5", false));
+        game.apply_move_unsafe(&Move::from_str("e1", "e2", true));
+        game.apply_move_unsafe(&Move::from_str("d7", "d5", false));
+        assert_eq!(
+            "rnbqkbnr/ppp2ppp/8/3pp3/4P3/8/PPPPKPPP/RNBQ1BNR w kq d6 0 1",
+            game.to_fen()
+        );
+
+        // A castling right is lost when its rook moves, even if it comes back
+        game.apply_move_unsafe(&Move::from_str("h7", "h6", false));
+        game.apply_move_unsafe(&Move::from_str("h8", "h7", false));
+        game.apply_move_unsafe(&Move::from_str("h7", "h8", false));
+        assert_eq!([false, false, false, true], game.castling_rights());
+    }
+
+    #[test]
+    fn test_from_fen_castling_and_en_passant() {
+        for fen in [
+            "r3k2r/8/8/8/8/8/8/R3K2R w - - 0 1",
+            "r3k2r/8/8/8/8/8/8/R3K2R w Kq - 0 1",
+            "r3k2r/8/8/8/8/8/8/R3K2R b Qk - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 1",
+        ] {
+            assert_eq!(fen, ChessGame::from_fen(fen).to_fen());
+        }
+
+        // Without the castling rights, the king can't castle with the rooks on their squares
+        let game = ChessGame::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w Kq - 0 1");
+        let castles: Vec<_> = game
+            .legal_moves(true)
+            .into_iter()
+            .filter(|m| m.from == 4 && (m.to == 2 || m.to == 6))
+            .map(|m| m.to)
+            .collect();
+        assert_eq!(vec![6], castles);
+
+        // The pawn can be captured en passant
+        let game = ChessGame::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1");
+        assert_eq!(Some(43), game.en_passant_square());
+        assert!(game.legal_moves(true).iter().any(|m| m.from == 36 && m.to == 43));
+    }
 }
 
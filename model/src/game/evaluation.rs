@@ -0,0 +1,158 @@
+use crate::chess_type::ScoreType;
+use crate::game::parameters::EvaluationParameters;
+use crate::game::pawns::PawnStructure;
+use crate::game::ChessGame;
+use crate::utils::consume_bits;
+
+/// Contribution of each piece to the game phase.
+/// The phase goes from `MAX_PHASE` (all the pieces are on the board) to 0 (only pawns and kings).
+const KNIGHT_PHASE: ScoreType = 1;
+const BISHOP_PHASE: ScoreType = 1;
+const ROOK_PHASE: ScoreType = 2;
+const QUEEN_PHASE: ScoreType = 4;
+pub const MAX_PHASE: ScoreType = 24;
+
+impl ChessGame {
+    /// Returns the phase of the game, from `MAX_PHASE` in the opening to 0 when there are only
+    /// pawns and kings left.
+    pub fn phase(&self) -> ScoreType {
+        let phase = self.knights.count_ones() as ScoreType * KNIGHT_PHASE
+            + self.bishops.count_ones() as ScoreType * BISHOP_PHASE
+            + self.rooks.count_ones() as ScoreType * ROOK_PHASE
+            + self.queens.count_ones() as ScoreType * QUEEN_PHASE;
+        // Promotions can increase the phase above its initial value
+        phase.min(MAX_PHASE)
+    }
+
+    /// Tapered evaluation of the material, of the placement of the pieces, of the pawn structure,
+    /// of the safety of the kings and of the mobility of the pieces.
+    ///
+    /// The middlegame and endgame scores are computed separately, and interpolated using the
+    /// phase of the game. The score is given in centipawns, from white's point of view.
+    pub(crate) fn tapered_evaluation(
+        &self,
+        pawns: &PawnStructure,
+        parameters: &EvaluationParameters,
+    ) -> ScoreType {
+        let (mut mg, mut eg) = self.material_and_placement(parameters);
+        mg += pawns.mg;
+        eg += pawns.eg + self.passed_pawns_king_distance(pawns, parameters);
+
+        let (king_mg, king_eg) = self.king_safety(parameters);
+        mg += king_mg;
+        eg += king_eg;
+
+        let (mobility_mg, mobility_eg) = self.mobility(parameters);
+        mg += mobility_mg;
+        eg += mobility_eg;
+
+        let phase = self.phase();
+        (mg * phase + eg * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+
+    /// Evaluates the position with the given weights, in centipawns, from white's point of view.
+    ///
+    /// Unlike `score`, the pawn structure is always computed: this is meant to tune the weights.
+    pub fn score_with_parameters(&self, parameters: &EvaluationParameters) -> ScoreType {
+        self.tapered_evaluation(&self.pawn_structure_with(parameters), parameters)
+    }
+
+    /// Returns the middlegame and endgame scores of the material and of the piece-square tables.
+    fn material_and_placement(&self, parameters: &EvaluationParameters) -> (ScoreType, ScoreType) {
+        let mut mg = 0;
+        let mut eg = 0;
+
+        let pieces = [
+            self.pawns,
+            self.knights,
+            self.bishops,
+            self.rooks,
+            self.queens,
+            self.kings,
+        ];
+
+        for (i, bitboard) in pieces.into_iter().enumerate() {
+            // The tables are read as seen by white: white pieces need to be flipped vertically.
+            consume_bits!(bitboard & self.whites, sq, {
+                mg += parameters.mg_values[i] + parameters.mg_tables[i][sq ^ 56];
+                eg += parameters.eg_values[i] + parameters.eg_tables[i][sq ^ 56];
+            });
+            consume_bits!(bitboard & !self.whites, sq, {
+                mg -= parameters.mg_values[i] + parameters.mg_tables[i][sq];
+                eg -= parameters.eg_values[i] + parameters.eg_tables[i][sq];
+            });
+        }
+
+        (mg, eg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_type::ScoreType;
+    use crate::chess_type::Type::{King, Knight, Pawn, Queen};
+    use crate::game::evaluation::MAX_PHASE;
+    use crate::game::parameters::DEFAULT_PARAMETERS;
+    use crate::game::ChessGame;
+
+    fn tapered_evaluation(game: &ChessGame) -> ScoreType {
+        game.tapered_evaluation(&game.pawn_structure(), &DEFAULT_PARAMETERS)
+    }
+
+    #[test]
+    fn test_phase() {
+        assert_eq!(MAX_PHASE, ChessGame::standard_game().phase());
+        assert_eq!(
+            0,
+            ChessGame::from_fen("4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1").phase()
+        );
+    }
+
+    #[test]
+    fn test_symmetric_positions_are_equal() {
+        assert_eq!(0, tapered_evaluation(&ChessGame::standard_game()));
+        let game = ChessGame::from_fen(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R w KQkq - 4 4",
+        );
+        assert_eq!(0, tapered_evaluation(&game));
+    }
+
+    #[test]
+    fn test_knight_prefers_the_centre() {
+        let mut centre = ChessGame::empty();
+        centre.set_piece(King, true, "g1");
+        centre.set_piece(King, false, "g8");
+        let mut rim = centre;
+
+        centre.set_piece(Knight, true, "e4");
+        rim.set_piece(Knight, true, "a4");
+        assert!(tapered_evaluation(&centre) > tapered_evaluation(&rim));
+    }
+
+    #[test]
+    fn test_king_safety_depends_on_phase() {
+        // With queens on the board, the king is safer in its corner
+        let mut middlegame = ChessGame::empty();
+        middlegame.set_piece(Queen, true, "d1");
+        middlegame.set_piece(Queen, false, "d8");
+        middlegame.set_piece(Queen, true, "c1");
+        middlegame.set_piece(Queen, false, "c8");
+        middlegame.set_piece(King, false, "g8");
+        let mut castled = middlegame;
+        castled.set_piece(King, true, "g1");
+        let mut centralized = middlegame;
+        centralized.set_piece(King, true, "e4");
+        assert!(tapered_evaluation(&castled) > tapered_evaluation(&centralized));
+
+        // With only pawns, the king must be active
+        let mut endgame = ChessGame::empty();
+        endgame.set_piece(Pawn, true, "a2");
+        endgame.set_piece(Pawn, false, "a7");
+        endgame.set_piece(King, false, "g8");
+        let mut castled = endgame;
+        castled.set_piece(King, true, "g1");
+        let mut centralized = endgame;
+        centralized.set_piece(King, true, "e4");
+        assert!(tapered_evaluation(&castled) < tapered_evaluation(&centralized));
+    }
+}
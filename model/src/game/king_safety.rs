@@ -0,0 +1,163 @@
+use crate::chess_type::ScoreType;
+use crate::game::attacks::ChessAttacks;
+use crate::game::parameters::EvaluationParameters;
+use crate::game::precomputation::{FILE_MASKS, KING_ATTACK_MASKS};
+use crate::game::ChessGame;
+use crate::utils::{consume_bits, pieces_for_color};
+
+/// The penalty grows with the square of the attack weights, since one attacker alone is rarely
+/// dangerous. It is divided by this value, and capped by `MAX_ATTACK_PENALTY`.
+const ATTACK_PENALTY_DIVISOR: ScoreType = 4;
+const MAX_ATTACK_PENALTY: ScoreType = 500;
+
+impl ChessGame {
+    /// Evaluates the safety of both kings, and returns the middlegame and the endgame scores from
+    /// white's point of view.
+    pub(crate) fn king_safety(&self, parameters: &EvaluationParameters) -> (ScoreType, ScoreType) {
+        let (white_mg, white_eg) = self.king_safety_for_color(true, parameters);
+        let (black_mg, black_eg) = self.king_safety_for_color(false, parameters);
+        (white_mg - black_mg, white_eg - black_eg)
+    }
+
+    /// Evaluates the safety of the king of one player: pawn shield and storm, open files near the
+    /// king and pieces attacking the squares around the king.
+    fn king_safety_for_color(
+        &self,
+        white: bool,
+        parameters: &EvaluationParameters,
+    ) -> (ScoreType, ScoreType) {
+        let king = pieces_for_color!(self.whites, self.kings, white);
+        if king == 0 {
+            return (0, 0);
+        }
+        let king_sq = king.trailing_zeros() as usize;
+        let king_rank = king_sq / 8;
+        let king_file = king_sq % 8;
+
+        let own_pawns = pieces_for_color!(self.whites, self.pawns, white);
+        let enemy_pawns = self.pawns & !own_pawns;
+
+        let mut mg = 0;
+
+        // Pawn structure on the files around the king
+        for file in king_file.saturating_sub(1)..=(king_file + 1).min(7) {
+            let own_on_file = own_pawns & FILE_MASKS[file];
+            let enemy_on_file = enemy_pawns & FILE_MASKS[file];
+
+            if own_on_file == 0 {
+                mg += if enemy_on_file == 0 {
+                    parameters.open_file_near_king
+                } else {
+                    parameters.semi_open_file_near_king
+                };
+            }
+
+            // Shield: the friendly pawns right in front of the king
+            consume_bits!(own_on_file, sq, {
+                if let Some(distance) = Self::rank_distance_in_front(king_rank, sq / 8, white) {
+                    if distance < parameters.pawn_shield.len() {
+                        mg += parameters.pawn_shield[distance];
+                    }
+                }
+            });
+
+            // Storm: the enemy pawns coming towards the king
+            consume_bits!(enemy_on_file, sq, {
+                if let Some(distance) = Self::rank_distance_in_front(king_rank, sq / 8, white) {
+                    mg += parameters.pawn_storm[distance];
+                }
+            });
+        }
+
+        // Attacks of the enemy pieces on the king zone
+        let zone = KING_ATTACK_MASKS[king_sq] | king;
+        let [knight_weight, bishop_weight, rook_weight, queen_weight] =
+            parameters.king_attack_weights;
+        let units = knight_weight
+            * (self.get_attacked_squares_knight(!white) & zone).count_ones() as ScoreType
+            + bishop_weight
+                * (self.get_attacked_squares_bishop(!white) & zone).count_ones() as ScoreType
+            + rook_weight
+                * (self.get_attacked_squares_rook(!white) & zone).count_ones() as ScoreType
+            + queen_weight
+                * (self.get_attacked_squares_queen(!white) & zone).count_ones() as ScoreType;
+        let attack_penalty = (units * units / ATTACK_PENALTY_DIVISOR).min(MAX_ATTACK_PENALTY);
+
+        // In the endgame, there are too few pieces to threaten the king: only the attacks count
+        (mg - attack_penalty, -attack_penalty / 2)
+    }
+
+    /// Returns the number of ranks between the king and a pawn which is in front of the king, as
+    /// seen by the owner of the king. Returns `None` if the pawn is not in front of the king.
+    fn rank_distance_in_front(king_rank: usize, pawn_rank: usize, white: bool) -> Option<usize> {
+        if white && pawn_rank > king_rank {
+            Some(pawn_rank - king_rank)
+        } else if !white && pawn_rank < king_rank {
+            Some(king_rank - pawn_rank)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::parameters::DEFAULT_PARAMETERS;
+    use crate::game::ChessGame;
+
+    #[test]
+    fn test_symmetric_king_safety() {
+        assert_eq!(
+            (0, 0),
+            ChessGame::standard_game().king_safety(&DEFAULT_PARAMETERS)
+        );
+    }
+
+    #[test]
+    fn test_castled_king_is_safer() {
+        // Same position, but white has castled in the first one
+        let castled = ChessGame::from_fen(
+            "r1bq1rk1/pppp1ppp/2n2n2/2b1p3/2B1P3/2N2N2/PPPP1PPP/R1BQ1RK1 w - - 6 5",
+        );
+        let uncastled = ChessGame::from_fen(
+            "r1bq1rk1/pppp1ppp/2n2n2/2b1p3/2B1P3/2N2N2/PPPP1PPP/R1BQK2R w - - 6 5",
+        );
+        assert!(
+            castled.king_safety(&DEFAULT_PARAMETERS).0
+                > uncastled.king_safety(&DEFAULT_PARAMETERS).0
+        );
+        assert!(castled.score() > uncastled.score());
+    }
+
+    #[test]
+    fn test_open_files_near_the_king() {
+        // Castled king with all its pawns, versus castled king without the g pawn
+        let closed = ChessGame::from_fen("6k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1");
+        let open = ChessGame::from_fen("6k1/5ppp/8/8/8/8/5P1P/6K1 w - - 0 1");
+        assert!(
+            closed.king_safety(&DEFAULT_PARAMETERS).0 > open.king_safety(&DEFAULT_PARAMETERS).0
+        );
+    }
+
+    #[test]
+    fn test_pawn_storm() {
+        // The black pawns on the king side are advancing towards the white king. This also weakens
+        // the black king, so only the safety of the white king is compared.
+        let calm = ChessGame::from_fen("6k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1");
+        let storm = ChessGame::from_fen("6k1/5p2/8/8/6pp/8/5PPP/6K1 w - - 0 1");
+        assert!(
+            calm.king_safety_for_color(true, &DEFAULT_PARAMETERS).0
+                > storm.king_safety_for_color(true, &DEFAULT_PARAMETERS).0
+        );
+    }
+
+    #[test]
+    fn test_attackers_near_the_king() {
+        // The black queen and rook are attacking the squares around the white king
+        let quiet = ChessGame::from_fen("r2qr1k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1");
+        let attacked = ChessGame::from_fen("6k1/5ppp/8/8/8/6r1/5PPq/6K1 w - - 0 1");
+        assert!(
+            quiet.king_safety(&DEFAULT_PARAMETERS).0 > attacked.king_safety(&DEFAULT_PARAMETERS).0
+        );
+    }
+}
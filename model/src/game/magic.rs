@@ -0,0 +1,351 @@
+use once_cell::sync::Lazy;
+
+/// Directions of the rook, as (rank, file) steps
+const ROOK_DIRECTIONS: [(isize, isize); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+/// Directions of the bishop, as (rank, file) steps
+const BISHOP_DIRECTIONS: [(isize, isize); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+// The magic numbers were found with a random search: random numbers with few bits set are tried
+// until all the occupancies of the square give an index which does not collide with the index of
+// an occupancy with different attacks.
+
+/// Magic numbers of the rook, for each square
+const ROOK_MAGICS: [u64; 64] = [
+    0x008000908064C000,
+    0x0040200040001000,
+    0x0180100080A0010A,
+    0x8880041000800800,
+    0x1200100201200804,
+    0x0200020004011008,
+    0x2180010000800600,
+    0x0200005088210204,
+    0x0400800040008021,
+    0x0400400020005000,
+    0x8240801000200080,
+    0x8611001004200900,
+    0x008180800C001800,
+    0x0100800200800400,
+    0x0A02000102000408,
+    0x8020802300104280,
+    0x0080004000402000,
+    0xE010104000402000,
+    0x0800808010002000,
+    0xA280210008100100,
+    0x0001818014000800,
+    0xA002010100080400,
+    0x0080240001020870,
+    0x0001020004048845,
+    0x0081826280004004,
+    0x2020810900284000,
+    0x0200100080802000,
+    0x0200080080100080,
+    0x8083080100100500,
+    0x4406000901000400,
+    0x0005020080800100,
+    0x0090204200008114,
+    0x0010400094800420,
+    0x0900804000802002,
+    0x0201001841002000,
+    0x4100080080801000,
+    0x4540040080800800,
+    0x0002001004040020,
+    0x0281195814001002,
+    0x1240800040800100,
+    0x0880042000524004,
+    0x02C080410206002C,
+    0x0801200241050010,
+    0x8400080010008080,
+    0x0008000500090010,
+    0x0082009084020008,
+    0x4012000108020004,
+    0x9000104D08860004,
+    0x2004204114800100,
+    0x0148802112400300,
+    0x0202842000100880,
+    0x001B080080900080,
+    0x001A002008100600,
+    0x0004008004020080,
+    0x5181000600040300,
+    0x0000044401128A00,
+    0x8044110480002441,
+    0x2008110084402202,
+    0x90806005090010C1,
+    0x000420310A004A42,
+    0x0023001004020801,
+    0x0882001008040102,
+    0x000230088118020C,
+    0x0000019025040042,
+];
+
+/// Magic numbers of the bishop, for each square
+const BISHOP_MAGICS: [u64; 64] = [
+    0x0020428400408200,
+    0x2008010104210004,
+    0x02D0009200480190,
+    0x0018158B00010100,
+    0x02C4042132048008,
+    0x020082202000C221,
+    0x4000421050080009,
+    0x0210140202022020,
+    0x00C0101410042248,
+    0x0405204800D48080,
+    0x3800C89200420002,
+    0x180844124A020440,
+    0x04403410A8002221,
+    0x4040209004200400,
+    0x084004020202A204,
+    0x3010002104022000,
+    0x00200240A9110900,
+    0x2302800404080210,
+    0x0204188800240010,
+    0x8048000C01401200,
+    0x120C001A11040900,
+    0x0000401200500440,
+    0x00004040840420A0,
+    0x0020930822880804,
+    0x4044401090900161,
+    0x0034100015210804,
+    0x8004100009010120,
+    0x48C8080000820500,
+    0x0080848004002000,
+    0x0801004012005044,
+    0x000080902C040400,
+    0x0004009005004100,
+    0x0B103010048A0200,
+    0x8004100203181A00,
+    0x0800140200100080,
+    0x8401010800910040,
+    0x0840010011290040,
+    0x40100214202E1000,
+    0x0842040040010840,
+    0x0028010040010860,
+    0x00080202A2051000,
+    0x4200841008084204,
+    0x0021120110000D02,
+    0x48C1004208000084,
+    0x0010088100414400,
+    0x0021101000420580,
+    0x0010040558401410,
+    0x200C0C82A1050205,
+    0x0011108820088000,
+    0x0001011910120402,
+    0x1580008608091248,
+    0x8010018020880C02,
+    0x20A1101032088480,
+    0x0080100408082800,
+    0x28100401140401C0,
+    0x8002102200930012,
+    0x4001040082080200,
+    0x082200A498081808,
+    0x000508610080D003,
+    0x0052020044842402,
+    0x4800A00140C84840,
+    0x5000000848080820,
+    0x0101086004240040,
+    0x0028280808005014,
+];
+
+/// Lookup table of the attacks of one type of sliding piece, indexed with magic bitboards.
+///
+/// For each square, only the squares which can block the piece matter: these are the squares of
+/// the rays, without the edges of the board (the `masks`). Multiplying the blockers by a "magic"
+/// number gathers their bits in the highest bits of the product, which gives a unique index in the
+/// table of attacks of the square.
+///
+/// https://www.chessprogramming.org/Magic_Bitboards
+struct MagicTable {
+    masks: [u64; 64],
+    magics: [u64; 64],
+    shifts: [u32; 64],
+    offsets: [usize; 64],
+    attacks: Vec<u64>,
+}
+
+impl MagicTable {
+    /// Fills the table of attacks of each square with its magic number.
+    fn new(directions: &[(isize, isize)], magics: [u64; 64]) -> Self {
+        let mut table = MagicTable {
+            masks: [0; 64],
+            magics,
+            shifts: [0; 64],
+            offsets: [0; 64],
+            attacks: Vec::new(),
+        };
+
+        for (sq, magic) in magics.into_iter().enumerate() {
+            let mask = relevant_blockers(sq, directions);
+            let bits = mask.count_ones();
+            let shift = 64 - bits;
+            let mut attacks = vec![None; 1 << bits];
+
+            // Enumerate all the subsets of the mask
+            let mut subset = 0u64;
+            loop {
+                let index = (subset.wrapping_mul(magic) >> shift) as usize;
+                let attack = sliding_attacks(sq, subset, directions);
+                // Two occupancies can share an index only if they have the same attacks
+                assert!(
+                    attacks[index].is_none_or(|a| a == attack),
+                    "Invalid magic number for square {sq}"
+                );
+                attacks[index] = Some(attack);
+
+                subset = subset.wrapping_sub(mask) & mask;
+                if subset == 0 {
+                    break;
+                }
+            }
+
+            table.masks[sq] = mask;
+            table.shifts[sq] = shift;
+            table.offsets[sq] = table.attacks.len();
+            // Some indices are never reached: their value doesn't matter
+            table
+                .attacks
+                .extend(attacks.into_iter().map(|a| a.unwrap_or_default()));
+        }
+
+        table
+    }
+
+    /// Returns the squares attacked from `sq`, given the occupancy of the board.
+    #[inline]
+    fn attacks(&self, sq: usize, occupancy: u64) -> u64 {
+        let blockers = occupancy & self.masks[sq];
+        let index = (blockers.wrapping_mul(self.magics[sq]) >> self.shifts[sq]) as usize;
+        self.attacks[self.offsets[sq] + index]
+    }
+}
+
+/// Computes the squares attacked by a sliding piece by walking along each direction until the
+/// edge of the board or an occupied square. Only used to fill the magic tables.
+fn sliding_attacks(sq: usize, occupancy: u64, directions: &[(isize, isize)]) -> u64 {
+    let mut attacks = 0;
+    for (dr, df) in directions {
+        let mut r = (sq / 8) as isize + dr;
+        let mut f = (sq % 8) as isize + df;
+        while (0..8).contains(&r) && (0..8).contains(&f) {
+            let target = (r * 8 + f) as usize;
+            attacks |= 1 << target;
+            if occupancy & (1 << target) != 0 {
+                break;
+            }
+            r += dr;
+            f += df;
+        }
+    }
+    attacks
+}
+
+/// Computes the squares which can block a sliding piece: the rays of the piece, without the last
+/// square of each ray since it is attacked whether it is occupied or not.
+fn relevant_blockers(sq: usize, directions: &[(isize, isize)]) -> u64 {
+    let mut mask = 0;
+    for (dr, df) in directions {
+        let mut r = (sq / 8) as isize + dr;
+        let mut f = (sq % 8) as isize + df;
+        while (0..8).contains(&(r + dr)) && (0..8).contains(&(f + df)) {
+            mask |= 1 << (r * 8 + f);
+            r += dr;
+            f += df;
+        }
+    }
+    mask
+}
+
+static ROOK_TABLE: Lazy<MagicTable> = Lazy::new(|| MagicTable::new(&ROOK_DIRECTIONS, ROOK_MAGICS));
+
+static BISHOP_TABLE: Lazy<MagicTable> =
+    Lazy::new(|| MagicTable::new(&BISHOP_DIRECTIONS, BISHOP_MAGICS));
+
+/// Returns the squares attacked by a rook in `sq`, given the occupancy of the board.
+/// The attacked squares include the occupied squares which block the rook.
+#[inline]
+pub(super) fn rook_attacks(sq: usize, occupancy: u64) -> u64 {
+    ROOK_TABLE.attacks(sq, occupancy)
+}
+
+/// Returns the squares attacked by a bishop in `sq`, given the occupancy of the board.
+#[inline]
+pub(super) fn bishop_attacks(sq: usize, occupancy: u64) -> u64 {
+    BISHOP_TABLE.attacks(sq, occupancy)
+}
+
+/// Returns the squares attacked by a queen in `sq`, given the occupancy of the board.
+#[inline]
+pub(super) fn queen_attacks(sq: usize, occupancy: u64) -> u64 {
+    rook_attacks(sq, occupancy) | bishop_attacks(sq, occupancy)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::magic::{
+        bishop_attacks, queen_attacks, rook_attacks, sliding_attacks, BISHOP_DIRECTIONS,
+        ROOK_DIRECTIONS,
+    };
+    use crate::utils::IntoChessPosition;
+
+    fn bb(squares: &[&str]) -> u64 {
+        squares
+            .iter()
+            .fold(0, |mask, sq| mask | (1 << sq.as_chess_position()))
+    }
+
+    #[test]
+    fn test_rook_attacks() {
+        // Rook in d4, blocked in d6 and f4
+        let occupancy = bb(&["d4", "d6", "f4"]);
+        let expected = bb(&["d5", "d6", "d3", "d2", "d1", "e4", "f4", "c4", "b4", "a4"]);
+        assert_eq!(
+            expected,
+            rook_attacks("d4".as_chess_position() as usize, occupancy)
+        );
+    }
+
+    #[test]
+    fn test_bishop_attacks() {
+        // Bishop in c1, blocked in e3
+        let occupancy = bb(&["c1", "e3"]);
+        let expected = bb(&["b2", "a3", "d2", "e3"]);
+        assert_eq!(
+            expected,
+            bishop_attacks("c1".as_chess_position() as usize, occupancy)
+        );
+        assert_eq!(
+            expected
+                | bb(&[
+                    "a1", "b1", "d1", "e1", "f1", "g1", "h1", "c2", "c3", "c4", "c5", "c6", "c7",
+                    "c8"
+                ]),
+            queen_attacks("c1".as_chess_position() as usize, occupancy)
+        );
+    }
+
+    #[test]
+    fn test_lookup_matches_ray_walk() {
+        // xorshift64* pseudo-random generator for the occupancies
+        let mut state = 0x9E3779B97F4A7C15u64;
+        let mut next = || {
+            state ^= state >> 12;
+            state ^= state << 25;
+            state ^= state >> 27;
+            state.wrapping_mul(0x2545F4914F6CDD1D)
+        };
+
+        for _ in 0..100 {
+            // Sparse occupancies, as in real games
+            let occupancy = next() & next();
+            for sq in 0..64 {
+                assert_eq!(
+                    sliding_attacks(sq, occupancy, &ROOK_DIRECTIONS),
+                    rook_attacks(sq, occupancy)
+                );
+                assert_eq!(
+                    sliding_attacks(sq, occupancy, &BISHOP_DIRECTIONS),
+                    bishop_attacks(sq, occupancy)
+                );
+            }
+        }
+    }
+}
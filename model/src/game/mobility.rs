@@ -0,0 +1,119 @@
+use crate::chess_type::ScoreType;
+use crate::game::attacks::ChessAttacks;
+use crate::game::magic::{bishop_attacks, queen_attacks, rook_attacks};
+use crate::game::parameters::EvaluationParameters;
+use crate::game::precomputation::KNIGHT_ATTACK_MASKS;
+use crate::game::ChessGame;
+use crate::utils::{consume_bits, pieces_for_color};
+
+impl ChessGame {
+    /// Evaluates the mobility of the pieces of both players, and returns the middlegame and the
+    /// endgame scores from white's point of view.
+    ///
+    /// The mobility is computed with the attack bitboards, without generating the moves.
+    pub(crate) fn mobility(&self, parameters: &EvaluationParameters) -> (ScoreType, ScoreType) {
+        let (white_mg, white_eg) = self.mobility_for_color(true, parameters);
+        let (black_mg, black_eg) = self.mobility_for_color(false, parameters);
+        (white_mg - black_mg, white_eg - black_eg)
+    }
+
+    /// Counts, for each knight, bishop, rook and queen of a player, the squares it attacks which
+    /// are neither occupied by a friendly piece nor attacked by an enemy pawn. For the pawns, only
+    /// the enemy pieces they attack are counted.
+    fn mobility_for_color(
+        &self,
+        white: bool,
+        parameters: &EvaluationParameters,
+    ) -> (ScoreType, ScoreType) {
+        let occupancy =
+            self.pawns | self.knights | self.bishops | self.rooks | self.queens | self.kings;
+        let own_pieces = occupancy & if white { self.whites } else { !self.whites };
+        let enemy_pieces = occupancy & !own_pieces;
+        let safe_squares = !own_pieces & !self.get_attacked_squares_pawn(!white);
+
+        let captures = (self.get_attacked_squares_pawn(white) & enemy_pieces).count_ones();
+        let mut mg = captures as ScoreType * parameters.pawn_mobility.0;
+        let mut eg = captures as ScoreType * parameters.pawn_mobility.1;
+        let [knight_mobility, bishop_mobility, rook_mobility, queen_mobility] = parameters.mobility;
+        let mut add = |attacks: u64, weight: (ScoreType, ScoreType)| {
+            let count = (attacks & safe_squares).count_ones() as ScoreType;
+            mg += count * weight.0;
+            eg += count * weight.1;
+        };
+
+        consume_bits!(pieces_for_color!(self.whites, self.knights, white), sq, {
+            add(KNIGHT_ATTACK_MASKS[sq], knight_mobility);
+        });
+        consume_bits!(pieces_for_color!(self.whites, self.bishops, white), sq, {
+            add(bishop_attacks(sq, occupancy), bishop_mobility);
+        });
+        consume_bits!(pieces_for_color!(self.whites, self.rooks, white), sq, {
+            add(rook_attacks(sq, occupancy), rook_mobility);
+        });
+        consume_bits!(pieces_for_color!(self.whites, self.queens, white), sq, {
+            add(queen_attacks(sq, occupancy), queen_mobility);
+        });
+
+        (mg, eg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_type::Type::{Bishop, King, Knight, Pawn};
+    use crate::game::parameters::DEFAULT_PARAMETERS;
+    use crate::game::ChessGame;
+
+    #[test]
+    fn test_symmetric_mobility() {
+        assert_eq!(
+            (0, 0),
+            ChessGame::standard_game().mobility(&DEFAULT_PARAMETERS)
+        );
+    }
+
+    #[test]
+    fn test_knight_mobility() {
+        let mut game = ChessGame::empty();
+        game.set_piece(King, true, "a1");
+        game.set_piece(King, false, "h8");
+        game.set_piece(Knight, true, "e4");
+        // 8 squares for the knight in the centre
+        assert_eq!(8 * 4, game.mobility(&DEFAULT_PARAMETERS).0);
+
+        // A square occupied by a friendly piece is not available
+        game.set_piece(Pawn, true, "f2");
+        assert_eq!(7 * 4, game.mobility(&DEFAULT_PARAMETERS).0);
+
+        // And neither are the squares attacked by an enemy pawn: d6 and f6 are attacked by e7
+        game.set_piece(Pawn, false, "e7");
+        assert_eq!(5 * 4, game.mobility(&DEFAULT_PARAMETERS).0);
+    }
+
+    #[test]
+    fn test_blocked_bishop() {
+        let mut open = ChessGame::empty();
+        open.set_piece(King, true, "a1");
+        open.set_piece(King, false, "a8");
+        open.set_piece(Bishop, true, "c1");
+        let mut blocked = open;
+        blocked.set_piece(Pawn, true, "b2");
+        blocked.set_piece(Pawn, true, "d2");
+        assert!(open.mobility(&DEFAULT_PARAMETERS).0 > blocked.mobility(&DEFAULT_PARAMETERS).0);
+        assert_eq!(0, blocked.mobility(&DEFAULT_PARAMETERS).0);
+    }
+
+    #[test]
+    fn test_pawn_captures() {
+        let mut game = ChessGame::empty();
+        game.set_piece(King, true, "a1");
+        game.set_piece(King, false, "a8");
+        game.set_piece(Pawn, true, "e4");
+        assert_eq!((0, 0), game.mobility_for_color(true, &DEFAULT_PARAMETERS));
+
+        // The pawn attacks the knight, even if the knight is defended by a pawn
+        game.set_piece(Knight, false, "d5");
+        game.set_piece(Pawn, false, "c6");
+        assert_eq!((5, 5), game.mobility_for_color(true, &DEFAULT_PARAMETERS));
+    }
+}
@@ -1,10 +1,12 @@
 use crate::chess_type::Type::{Bishop, King, Knight, Pawn, Queen, Rook};
 use crate::chess_type::Type;
 use crate::game::attacks::ChessAttacks;
-use crate::game::precomputation::{
-    KING_ATTACK_MASKS, KNIGHT_ATTACK_MASKS, PAWN_ATTACK_MASKS, SLIDING_ATTACK_MASKS,
+use crate::game::magic::{bishop_attacks, queen_attacks, rook_attacks};
+use crate::game::precomputation::{KING_ATTACK_MASKS, KNIGHT_ATTACK_MASKS, PAWN_ATTACK_MASKS};
+use crate::game::{
+    ChessGame, FLAG_BLACK_KING_MOVED, FLAG_BLACK_KING_ROOK_MOVED, FLAG_BLACK_QUEEN_ROOK_MOVED,
+    FLAG_WHITE_KING_MOVED, FLAG_WHITE_KING_ROOK_MOVED, FLAG_WHITE_QUEEN_ROOK_MOVED,
 };
-use crate::game::{ChessGame, FLAG_BLACK_KING_MOVED, FLAG_WHITE_KING_MOVED};
 use crate::motion_iterator::StepMotionIterator;
 use crate::moves::MoveQuality::{EqualCapture, GoodCapture};
 use crate::moves::{
@@ -12,7 +14,6 @@ use crate::moves::{
 };
 use crate::moves_container::MovesContainer;
 use crate::utils::{consume_bits, is_set, pieces_for_color, ChessPosition};
-use std::ops::Range;
 
 impl ChessGame {
     /// Fills the provided container with all the available moves at the current position.
@@ -309,7 +310,7 @@ impl ChessGame {
             rook_left,
             Rook,
             occupancy,
-            0..4,
+            rook_attacks,
             white_playing,
             container,
         );
@@ -321,7 +322,7 @@ impl ChessGame {
             bishops,
             Bishop,
             occupancy,
-            4..8,
+            bishop_attacks,
             white_playing,
             container,
         );
@@ -334,7 +335,7 @@ impl ChessGame {
             // The score of the queen is the problem...
             Queen,
             occupancy,
-            0..8,
+            queen_attacks,
             white_playing,
             container,
         );
@@ -343,11 +344,22 @@ impl ChessGame {
 
         // White castling
 
-        if white_playing && !is_set!(self.flags, FLAG_WHITE_KING_MOVED) {
+        // The king and the rooks must be on their initial squares: the flags are not enough for games
+        // which were not created from the standard position.
+        let white_rooks = self.rooks & self.whites;
+        let black_rooks = self.rooks & !self.whites;
+        let rook_moved = |flag| is_set!(self.flags, flag);
+
+        if white_playing
+            && !is_set!(self.flags, FLAG_WHITE_KING_MOVED)
+            && is_set!(self.kings & self.whites, 4)
+        {
             let mut attacked: Option<u64> = None;
 
             // Check occupancy for first condition
-            if !is_set!(occupancy, 5) && !is_set!(occupancy, 6) {
+            if is_set!(white_rooks, 7)
+                && !rook_moved(FLAG_WHITE_KING_ROOK_MOVED)
+                && !is_set!(occupancy, 5) && !is_set!(occupancy, 6) {
                 // Compute attacked squares only if needed
                 if attacked.is_none() {
                     attacked = Some(self.get_attacked_squares(false));
@@ -361,7 +373,12 @@ impl ChessGame {
             }
 
             // Check occupancy for second condition
-            if !is_set!(occupancy, 3) && !is_set!(occupancy, 2) && !is_set!(occupancy, 1) {
+            if is_set!(white_rooks, 0)
+                && !rook_moved(FLAG_WHITE_QUEEN_ROOK_MOVED)
+                && !is_set!(occupancy, 3)
+                && !is_set!(occupancy, 2)
+                && !is_set!(occupancy, 1)
+            {
                 if attacked.is_none() {
                     attacked = Some(self.get_attacked_squares(false));
                 }
@@ -376,10 +393,15 @@ impl ChessGame {
 
         // black castling
 
-        if !white_playing && !is_set!(self.flags, FLAG_BLACK_KING_MOVED) {
+        if !white_playing
+            && !is_set!(self.flags, FLAG_BLACK_KING_MOVED)
+            && is_set!(self.kings & !self.whites, 60)
+        {
             let mut attacked: Option<u64> = None;
 
-            if !is_set!(occupancy, 61) && !is_set!(occupancy, 62) {
+            if is_set!(black_rooks, 63)
+                && !rook_moved(FLAG_BLACK_KING_ROOK_MOVED)
+                && !is_set!(occupancy, 61) && !is_set!(occupancy, 62) {
                 if attacked.is_none() {
                     attacked = Some(self.get_attacked_squares(true));
                 }
@@ -391,7 +413,12 @@ impl ChessGame {
             }
 
             // Check occupancy for black's large castle
-            if !is_set!(occupancy, 59) && !is_set!(occupancy, 58) && !is_set!(occupancy, 57) {
+            if is_set!(black_rooks, 56)
+                && !rook_moved(FLAG_BLACK_QUEEN_ROOK_MOVED)
+                && !is_set!(occupancy, 59)
+                && !is_set!(occupancy, 58)
+                && !is_set!(occupancy, 57)
+            {
                 if attacked.is_none() {
                     attacked = Some(self.get_attacked_squares(true));
                 }
@@ -409,33 +436,28 @@ impl ChessGame {
         pieces: u64,
         t: Type,
         occupancy: u64,
-        direction_indices: Range<usize>,
+        attacks: fn(usize, u64) -> u64,
         white_playing: bool,
         container: &mut T,
     ) {
-        consume_bits!(pieces, from, {
-            // For each direction
-            for dir in direction_indices.clone() {
-                // Get the attack ray for this direction from the precomputed sliding masks
-                let ray = &SLIDING_ATTACK_MASKS[dir][from];
-                // Go through all the positions
-                for to in ray {
-                    let occupied = is_set!(occupancy, to);
-                    if !occupied {
-                        container.push(Move::new(from as ChessPosition, *to, white_playing));
-                    } else if is_set!(self.whites, to) != white_playing {
-                        let mut m = Move::new(from as ChessPosition, *to, white_playing);
-                        if let Some(captured) = self.type_at_index(m.to) {
-                            m.set_quality_from_scores(t, captured);
-                        }
-                        container.push(m);
-                    }
+        let own_pieces = if white_playing {
+            self.whites
+        } else {
+            occupancy & !self.whites
+        };
 
-                    if occupied {
-                        break;
+        consume_bits!(pieces, from, {
+            // A single lookup gives all the squares reachable in every direction
+            let targets = attacks(from, occupancy) & !own_pieces;
+            consume_bits!(targets, to, {
+                let mut m = Move::new(from as ChessPosition, to as ChessPosition, white_playing);
+                if is_set!(occupancy, to) {
+                    if let Some(captured) = self.type_at_index(m.to) {
+                        m.set_quality_from_scores(t, captured);
                     }
                 }
-            }
+                container.push(m);
+            });
         });
     }
 }
@@ -509,7 +531,7 @@ mod tests {
 
     #[test]
     fn test_small_castle_no_enemies() {
-        let game = ChessGame::from_fen("4k2r/4pppp/8/8/8/8/4PPPP/4K2R w - - 0 1");
+        let game = ChessGame::from_fen("4k2r/4pppp/8/8/8/8/4PPPP/4K2R w Kk - 0 1");
         let mut container = SimpleMovesContainer::new();
 
         // White can small castle
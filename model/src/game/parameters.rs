@@ -0,0 +1,347 @@
+use crate::chess_type::ScoreType;
+
+/// All the weights of the evaluation, in centipawns.
+///
+/// The terms which have a middlegame and an endgame value are stored as `(middlegame, endgame)`.
+/// The engine always uses `DEFAULT_PARAMETERS`: the other values are used to tune the evaluation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct EvaluationParameters {
+    /// Values of the pieces in the middlegame, in the order: pawn, knight, bishop, rook, queen,
+    /// king.
+    pub mg_values: [ScoreType; 6],
+    /// Values of the pieces in the endgame, in the same order as `mg_values`.
+    pub eg_values: [ScoreType; 6],
+    /// Middlegame piece-square tables, in the same order as `mg_values`.
+    ///
+    /// All the tables are written from white's point of view, as they would be seen on a board:
+    /// the first line is the 8th rank and the last line is the 1st rank.
+    pub mg_tables: [[ScoreType; 64]; 6],
+    /// Endgame piece-square tables, in the same order as `mg_values`.
+    pub eg_tables: [[ScoreType; 64]; 6],
+
+    /// Penalty for each extra pawn on a file
+    pub doubled_pawn: (ScoreType, ScoreType),
+    /// Penalty for a pawn without any friendly pawn on the adjacent files
+    pub isolated_pawn: (ScoreType, ScoreType),
+    /// Penalty for a pawn which can't be defended by a pawn, and can't advance safely
+    pub backward_pawn: (ScoreType, ScoreType),
+    /// Bonus of a pawn defended by another pawn or next to another pawn, indexed by the rank of the
+    /// pawn as seen by its owner.
+    pub connected_pawn: [ScoreType; 8],
+    /// Bonus of a passed pawn in the middlegame, indexed by the rank of the pawn as seen by its
+    /// owner.
+    pub passed_pawn_mg: [ScoreType; 8],
+    /// Bonus of a passed pawn in the endgame, indexed by the rank of the pawn as seen by its owner.
+    pub passed_pawn_eg: [ScoreType; 8],
+    /// In the endgame, a passed pawn is stronger when the enemy king is far from it and when the
+    /// friendly king is close to it. These are the weights for each square of distance to the
+    /// square in front of the pawn, multiplied by the advancement of the pawn.
+    pub passed_pawn_enemy_king_distance: ScoreType,
+    pub passed_pawn_own_king_distance: ScoreType,
+
+    /// Bonus for each pawn of the shield, in front of the king, indexed by the distance between the
+    /// rank of the pawn and the rank of the king (1 or 2).
+    pub pawn_shield: [ScoreType; 3],
+    /// Penalty for each enemy pawn advancing towards the king, indexed by the distance between the
+    /// rank of the pawn and the rank of the king.
+    pub pawn_storm: [ScoreType; 8],
+    /// Penalty for each file around the king without any friendly pawn (semi-open) and without any
+    /// pawn at all (open).
+    pub semi_open_file_near_king: ScoreType,
+    pub open_file_near_king: ScoreType,
+    /// Weight of each attack on the squares around the king, in the order: knight, bishop, rook,
+    /// queen.
+    pub king_attack_weights: [ScoreType; 4],
+
+    /// Bonus for each safe square attacked by a piece, in the order: knight, bishop, rook, queen.
+    /// Rooks and queens have more squares available, so each square is worth less.
+    pub mobility: [(ScoreType, ScoreType); 4],
+    /// The pawns only move forward, so only their captures are counted
+    pub pawn_mobility: (ScoreType, ScoreType),
+}
+
+// Piece-square tables
+//
+// All the tables are written from white's point of view, as they would be seen on a board:
+// the first line is the 8th rank and the last line is the 1st rank.
+
+#[rustfmt::skip]
+const MG_PAWN: [ScoreType; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     50,  50,  50,  50,  50,  50,  50,  50,
+     10,  10,  20,  30,  30,  20,  10,  10,
+      5,   5,  10,  25,  25,  10,   5,   5,
+      0,   0,   0,  20,  20,   0,   0,   0,
+      5,  -5, -10,   0,   0, -10,  -5,   5,
+      5,  10,  10, -20, -20,  10,  10,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const EG_PAWN: [ScoreType; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     50,  50,  50,  50,  50,  50,  50,  50,
+     30,  30,  30,  30,  30,  30,  30,  30,
+     20,  20,  20,  20,  20,  20,  20,  20,
+     10,  10,  10,  10,  10,  10,  10,  10,
+      5,   5,   5,   5,   5,   5,   5,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const KNIGHT: [ScoreType; 64] = [
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    -40, -20,   0,   0,   0,   0, -20, -40,
+    -30,   0,  10,  15,  15,  10,   0, -30,
+    -30,   5,  15,  20,  20,  15,   5, -30,
+    -30,   0,  15,  20,  20,  15,   0, -30,
+    -30,   5,  10,  15,  15,  10,   5, -30,
+    -40, -20,   0,   5,   5,   0, -20, -40,
+    -50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+#[rustfmt::skip]
+const BISHOP: [ScoreType; 64] = [
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   5,   5,  10,  10,   5,   5, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,  10,  10,  10,  10,  10,  10, -10,
+    -10,   5,   0,   0,   0,   0,   5, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const MG_ROOK: [ScoreType; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+      5,  10,  10,  10,  10,  10,  10,   5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+      0,   0,   0,   5,   5,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const EG_ROOK: [ScoreType; 64] = [
+      5,   5,   5,   5,   5,   5,   5,   5,
+     10,  10,  10,  10,  10,  10,  10,  10,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const QUEEN: [ScoreType; 64] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+     -5,   0,   5,   5,   5,   5,   0,  -5,
+      0,   0,   5,   5,   5,   5,   0,  -5,
+    -10,   5,   5,   5,   5,   5,   0, -10,
+    -10,   0,   5,   0,   0,   0,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+/// In the middlegame, the king hides behind its pawns
+#[rustfmt::skip]
+const MG_KING: [ScoreType; 64] = [
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+     20,  20,   0,   0,   0,   0,  20,  20,
+     20,  30,  10,   0,   0,  10,  30,  20,
+];
+
+/// In the endgame, the king becomes an active piece and goes to the centre
+#[rustfmt::skip]
+const EG_KING: [ScoreType; 64] = [
+    -30, -20, -15, -10, -10, -15, -20, -30,
+    -20, -10,  -5,   0,   0,  -5, -10, -20,
+    -15,  -5,  10,  15,  15,  10,  -5, -15,
+    -15,  -5,  15,  20,  20,  15,  -5, -15,
+    -15,  -5,  15,  20,  20,  15,  -5, -15,
+    -15,  -5,  10,  15,  15,  10,  -5, -15,
+    -20, -15,   0,   0,   0,   0, -15, -20,
+    -30, -20, -15, -15, -15, -15, -20, -30,
+];
+
+/// Weights used by the engine
+pub const DEFAULT_PARAMETERS: EvaluationParameters = EvaluationParameters {
+    mg_values: [100, 320, 330, 500, 900, 20000],
+    eg_values: [120, 300, 320, 530, 950, 20000],
+    mg_tables: [MG_PAWN, KNIGHT, BISHOP, MG_ROOK, QUEEN, MG_KING],
+    eg_tables: [EG_PAWN, KNIGHT, BISHOP, EG_ROOK, QUEEN, EG_KING],
+
+    doubled_pawn: (-10, -20),
+    isolated_pawn: (-10, -15),
+    backward_pawn: (-8, -10),
+    connected_pawn: [0, 5, 7, 10, 15, 25, 40, 0],
+    passed_pawn_mg: [0, 5, 10, 15, 25, 40, 60, 0],
+    passed_pawn_eg: [0, 10, 15, 25, 45, 70, 110, 0],
+    passed_pawn_enemy_king_distance: 5,
+    passed_pawn_own_king_distance: 2,
+
+    pawn_shield: [0, 15, 8],
+    pawn_storm: [0, 0, -25, -15, -5, 0, 0, 0],
+    semi_open_file_near_king: -15,
+    open_file_near_king: -25,
+    king_attack_weights: [2, 2, 3, 5],
+
+    mobility: [(4, 4), (5, 5), (2, 4), (1, 2)],
+    pawn_mobility: (5, 5),
+};
+
+impl EvaluationParameters {
+    /// Returns all the weights which can be tuned, in a fixed order.
+    ///
+    /// The values of the kings are left out since both kings are always on the board, and so are
+    /// the weights of the ranks where a pawn can never be.
+    fn tunable_values_mut(&mut self) -> Vec<&mut ScoreType> {
+        let mut values: Vec<&mut ScoreType> = vec![];
+        values.extend(self.mg_values[..5].iter_mut());
+        values.extend(self.eg_values[..5].iter_mut());
+        for tables in [&mut self.mg_tables, &mut self.eg_tables] {
+            let (pawns, pieces) = tables.split_at_mut(1);
+            values.extend(pawns[0][8..56].iter_mut());
+            values.extend(pieces.iter_mut().flatten());
+        }
+
+        let pairs = [
+            &mut self.doubled_pawn,
+            &mut self.isolated_pawn,
+            &mut self.backward_pawn,
+            &mut self.pawn_mobility,
+        ];
+        for (mg, eg) in pairs.into_iter().chain(self.mobility.iter_mut()) {
+            values.push(mg);
+            values.push(eg);
+        }
+
+        values.extend(self.connected_pawn[1..7].iter_mut());
+        values.extend(self.passed_pawn_mg[1..7].iter_mut());
+        values.extend(self.passed_pawn_eg[1..7].iter_mut());
+        values.push(&mut self.passed_pawn_enemy_king_distance);
+        values.push(&mut self.passed_pawn_own_king_distance);
+        values.extend(self.pawn_shield[1..].iter_mut());
+        values.extend(self.pawn_storm[1..].iter_mut());
+        values.push(&mut self.semi_open_file_near_king);
+        values.push(&mut self.open_file_near_king);
+        values.extend(self.king_attack_weights.iter_mut());
+        values
+    }
+
+    /// Returns the vector of the weights which can be tuned
+    pub fn to_vector(&self) -> Vec<ScoreType> {
+        let mut copy = *self;
+        copy.tunable_values_mut().into_iter().map(|v| *v).collect()
+    }
+
+    /// Sets the weights which can be tuned from a vector returned by `to_vector`
+    pub fn set_vector(&mut self, vector: &[ScoreType]) {
+        let values = self.tunable_values_mut();
+        assert_eq!(values.len(), vector.len(), "Invalid number of parameters");
+        for (value, new_value) in values.into_iter().zip(vector) {
+            *value = *new_value;
+        }
+    }
+
+    /// Writes the parameters as the Rust source of `DEFAULT_PARAMETERS`
+    pub fn to_rust_source(&self) -> String {
+        let list = |values: &[ScoreType]| {
+            let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+            format!("[{}]", values.join(", "))
+        };
+        let pair = |(mg, eg): (ScoreType, ScoreType)| format!("({mg}, {eg})");
+        let tables = |tables: &[[ScoreType; 64]; 6]| {
+            let mut source = String::from("[\n");
+            for table in tables {
+                source += "        [\n";
+                for rank in table.chunks(8) {
+                    let values: Vec<String> = rank.iter().map(|v| format!("{v:>4}")).collect();
+                    source += &format!("            {},\n", values.join(","));
+                }
+                source += "        ],\n";
+            }
+            source + "    ]"
+        };
+        let mobility: Vec<String> = self.mobility.iter().map(|m| pair(*m)).collect();
+
+        let mut source = String::new();
+        source += "#[rustfmt::skip]\n";
+        source += "pub const DEFAULT_PARAMETERS: EvaluationParameters = EvaluationParameters {\n";
+        source += &format!("    mg_values: {},\n", list(&self.mg_values));
+        source += &format!("    eg_values: {},\n", list(&self.eg_values));
+        source += &format!("    mg_tables: {},\n", tables(&self.mg_tables));
+        source += &format!("    eg_tables: {},\n\n", tables(&self.eg_tables));
+        source += &format!("    doubled_pawn: {},\n", pair(self.doubled_pawn));
+        source += &format!("    isolated_pawn: {},\n", pair(self.isolated_pawn));
+        source += &format!("    backward_pawn: {},\n", pair(self.backward_pawn));
+        source += &format!("    connected_pawn: {},\n", list(&self.connected_pawn));
+        source += &format!("    passed_pawn_mg: {},\n", list(&self.passed_pawn_mg));
+        source += &format!("    passed_pawn_eg: {},\n", list(&self.passed_pawn_eg));
+        source += &format!(
+            "    passed_pawn_enemy_king_distance: {},\n",
+            self.passed_pawn_enemy_king_distance
+        );
+        source += &format!(
+            "    passed_pawn_own_king_distance: {},\n\n",
+            self.passed_pawn_own_king_distance
+        );
+        source += &format!("    pawn_shield: {},\n", list(&self.pawn_shield));
+        source += &format!("    pawn_storm: {},\n", list(&self.pawn_storm));
+        source += &format!(
+            "    semi_open_file_near_king: {},\n",
+            self.semi_open_file_near_king
+        );
+        source += &format!("    open_file_near_king: {},\n", self.open_file_near_king);
+        source += &format!(
+            "    king_attack_weights: {},\n\n",
+            list(&self.king_attack_weights)
+        );
+        source += &format!("    mobility: [{}],\n", mobility.join(", "));
+        source += &format!("    pawn_mobility: {},\n", pair(self.pawn_mobility));
+        source += "};\n";
+        source
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::parameters::DEFAULT_PARAMETERS;
+
+    #[test]
+    fn test_vector_roundtrip() {
+        let vector = DEFAULT_PARAMETERS.to_vector();
+        // Knight, bishop, rook and queen values come right after the pawn value
+        assert_eq!(&[100, 320, 330, 500, 900], &vector[..5]);
+
+        let mut parameters = DEFAULT_PARAMETERS;
+        parameters.set_vector(&vector);
+        assert_eq!(DEFAULT_PARAMETERS, parameters);
+
+        let mut tuned = vector.clone();
+        tuned[1] += 10;
+        parameters.set_vector(&tuned);
+        assert_eq!(330, parameters.mg_values[1]);
+        assert_eq!(tuned, parameters.to_vector());
+    }
+
+    #[test]
+    fn test_rust_source() {
+        let source = DEFAULT_PARAMETERS.to_rust_source();
+        assert!(source.contains("    mg_values: [100, 320, 330, 500, 900, 20000],\n"));
+        assert!(source.contains("    mobility: [(4, 4), (5, 5), (2, 4), (1, 2)],\n"));
+        // The first rank of the middlegame table of the king
+        assert!(source.contains("              20,  30,  10,   0,   0,  10,  30,  20,\n"));
+    }
+}
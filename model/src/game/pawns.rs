@@ -0,0 +1,270 @@
+use crate::chess_type::ScoreType;
+use crate::game::parameters::{EvaluationParameters, DEFAULT_PARAMETERS};
+use crate::game::precomputation::{
+    ADJACENT_FILES_MASKS, FILE_MASKS, PASSED_PAWN_MASKS, PAWN_ATTACK_MASKS, PAWN_SUPPORT_MASKS,
+    ZOBRIST_KEYS,
+};
+use crate::game::ChessGame;
+use crate::utils::consume_bits;
+
+/// Evaluation of the pawn structure, which only depends on the position of the pawns and can
+/// therefore be cached with the pawn key of a game.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct PawnStructure {
+    /// Score in the middlegame, from white's point of view
+    pub(crate) mg: ScoreType,
+    /// Score in the endgame, from white's point of view
+    pub(crate) eg: ScoreType,
+    /// Passed pawns of white
+    pub(crate) white_passed: u64,
+    /// Passed pawns of black
+    pub(crate) black_passed: u64,
+}
+
+/// Returns the Chebyshev distance between two squares, which is the number of moves needed by a
+/// king to go from one square to the other.
+fn distance(a: usize, b: usize) -> ScoreType {
+    let rank_distance = (a / 8).abs_diff(b / 8);
+    let file_distance = (a % 8).abs_diff(b % 8);
+    rank_distance.max(file_distance) as ScoreType
+}
+
+impl ChessGame {
+    /// Zobrist key computed only with the pawns of the game.
+    ///
+    /// Two games with the same pawns have the same pawn key, whatever the other pieces are.
+    pub fn pawn_key(&self) -> u64 {
+        let mut key = 0;
+        consume_bits!(self.pawns & self.whites, sq, {
+            key ^= ZOBRIST_KEYS[0][sq];
+        });
+        consume_bits!(self.pawns & !self.whites, sq, {
+            key ^= ZOBRIST_KEYS[1][sq];
+        });
+        key
+    }
+
+    /// Evaluates the pawn structure: passed, isolated, doubled, backward and connected pawns.
+    pub(crate) fn pawn_structure(&self) -> PawnStructure {
+        self.pawn_structure_with(&DEFAULT_PARAMETERS)
+    }
+
+    /// Evaluates the pawn structure with the given weights
+    pub(crate) fn pawn_structure_with(&self, parameters: &EvaluationParameters) -> PawnStructure {
+        let white_pawns = self.pawns & self.whites;
+        let black_pawns = self.pawns & !self.whites;
+
+        let (white_mg, white_eg, white_passed) =
+            Self::pawn_structure_for_color(white_pawns, black_pawns, true, parameters);
+        let (black_mg, black_eg, black_passed) =
+            Self::pawn_structure_for_color(black_pawns, white_pawns, false, parameters);
+
+        PawnStructure {
+            mg: white_mg - black_mg,
+            eg: white_eg - black_eg,
+            white_passed,
+            black_passed,
+        }
+    }
+
+    /// Evaluates the pawns of one player, and returns the middlegame score, the endgame score and
+    /// the bitboard of the passed pawns.
+    fn pawn_structure_for_color(
+        own: u64,
+        enemy: u64,
+        white: bool,
+        parameters: &EvaluationParameters,
+    ) -> (ScoreType, ScoreType, u64) {
+        let (white_passed_masks, black_passed_masks) = &*PASSED_PAWN_MASKS;
+        let (white_support_masks, black_support_masks) = &*PAWN_SUPPORT_MASKS;
+        let (white_attacks, black_attacks) = &*PAWN_ATTACK_MASKS;
+
+        let (passed_masks, support_masks, own_attacks, enemy_attacks) = if white {
+            (
+                white_passed_masks,
+                white_support_masks,
+                white_attacks,
+                black_attacks,
+            )
+        } else {
+            (
+                black_passed_masks,
+                black_support_masks,
+                black_attacks,
+                white_attacks,
+            )
+        };
+
+        let mut mg = 0;
+        let mut eg = 0;
+        let mut passed = 0;
+
+        // Doubled pawns: each extra pawn on a file is penalized
+        for file in FILE_MASKS.iter() {
+            let count = (own & file).count_ones() as ScoreType;
+            if count > 1 {
+                mg += parameters.doubled_pawn.0 * (count - 1);
+                eg += parameters.doubled_pawn.1 * (count - 1);
+            }
+        }
+
+        consume_bits!(own, sq, {
+            let file = sq % 8;
+            let relative_rank = if white { sq / 8 } else { 7 - sq / 8 };
+
+            // Passed pawns: no enemy pawn can stop it
+            if passed_masks[sq] & enemy == 0 {
+                passed |= 1 << sq;
+                mg += parameters.passed_pawn_mg[relative_rank];
+                eg += parameters.passed_pawn_eg[relative_rank];
+            }
+
+            if own & ADJACENT_FILES_MASKS[file] == 0 {
+                // Isolated pawns: no friendly pawn on the adjacent files
+                mg += parameters.isolated_pawn.0;
+                eg += parameters.isolated_pawn.1;
+            } else if own & support_masks[sq] == 0 && relative_rank < 7 {
+                // Backward pawns: all the friendly pawns of the adjacent files are in front of it,
+                // and it can't advance because the square in front of it is attacked by a pawn.
+                let stop = if white { sq + 8 } else { sq - 8 };
+                if own_attacks[stop] & enemy != 0 {
+                    mg += parameters.backward_pawn.0;
+                    eg += parameters.backward_pawn.1;
+                }
+            }
+
+            // Connected pawns: defended by a pawn (the squares from which a friendly pawn attacks
+            // this pawn are given by the attack mask of the enemy), or next to a friendly pawn.
+            let defended = enemy_attacks[sq] & own != 0;
+            let phalanx = ADJACENT_FILES_MASKS[file] & (0xFFu64 << (8 * (sq / 8))) & own != 0;
+            if defended || phalanx {
+                mg += parameters.connected_pawn[relative_rank];
+                eg += parameters.connected_pawn[relative_rank];
+            }
+        });
+
+        (mg, eg, passed)
+    }
+
+    /// Endgame bonus of the passed pawns depending on the distance of the kings to the square in
+    /// front of each pawn. It can't be cached with the pawn structure since it depends on the kings.
+    pub(crate) fn passed_pawns_king_distance(
+        &self,
+        pawns: &PawnStructure,
+        parameters: &EvaluationParameters,
+    ) -> ScoreType {
+        let white_king = (self.kings & self.whites).trailing_zeros() as usize;
+        let black_king = (self.kings & !self.whites).trailing_zeros() as usize;
+        if white_king >= 64 || black_king >= 64 {
+            return 0;
+        }
+
+        let mut score = 0;
+        consume_bits!(pawns.white_passed, sq, {
+            let rank = sq / 8;
+            if rank > 2 {
+                let stop = sq + 8;
+                score += (rank as ScoreType - 2)
+                    * (parameters.passed_pawn_enemy_king_distance * distance(black_king, stop)
+                        - parameters.passed_pawn_own_king_distance * distance(white_king, stop));
+            }
+        });
+        consume_bits!(pawns.black_passed, sq, {
+            let rank = 7 - sq / 8;
+            if rank > 2 {
+                let stop = sq - 8;
+                score -= (rank as ScoreType - 2)
+                    * (parameters.passed_pawn_enemy_king_distance * distance(white_king, stop)
+                        - parameters.passed_pawn_own_king_distance * distance(black_king, stop));
+            }
+        });
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_type::Type::{King, Knight, Pawn};
+    use crate::game::parameters::DEFAULT_PARAMETERS;
+    use crate::game::ChessGame;
+
+    #[test]
+    fn test_pawn_key() {
+        let game = ChessGame::standard_game();
+
+        // The other pieces have no impact on the key
+        let mut pawns_only = ChessGame::from_fen("4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1");
+        assert_eq!(game.pawn_key(), pawns_only.pawn_key());
+        pawns_only.set_piece(Knight, true, "e4");
+        assert_eq!(game.pawn_key(), pawns_only.pawn_key());
+
+        // But moving a pawn, or changing its color, changes the key
+        let moved = ChessGame::from_fen("4k3/pppppppp/8/8/4P3/8/PPPP1PPP/4K3 w - - 0 1");
+        assert_ne!(game.pawn_key(), moved.pawn_key());
+        let mut white = ChessGame::empty();
+        white.set_piece(Pawn, true, "e4");
+        let mut black = ChessGame::empty();
+        black.set_piece(Pawn, false, "e4");
+        assert_ne!(white.pawn_key(), black.pawn_key());
+    }
+
+    #[test]
+    fn test_symmetric_pawn_structure() {
+        let structure = ChessGame::standard_game().pawn_structure();
+        assert_eq!(0, structure.mg);
+        assert_eq!(0, structure.eg);
+        assert_eq!(0, structure.white_passed);
+        assert_eq!(0, structure.black_passed);
+    }
+
+    #[test]
+    fn test_passed_pawns() {
+        // The pawn in a5 is passed, the pawns in e4 and d5 block each other's neighbours
+        let game = ChessGame::from_fen("4k3/8/8/P2p4/4P3/8/8/4K3 w - - 0 1");
+        let structure = game.pawn_structure();
+        assert_eq!(1 << 32, structure.white_passed);
+        assert_eq!(0, structure.black_passed);
+        assert!(structure.eg > 0);
+
+        // A pawn on the adjacent file also stops the pawn
+        let game = ChessGame::from_fen("4k3/1p6/8/P7/8/8/8/4K3 w - - 0 1");
+        assert_eq!(0, game.pawn_structure().white_passed);
+    }
+
+    #[test]
+    fn test_doubled_and_isolated_pawns() {
+        // Same number of pawns: healthy chain vs doubled and isolated pawns
+        let healthy = ChessGame::from_fen("4k3/ppp5/8/8/8/8/PPP5/4K3 w - - 0 1");
+        let doubled = ChessGame::from_fen("4k3/ppp5/8/8/8/P7/P1P5/4K3 w - - 0 1");
+        let healthy = healthy.pawn_structure();
+        let doubled = doubled.pawn_structure();
+        assert!(healthy.mg > doubled.mg);
+        assert!(healthy.eg > doubled.eg);
+    }
+
+    #[test]
+    fn test_backward_pawn() {
+        // The pawn in d3 can't advance since c5 and e5 control d4, and its neighbour in c4 is ahead
+        let backward = ChessGame::from_fen("4k3/8/8/2p1p3/2P5/3P4/8/4K3 w - - 0 1");
+        // Same pawns, but d3 is defended by c2
+        let supported = ChessGame::from_fen("4k3/8/8/2p1p3/8/3P4/2P5/4K3 w - - 0 1");
+        assert!(backward.pawn_structure().mg < supported.pawn_structure().mg);
+    }
+
+    #[test]
+    fn test_king_distance_to_passed_pawn() {
+        let mut game = ChessGame::empty();
+        game.set_piece(Pawn, true, "e6");
+        game.set_piece(King, true, "e5");
+        let mut near = game;
+        near.set_piece(King, false, "e8");
+        let mut far = game;
+        far.set_piece(King, false, "a1");
+
+        let structure = game.pawn_structure();
+        assert!(
+            far.passed_pawns_king_distance(&structure, &DEFAULT_PARAMETERS)
+                > near.passed_pawns_king_distance(&structure, &DEFAULT_PARAMETERS)
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use crate::chess_type::Type;
+use crate::chess_type::Type::{Bishop, Knight, Pawn, Rook};
+use crate::game::attacks::ChessAttacks;
+use crate::game::ChessGame;
+use crate::moves::Move;
+use crate::moves_container::{MovesContainer, SimpleMovesContainer};
+use crate::utils::pieces_for_color;
+
+impl ChessGame {
+    /// Returns true if the king of the given player is attacked by the opponent
+    pub fn is_in_check(&self, white: bool) -> bool {
+        let king = pieces_for_color!(self.whites, self.kings, white);
+        self.get_attacked_squares(!white) & king != 0
+    }
+
+    /// Returns the moves of the given player which don't leave its king in check
+    pub fn legal_moves(&self, white: bool) -> Vec<Move> {
+        let mut container = SimpleMovesContainer::new();
+        self.update_move_container(&mut container, white);
+        container
+            .moves
+            .into_iter()
+            .filter(|m| {
+                let mut new_game = *self;
+                new_game.apply_move_unsafe(m);
+                !new_game.is_in_check(white)
+            })
+            .collect()
+    }
+
+    /// Returns true if the move brings a pawn to the last rank
+    pub fn is_promotion(&self, m: &Move) -> bool {
+        self.type_at_index(m.from) == Some(Pawn) && (m.to / 8 == 7 || m.to / 8 == 0)
+    }
+
+    /// Returns the move, followed by its underpromotions to a knight, a bishop and a rook when it
+    /// promotes a pawn. The move generator only promotes to queens, which is enough for the
+    /// search, so the notations and perft add the other pieces with this method.
+    pub fn with_underpromotions(&self, m: Move) -> impl Iterator<Item = Move> {
+        let pieces: &[Option<Type>] = if self.is_promotion(&m) {
+            &[None, Some(Knight), Some(Bishop), Some(Rook)]
+        } else {
+            &[None]
+        };
+        pieces
+            .iter()
+            .map(move |&underpromotion| Move { underpromotion, ..m })
+    }
+
+    /// Counts the number of leaves of the tree of legal moves of the given depth, from the side to
+    /// move.
+    ///
+    /// The move generator produces pseudo-legal moves: the moves which leave the king in check are
+    /// filtered out here, and the underpromotions are added.
+    pub fn perft(&self, depth: usize) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let white_to_play = self.white_to_play();
+        let mut container = SimpleMovesContainer::new();
+        self.update_move_container(&mut container, white_to_play);
+
+        // The moves are taken back instead of copying the game for each of them
+        let mut game = *self;
+        let mut nodes = 0;
+        while container.has_next() {
+            for m in self.with_underpromotions(container.pop_next_move()) {
+                let undo = game.make_move(&m);
+                if !game.is_in_check(white_to_play) {
+                    nodes += if depth == 1 {
+                        1
+                    } else {
+                        game.perft(depth - 1)
+                    };
+                }
+                game.unmake_move(undo);
+            }
+        }
+        nodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::ChessGame;
+
+    #[test]
+    fn test_is_in_check() {
+        let game = ChessGame::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1");
+        assert!(!game.is_in_check(true));
+        assert!(!game.is_in_check(false));
+
+        let game = ChessGame::from_fen("R3k3/8/8/8/8/8/8/4K3 b - - 0 1");
+        assert!(game.is_in_check(false));
+        assert!(!game.is_in_check(true));
+    }
+
+    #[test]
+    fn test_legal_moves() {
+        assert_eq!(20, ChessGame::standard_game().legal_moves(true).len());
+
+        // The king in check can only escape: the other moves of the rook are not legal
+        let game = ChessGame::from_fen("4k3/8/8/8/8/8/8/R3K2r w - - 0 1");
+        let moves = game.legal_moves(true);
+        assert!(moves.iter().all(|m| m.from == 4));
+        assert_eq!(3, moves.len());
+    }
+
+    #[test]
+    fn test_perft_standard_game() {
+        let game = ChessGame::standard_game();
+        assert_eq!(20, game.perft(1));
+        assert_eq!(400, game.perft(2));
+        assert_eq!(8_902, game.perft(3));
+        assert_eq!(197_281, game.perft(4));
+    }
+
+    #[test]
+    fn test_perft_kiwipete() {
+        // Castling, en passant, pins and checks
+        let game = ChessGame::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        );
+        assert_eq!(48, game.perft(1));
+        assert_eq!(2_039, game.perft(2));
+        assert_eq!(97_862, game.perft(3));
+    }
+
+    #[test]
+    fn test_perft_endgame() {
+        // Rook endgame with discovered checks and en passant
+        let game = ChessGame::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1");
+        assert_eq!(14, game.perft(1));
+        assert_eq!(191, game.perft(2));
+        assert_eq!(2_812, game.perft(3));
+        assert_eq!(43_238, game.perft(4));
+    }
+
+    #[test]
+    fn test_perft_middlegame() {
+        let game = ChessGame::from_fen(
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        );
+        assert_eq!(46, game.perft(1));
+        assert_eq!(2_079, game.perft(2));
+        assert_eq!(89_890, game.perft(3));
+    }
+
+    #[test]
+    fn test_perft_promotions() {
+        // Promotions with and without capture, to every piece
+        let game = ChessGame::from_fen("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8");
+        assert_eq!(44, game.perft(1));
+        assert_eq!(1_486, game.perft(2));
+        assert_eq!(62_379, game.perft(3));
+    }
+}
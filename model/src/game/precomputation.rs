@@ -1,4 +1,3 @@
-use crate::utils::{from_rank_file, ChessPosition};
 use once_cell::sync::Lazy;
 
 /// Computes the attack masks for pawns
@@ -109,92 +108,90 @@ fn king_attacks() -> [u64; 64] {
     attacks
 }
 
-/// For each square and direction, computes the "ray" of squares that would be attacked.
+/// Computes the mask of each file of the board
+fn file_masks() -> [u64; 8] {
+    std::array::from_fn(|file| 0x0101010101010101u64 << file)
+}
+
+/// Computes, for each file, the mask of the files next to it.
 ///
-/// Each position of the board is associated with a vector of positions, as the ray expand in the
-/// given direction.
+/// A pawn without any friendly pawn on these files is isolated.
+fn adjacent_files_masks() -> [u64; 8] {
+    let files = file_masks();
+    std::array::from_fn(|file| {
+        let mut mask = 0;
+        if file > 0 {
+            mask |= files[file - 1];
+        }
+        if file < 7 {
+            mask |= files[file + 1];
+        }
+        mask
+    })
+}
+
+/// Computes the passed pawn masks, for white and black pawns.
 ///
-/// Directions are defined as: N, S, E, W, as per the ordering defined in the class.
-fn sliding_attacks() -> [[Vec<ChessPosition>; 64]; 8] {
-    // Use std::array::from_fn to initialize the 2D array (1st dimension is the direction)
-    std::array::from_fn(|direction_index| {
-        // For each square on the board, compute the sliding ray for the given direction
-        std::array::from_fn(|sq| {
-            let mut ray = Vec::new();
-            let rank = sq / 8;
-            let file = sq % 8;
-
-            match direction_index {
-                0 => {
-                    // North (upwards direction)
-                    for r in rank + 1..8 {
-                        ray.push(from_rank_file(r, file));
-                    }
-                }
-                1 => {
-                    // South (downwards direction)
-                    for r in (0..rank).rev() {
-                        ray.push(from_rank_file(r, file));
-                    }
-                }
-                2 => {
-                    // East (right direction)
-                    for f in file + 1..8 {
-                        ray.push(from_rank_file(rank, f));
-                    }
-                }
-                3 => {
-                    // West (left direction)
-                    for f in (0..file).rev() {
-                        ray.push(from_rank_file(rank, f));
-                    }
-                }
-                4 => {
-                    // Northeast (up-right)
-                    let mut r = rank as isize + 1;
-                    let mut f = file as isize + 1;
-                    while r < 8 && f < 8 {
-                        ray.push(from_rank_file(r as usize, f as usize));
-                        r += 1;
-                        f += 1;
-                    }
-                }
-                5 => {
-                    // Northwest (up-left)
-                    let mut r = rank as isize + 1;
-                    let mut f = file as isize - 1;
-                    while r < 8 && f >= 0 {
-                        ray.push(from_rank_file(r as usize, f as usize));
-                        r += 1;
-                        f -= 1;
-                    }
-                }
-                6 => {
-                    // Southeast (down-right)
-                    let mut r = rank as isize - 1;
-                    let mut f = file as isize + 1;
-                    while r >= 0 && f < 8 {
-                        ray.push(from_rank_file(r as usize, f as usize));
-                        r -= 1;
-                        f += 1;
-                    }
-                }
-                7 => {
-                    // Southwest (down-left)
-                    let mut r = rank as isize - 1;
-                    let mut f = file as isize - 1;
-                    while r >= 0 && f >= 0 {
-                        ray.push(from_rank_file(r as usize, f as usize));
-                        r -= 1;
-                        f -= 1;
-                    }
-                }
-                _ => unreachable!(),
-            }
+/// For each square, the mask contains all the squares in front of the pawn, on its file and on the
+/// adjacent files. A pawn is passed when there is no enemy pawn in this mask.
+fn passed_pawn_masks() -> ([u64; 64], [u64; 64]) {
+    let files = file_masks();
+    let adjacent = adjacent_files_masks();
+    let mut white_masks = [0u64; 64];
+    let mut black_masks = [0u64; 64];
 
-            ray
-        })
-    })
+    for sq in 0..64 {
+        let rank = sq / 8;
+        let file = sq % 8;
+        let span = files[file] | adjacent[file];
+
+        // Ranks strictly above (for white) or below (for black) the pawn
+        let above = if rank < 7 { u64::MAX << (8 * (rank + 1)) } else { 0 };
+        let below = if rank > 0 { u64::MAX >> (8 * (8 - rank)) } else { 0 };
+
+        white_masks[sq] = span & above;
+        black_masks[sq] = span & below;
+    }
+
+    (white_masks, black_masks)
+}
+
+/// Computes the support masks, for white and black pawns.
+///
+/// For each square, the mask contains the squares of the adjacent files which are on the same rank
+/// or behind the pawn. A pawn without any friendly pawn in this mask can't be defended by other
+/// pawns anymore.
+fn pawn_support_masks() -> ([u64; 64], [u64; 64]) {
+    let (white_passed, black_passed) = passed_pawn_masks();
+    let adjacent = adjacent_files_masks();
+    let mut white_masks = [0u64; 64];
+    let mut black_masks = [0u64; 64];
+
+    for sq in 0..64 {
+        let file = sq % 8;
+        // Everything which is not in front of the pawn is either on its rank or behind it
+        white_masks[sq] = adjacent[file] & !white_passed[sq];
+        black_masks[sq] = adjacent[file] & !black_passed[sq];
+    }
+
+    (white_masks, black_masks)
+}
+
+/// Generates the random numbers used for Zobrist hashing, with a fixed seed so that the keys are
+/// identical between runs.
+///
+/// There is one key per piece type (pawn, knight, bishop, rook, queen, king), color and square:
+/// the index of the first dimension is `2 * piece + (is_black as usize)`.
+fn zobrist_keys() -> [[u64; 64]; 12] {
+    // xorshift64* pseudo-random generator
+    let mut state = 0x9E3779B97F4A7C15u64;
+    let mut next = || {
+        state ^= state >> 12;
+        state ^= state << 25;
+        state ^= state >> 27;
+        state.wrapping_mul(0x2545F4914F6CDD1D)
+    };
+    std::array::from_fn(|_| std::array::from_fn(|_| next()))
 }
 
 // TODO understand clearly if the way that I use these static variables it the right one
@@ -206,5 +203,13 @@ pub static KNIGHT_ATTACK_MASKS: Lazy<[u64; 64]> = Lazy::new(knight_attacks);
 
 pub static KING_ATTACK_MASKS: Lazy<[u64; 64]> = Lazy::new(king_attacks);
 
-pub static SLIDING_ATTACK_MASKS: Lazy<[[Vec<ChessPosition>; 64]; 8]> = Lazy::new(sliding_attacks);
 
+pub static FILE_MASKS: Lazy<[u64; 8]> = Lazy::new(file_masks);
+
+pub static ADJACENT_FILES_MASKS: Lazy<[u64; 8]> = Lazy::new(adjacent_files_masks);
+
+pub static PASSED_PAWN_MASKS: Lazy<([u64; 64], [u64; 64])> = Lazy::new(passed_pawn_masks);
+
+pub static PAWN_SUPPORT_MASKS: Lazy<([u64; 64], [u64; 64])> = Lazy::new(pawn_support_masks);
+
+pub static ZOBRIST_KEYS: Lazy<[[u64; 64]; 12]> = Lazy::new(zobrist_keys);
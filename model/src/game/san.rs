@@ -0,0 +1,350 @@
+use crate::chess_type::Type;
+use crate::chess_type::Type::{Bishop, King, Knight, Pawn, Queen, Rook};
+use crate::game::ChessGame;
+use crate::moves::Move;
+use crate::utils::{chesspos_to_index, index_to_chesspos, is_set};
+
+/// Returns the piece of a letter of the standard algebraic notation
+fn letter_piece(letter: char) -> Option<Type> {
+    match letter {
+        'N' => Some(Knight),
+        'B' => Some(Bishop),
+        'R' => Some(Rook),
+        'Q' => Some(Queen),
+        'K' => Some(King),
+        _ => None,
+    }
+}
+
+/// Returns the letter of a piece in the standard algebraic notation
+fn piece_letter(piece: Type) -> char {
+    match piece {
+        Pawn => 'P',
+        Knight => 'N',
+        Bishop => 'B',
+        Rook => 'R',
+        Queen => 'Q',
+        King => 'K',
+    }
+}
+
+impl ChessGame {
+    /// Writes a legal move of this game in standard algebraic notation, such as `Nbd7`, `exd6`,
+    /// `O-O-O`, `e8=Q+` or `Qxf7#`.
+    ///
+    /// Pieces are disambiguated by their file, by their rank, or by both when several pieces of
+    /// the same type can reach the destination square.
+    pub fn to_san(&self, m: &Move) -> String {
+        let piece = self
+            .type_at_index(m.from)
+            .expect("There is no piece to move");
+        let white = is_set!(self.whites, m.from);
+        let from = index_to_chesspos(m.from);
+
+        let en_passant = self.is_en_passant(m);
+
+        let mut san = if piece == King && (m.to - m.from).abs() == 2 {
+            if m.to > m.from {
+                "O-O".to_string()
+            } else {
+                "O-O-O".to_string()
+            }
+        } else {
+            let is_capture = self.has_piece_at(m.to) || en_passant;
+            let mut san = String::new();
+
+            if piece == Pawn {
+                if is_capture {
+                    san.push_str(&from[..1]);
+                }
+            } else {
+                san.push(piece_letter(piece));
+                let ambiguous: Vec<Move> = self
+                    .legal_moves(white)
+                    .into_iter()
+                    .filter(|other| {
+                        other.to == m.to
+                            && other.from != m.from
+                            && self.type_at_index(other.from) == Some(piece)
+                    })
+                    .collect();
+                if !ambiguous.is_empty() {
+                    let same_file = ambiguous.iter().any(|o| o.from % 8 == m.from % 8);
+                    let same_rank = ambiguous.iter().any(|o| o.from / 8 == m.from / 8);
+                    if !same_file {
+                        san.push_str(&from[..1]);
+                    } else if !same_rank {
+                        san.push_str(&from[1..]);
+                    } else {
+                        san.push_str(&from);
+                    }
+                }
+            }
+
+            if is_capture {
+                san.push('x');
+            }
+            san.push_str(&index_to_chesspos(m.to));
+
+            if self.is_promotion(m) {
+                san.push('=');
+                san.push(piece_letter(m.underpromotion.unwrap_or(Queen)));
+            }
+            san
+        };
+
+        let mut new_game = *self;
+        new_game.apply_move_unsafe(m);
+        if new_game.is_in_check(!white) {
+            san.push(if new_game.legal_moves(!white).is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+        san
+    }
+
+    /// Writes a move as [`to_san`](Self::to_san), with the en passant captures followed by
+    /// ` e.p.` to be displayed to the players. The suffix isn't allowed in PGN.
+    pub fn to_display_san(&self, m: &Move) -> String {
+        let san = self.to_san(m);
+        if self.is_en_passant(m) {
+            format!("{san} e.p.")
+        } else {
+            san
+        }
+    }
+
+    fn is_en_passant(&self, m: &Move) -> bool {
+        self.type_at_index(m.from) == Some(Pawn)
+            && m.from % 8 != m.to % 8
+            && !self.has_piece_at(m.to)
+    }
+
+    /// Reads a move of the given player written in standard algebraic notation, and returns it if
+    /// it is legal and not ambiguous.
+    ///
+    /// The notation is read leniently: check, annotation and en passant (`e.p.`) suffixes are
+    /// ignored, castling may be written with zeros, and pieces may be disambiguated even when it
+    /// isn't necessary.
+    pub fn from_san(&self, san: &str, white: bool) -> Option<Move> {
+        let san = san.trim();
+        let san = san
+            .strip_suffix("e.p.")
+            .unwrap_or(san)
+            .trim_end()
+            .trim_end_matches(['+', '#', '!', '?']);
+        let legal_moves: Vec<Move> = self
+            .legal_moves(white)
+            .into_iter()
+            .flat_map(|m| self.with_underpromotions(m))
+            .collect();
+        let king = self.kings & if white { self.whites } else { !self.whites };
+
+        let castling_target = match san {
+            "O-O" | "0-0" => Some(2),
+            "O-O-O" | "0-0-0" => Some(-2),
+            _ => None,
+        };
+        if let Some(offset) = castling_target {
+            return legal_moves
+                .into_iter()
+                .find(|m| is_set!(king, m.from) && m.to - m.from == offset);
+        }
+
+        // Promotion, written `e8=Q` or `e8Q`
+        let (san, promotion) = match san.char_indices().last() {
+            Some((i, letter)) if letter_piece(letter).is_some() => {
+                (san[..i].trim_end_matches('='), letter_piece(letter))
+            }
+            _ => (san, None),
+        };
+        // The queen is the promotion of the moves generated without underpromotion
+        let underpromotion = promotion.filter(|&piece| piece != Queen);
+        if underpromotion == Some(King) {
+            return None;
+        }
+
+        let (piece, san) = match san.chars().next().and_then(letter_piece) {
+            Some(piece) => (piece, &san[1..]),
+            None => (Pawn, san),
+        };
+        let san = san.replace('x', "");
+        if san.len() < 2 || !san.is_ascii() {
+            return None;
+        }
+        let (hints, to) = san.split_at(san.len() - 2);
+        if !matches!(to.as_bytes(), [b'a'..=b'h', b'1'..=b'8']) {
+            return None;
+        }
+        let to = chesspos_to_index(to)?;
+
+        let mut candidates = legal_moves.into_iter().filter(|m| {
+            let from = index_to_chesspos(m.from);
+            m.to == to
+                && m.underpromotion == underpromotion
+                && self.type_at_index(m.from) == Some(piece)
+                && hints.chars().all(|hint| from.contains(hint))
+        });
+        let m = candidates.next()?;
+        match candidates.next() {
+            Some(_) => None,
+            None => Some(m),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_type::Type::{Knight, Rook};
+    use crate::game::ChessGame;
+    use crate::moves::Move;
+
+    fn san(fen: &str, from: &str, to: &str, white: bool) -> String {
+        ChessGame::from_fen(fen).to_san(&Move::from_str(from, to, white))
+    }
+
+    #[test]
+    fn test_pawn_and_piece_moves() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!("e4", san(start, "e2", "e4", true));
+        assert_eq!("Nf3", san(start, "g1", "f3", true));
+
+        let game = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2";
+        assert_eq!("exd5", san(game, "e4", "d5", true));
+        assert_eq!("dxe4", san(game, "d5", "e4", false));
+        assert_eq!("Bb5+", san(game, "f1", "b5", true));
+    }
+
+    #[test]
+    fn test_disambiguation() {
+        // Both knights can go to d2: the file is enough
+        let game = "4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1";
+        assert_eq!("Nbd2", san(game, "b1", "d2", true));
+        assert_eq!("Nfd2", san(game, "f1", "d2", true));
+
+        // Both rooks are on the same file: the rank is needed
+        let game = "4k3/R7/8/8/8/8/8/R3K3 w - - 0 1";
+        assert_eq!("R1a4", san(game, "a1", "a4", true));
+
+        // Three queens: both the file and the rank are needed
+        let game = "4k3/8/8/8/Q2Q4/8/8/Q3K3 w - - 0 1";
+        assert_eq!("Qa4d1", san(game, "a4", "d1", true));
+    }
+
+    #[test]
+    fn test_castling_promotion_and_checks() {
+        let game = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!("O-O", san(game, "e1", "g1", true));
+        assert_eq!("O-O-O", san(game, "e8", "c8", false));
+
+        assert_eq!(
+            "b8=Q+",
+            san("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7", "b8", true)
+        );
+        let game = ChessGame::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1");
+        let m = Move {
+            underpromotion: Some(Rook),
+            ..Move::from_str("b7", "b8", true)
+        };
+        assert_eq!("b8=R+", game.to_san(&m));
+        let m = Move {
+            underpromotion: Some(Knight),
+            ..Move::from_str("b7", "b8", true)
+        };
+        assert_eq!("b8=N", game.to_san(&m));
+        assert_eq!(
+            "Ra8#",
+            san("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1", "a8", true)
+        );
+    }
+
+    #[test]
+    fn test_en_passant() {
+        let game = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1";
+        assert_eq!("exd6", san(game, "e5", "d6", true));
+        assert_eq!("exd5", san("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", "e4", "d5", true));
+        let game = "7k/8/8/8/4pP2/8/8/6K1 b - f3 0 1";
+        assert_eq!("exf3", san(game, "e4", "f3", false));
+        let game = "7k/8/8/8/4pP2/8/6K1/8 b - f3 0 1";
+        assert_eq!("exf3+", san(game, "e4", "f3", false));
+
+        // The suffix is only displayed, after the check suffix
+        let game = ChessGame::from_fen(game);
+        let m = Move::from_str("e4", "f3", false);
+        assert_eq!("exf3+ e.p.", game.to_display_san(&m));
+        let m = Move::from_str("h8", "g8", false);
+        assert_eq!(game.to_san(&m), game.to_display_san(&m));
+    }
+
+    #[test]
+    fn test_from_san() {
+        let game = ChessGame::from_fen("4k3/8/8/8/Q2Q4/8/8/Q3K3 w - - 0 1");
+        assert_eq!(
+            Some(Move::from_str("a4", "d1", true)),
+            game.from_san("Qa4d1", true)
+        );
+        assert_eq!(
+            Some(Move::from_str("d4", "d8", true)),
+            game.from_san("Qd8+", true)
+        );
+        // Ambiguous, or illegal
+        assert_eq!(None, game.from_san("Qd1", true));
+        assert_eq!(None, game.from_san("Qb7", true));
+        assert_eq!(None, game.from_san("Nf3", true));
+
+        let game = ChessGame::from_fen("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w - - 0 2");
+        assert_eq!(
+            Some(Move::from_str("e4", "d5", true)),
+            game.from_san("exd5", true)
+        );
+        assert_eq!(
+            Some(Move::from_str("g8", "f6", false)),
+            game.from_san("Ngf6", false)
+        );
+
+        let game = ChessGame::from_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        assert_eq!(
+            Some(Move::from_str("e1", "c1", true)),
+            game.from_san("0-0-0", true)
+        );
+        assert_eq!(
+            Some(Move::from_str("e8", "g8", false)),
+            game.from_san("O-O", false)
+        );
+        assert_eq!(
+            Some(Move::from_str("b7", "a8", true)),
+            game.from_san("bxa8=Q", true)
+        );
+        assert_eq!(
+            Some(Move {
+                underpromotion: Some(Knight),
+                ..Move::from_str("b7", "a8", true)
+            }),
+            game.from_san("bxa8=N", true)
+        );
+        assert_eq!(None, game.from_san("bxa8=K", true));
+        assert_eq!(None, game.from_san("Ra2=N", true));
+        // Every promotion is read back from its notation
+        for m in game.legal_moves(true) {
+            for m in game.with_underpromotions(m) {
+                assert_eq!(Some(m), game.from_san(&game.to_san(&m), true));
+            }
+        }
+
+        let mut game = ChessGame::from_fen("4k3/3p4/8/4P3/8/8/8/4K3 b - - 0 1");
+        game.apply_move_unsafe(&Move::from_str("d7", "d5", false));
+        for san in ["exd6 e.p.", "exd6e.p.", "exd6", "exd6+ e.p."] {
+            assert_eq!(
+                Some(Move::from_str("e5", "d6", true)),
+                game.from_san(san, true)
+            );
+        }
+
+        // Every move is read back from its notation
+        for m in game.legal_moves(true) {
+            assert_eq!(Some(m), game.from_san(&game.to_san(&m), true));
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use crate::chess_type::Type;
+use crate::chess_type::Type::{King, Pawn};
+use crate::game::ChessGame;
+use crate::moves::Move;
+use crate::utils::{clear_at, is_set, set_at, ChessPosition};
+
+/// What a move changes in a game and can't be found again from the game after the move: the
+/// captured piece, whether the pawn was promoted, the castling flags and the side to move,
+/// and the en passant square. The model has no move clocks to restore.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct UndoInfo {
+    /// The move which was played
+    pub played: Move,
+    /// Captured piece, with its color and its square, which is not the destination of the move
+    /// for the en passant captures
+    captured: Option<(Type, bool, ChessPosition)>,
+    promotion: bool,
+    flags: u64,
+    en_passant_target: u64,
+}
+
+impl ChessGame {
+    /// Plays a move without any kind of safety check, like `apply_move_unsafe`, and returns what
+    /// is needed to take it back with `unmake_move`.
+    pub fn make_move(&mut self, m: &Move) -> UndoInfo {
+        let piece = self.type_at_index(m.from);
+        let en_passant = piece == Some(Pawn)
+            && m.from % 8 != m.to % 8
+            && !self.has_piece_at(m.to)
+            && is_set!(self.en_passant_target, m.to);
+        let captured_square = match (en_passant, is_set!(self.whites, m.from)) {
+            (false, _) => m.to,
+            (true, true) => m.to - 8,
+            (true, false) => m.to + 8,
+        };
+        let undo = UndoInfo {
+            played: *m,
+            captured: self.type_at_index(captured_square).map(|captured| {
+                (
+                    captured,
+                    is_set!(self.whites, captured_square),
+                    captured_square,
+                )
+            }),
+            promotion: piece == Some(Pawn) && (m.to / 8 == 7 || m.to / 8 == 0),
+            flags: self.flags,
+            en_passant_target: self.en_passant_target,
+        };
+        self.apply_move_unsafe(m);
+        undo
+    }
+
+    /// Takes back the last move played with `make_move`, which restores the game as it was
+    /// before the move.
+    pub fn unmake_move(&mut self, undo: UndoInfo) {
+        let m = undo.played;
+        let Some(piece) = self.type_at_index(m.to) else {
+            // `apply_move_unsafe` doesn't play the moves without piece
+            return;
+        };
+        let white = is_set!(self.whites, m.to);
+        self.apply_capture(&m);
+        let piece = if undo.promotion { Pawn } else { piece };
+        self.set_piece(piece, white, m.from);
+
+        // The rook comes back to its corner
+        let motion = m.to - m.from;
+        if piece == King && (motion == 2 || motion == -2) {
+            let (rook_from, rook_to) = if motion == 2 {
+                (m.from + 3, m.from + 1)
+            } else {
+                (m.from - 4, m.from - 1)
+            };
+            clear_at!(self.rooks, rook_to);
+            clear_at!(self.whites, rook_to);
+            set_at!(self.rooks, rook_from);
+            if white {
+                set_at!(self.whites, rook_from);
+            }
+        }
+
+        if let Some((captured, captured_white, square)) = undo.captured {
+            self.set_piece(captured, captured_white, square);
+        }
+        self.flags = undo.flags;
+        self.en_passant_target = undo.en_passant_target;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::ChessGame;
+    use crate::moves::Move;
+    use crate::moves_container::SimpleMovesContainer;
+
+    /// Checks that making and unmaking every pseudo-legal move of the game gives the same games as
+    /// copying the game to play the move
+    fn assert_make_unmake(game: &ChessGame, white: bool) {
+        let mut container = SimpleMovesContainer::new();
+        game.update_move_container(&mut container, white);
+        for m in &container.moves {
+            let mut copy = *game;
+            copy.apply_move_unsafe(m);
+            let mut made = *game;
+            let undo = made.make_move(m);
+            assert_eq!(copy, made, "{} {m}", game.to_fen());
+            made.unmake_move(undo);
+            assert_eq!(*game, made, "{} {m}", game.to_fen());
+        }
+    }
+
+    #[test]
+    fn test_special_moves() {
+        // Castling on both sides, captures and promotions with and without capture
+        let game = ChessGame::from_fen("r3k2r/1P4P1/8/8/8/8/1p4p1/R3K2R w KQkq - 0 1");
+        assert_make_unmake(&game, true);
+        assert_make_unmake(&game, false);
+
+        // En passant, for both colors
+        let mut game = ChessGame::from_fen("4k3/2p5/8/3P4/5p2/8/4P3/4K3 w - - 0 1");
+        game.apply_move_unsafe(&Move::from_str("e2", "e4", true));
+        assert_make_unmake(&game, false);
+        let mut made = game;
+        let undo = made.make_move(&Move::from_str("f4", "e3", false));
+        assert!(!made.has_piece_at(28));
+        made.unmake_move(undo);
+        assert_eq!(game, made);
+
+        game.apply_move_unsafe(&Move::from_str("c7", "c5", false));
+        assert_make_unmake(&game, true);
+    }
+
+    #[test]
+    fn test_random_games() {
+        // xorshift64* pseudo-random generator, with a fixed seed
+        let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+        let mut random = |n: usize| {
+            state ^= state >> 12;
+            state ^= state << 25;
+            state ^= state >> 27;
+            (state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 32) as usize % n
+        };
+
+        for _ in 0..20 {
+            let mut game = ChessGame::standard_game();
+            let mut history = vec![];
+            for _ in 0..120 {
+                let white = game.white_to_play();
+                assert_make_unmake(&game, white);
+                let moves = game.legal_moves(white);
+                if moves.is_empty() {
+                    break;
+                }
+                let m = moves[random(moves.len())];
+                history.push((game, game.make_move(&m)));
+            }
+
+            // Taking back all the moves comes back to the start of the game
+            while let Some((before, undo)) = history.pop() {
+                game.unmake_move(undo);
+                assert_eq!(before, game);
+            }
+            assert_eq!(ChessGame::standard_game(), game);
+        }
+    }
+}
@@ -0,0 +1,526 @@
+//! A game with its variations, as needed to annotate it.
+//!
+//! The positions of the game are the nodes of a tree: each node holds the position reached by a
+//! move, and its children are the moves which can be played next. The first child continues the
+//! line of the node, and the other children are its variations, in the order in which they are
+//! written. The nodes are stored in a vector and refer to each other by their index.
+
+use crate::game::ChessGame;
+use crate::moves::Move;
+use crate::pgn::{PgnGame, PgnLine, PgnMove};
+
+/// Index of a node in its tree
+pub type NodeId = usize;
+
+/// Index of the node of the initial position
+pub const ROOT: NodeId = 0;
+
+#[derive(Clone, Debug)]
+pub struct Node {
+    game: ChessGame,
+    /// Move which leads to this node, except for the root
+    played: Option<Move>,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    /// Numeric annotation glyphs of the move, such as 2 for a mistake
+    pub nags: Vec<u8>,
+    /// Comment written after the move, or before the first move of the game for the root
+    pub comment: Option<String>,
+    /// Comment written before the move, when it starts a variation
+    pub comment_before: Option<String>,
+}
+
+impl Node {
+    fn new(game: ChessGame) -> Self {
+        Self {
+            game,
+            played: None,
+            parent: None,
+            children: vec![],
+            nags: vec![],
+            comment: None,
+            comment_before: None,
+        }
+    }
+
+    /// Position reached by the move of the node
+    pub fn game(&self) -> &ChessGame {
+        &self.game
+    }
+
+    pub fn played(&self) -> Option<Move> {
+        self.played
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    /// Moves which can be played from the node: the continuation of its line first, and then
+    /// its variations
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GameTree {
+    nodes: Vec<Node>,
+    current: NodeId,
+    /// Tags of the game, written in PGN
+    pub tags: Vec<(String, String)>,
+    /// Result of the game: `1-0`, `0-1`, `1/2-1/2` or `*`
+    pub result: Option<String>,
+}
+
+impl Default for GameTree {
+    fn default() -> Self {
+        Self::new(ChessGame::standard_game())
+    }
+}
+
+impl GameTree {
+    /// Creates a tree without moves, from the given position
+    pub fn new(game: ChessGame) -> Self {
+        Self {
+            nodes: vec![Node::new(game)],
+            current: ROOT,
+            tags: vec![],
+            result: None,
+        }
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id]
+    }
+
+    pub fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        &mut self.nodes[id]
+    }
+
+    /// Number of positions of the tree, including the initial position
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns true if no move was played
+    pub fn is_empty(&self) -> bool {
+        self.nodes.len() == 1
+    }
+
+    /// Node of the position displayed
+    pub fn current(&self) -> NodeId {
+        self.current
+    }
+
+    /// Position of the current node
+    pub fn game(&self) -> &ChessGame {
+        &self.nodes[self.current].game
+    }
+
+    pub fn white_to_play(&self) -> bool {
+        self.game().white_to_play()
+    }
+
+    /// Writes the move of a node in standard algebraic notation
+    pub fn san(&self, id: NodeId) -> Option<String> {
+        let node = &self.nodes[id];
+        Some(self.nodes[node.parent?].game.to_san(&node.played?))
+    }
+
+    /// Adds a legal move after a node, and returns the node of the move. When the move was
+    /// already played from this node, its node is returned and the tree is left unchanged. The
+    /// first move played from a node continues its line, and the next ones are variations.
+    pub fn add_move(&mut self, parent: NodeId, m: &Move) -> Option<NodeId> {
+        if let Some(&child) = self.nodes[parent]
+            .children
+            .iter()
+            .find(|&&child| self.nodes[child].played.as_ref() == Some(m))
+        {
+            return Some(child);
+        }
+
+        let node = &self.nodes[parent];
+        let m = node
+            .game
+            .legal_moves(node.game.white_to_play())
+            .into_iter()
+            .flat_map(|legal| node.game.with_underpromotions(legal))
+            .find(|legal| legal == m)?;
+        let mut game = node.game;
+        game.apply_move_unsafe(&m);
+        let child = Node {
+            played: Some(m),
+            parent: Some(parent),
+            ..Node::new(game)
+        };
+        let id = self.nodes.len();
+        self.nodes.push(child);
+        self.nodes[parent].children.push(id);
+        Some(id)
+    }
+
+    /// Plays a move from the current node, which becomes the node of the move if it is legal
+    pub fn play(&mut self, m: &Move) -> Option<NodeId> {
+        let id = self.add_move(self.current, m)?;
+        self.current = id;
+        Some(id)
+    }
+
+    /// Plays a move written in standard algebraic notation from the current node
+    pub fn play_san(&mut self, san: &str) -> Option<NodeId> {
+        let m = self.game().from_san(san, self.white_to_play())?;
+        self.play(&m)
+    }
+
+    pub fn go_to(&mut self, id: NodeId) {
+        assert!(id < self.nodes.len(), "There is no node {id}");
+        self.current = id;
+    }
+
+    /// Goes back to the position before the current move. Returns false at the initial position.
+    pub fn back(&mut self) -> bool {
+        match self.nodes[self.current].parent {
+            Some(parent) => {
+                self.current = parent;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Goes to the next move of the current line. Returns false at the end of the line.
+    pub fn forward(&mut self) -> bool {
+        match self.nodes[self.current].children.first() {
+            Some(&child) => {
+                self.current = child;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn go_to_start(&mut self) {
+        self.current = ROOT;
+    }
+
+    /// Goes to the last move of the current line
+    pub fn go_to_end(&mut self) {
+        while self.forward() {}
+    }
+
+    /// Nodes of the moves played from the initial position to a node, in this order
+    pub fn path(&self, id: NodeId) -> Vec<NodeId> {
+        let mut path = vec![];
+        let mut node = id;
+        while let Some(parent) = self.nodes[node].parent {
+            path.push(node);
+            node = parent;
+        }
+        path.reverse();
+        path
+    }
+
+    /// Nodes of the main line of the game
+    pub fn main_line(&self) -> Vec<NodeId> {
+        let mut line = vec![];
+        let mut node = ROOT;
+        while let Some(&child) = self.nodes[node].children.first() {
+            line.push(child);
+            node = child;
+        }
+        line
+    }
+
+    /// Moves a node one place up among the moves played from its parent, so that a variation
+    /// comes before the other ones, or replaces the line that it comes from. Returns false if
+    /// the node is already the first one.
+    pub fn promote_variation(&mut self, id: NodeId) -> bool {
+        self.move_variation(id, -1)
+    }
+
+    /// Moves a node one place down among the moves played from its parent. Returns false if the
+    /// node is already the last one.
+    pub fn demote_variation(&mut self, id: NodeId) -> bool {
+        self.move_variation(id, 1)
+    }
+
+    fn move_variation(&mut self, id: NodeId, offset: isize) -> bool {
+        let Some(parent) = self.nodes[id].parent else {
+            return false;
+        };
+        let siblings = &mut self.nodes[parent].children;
+        let position = siblings.iter().position(|&child| child == id).unwrap();
+        match position.checked_add_signed(offset) {
+            Some(other) if other < siblings.len() => {
+                siblings.swap(position, other);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Deletes a node with all the moves which follow it. When the current node is deleted, the
+    /// parent of the node becomes the current node. The initial position can't be deleted.
+    ///
+    /// The indices of the remaining nodes may change.
+    pub fn delete_variation(&mut self, id: NodeId) -> bool {
+        let Some(parent) = self.nodes[id].parent else {
+            return false;
+        };
+        self.nodes[parent].children.retain(|&child| child != id);
+
+        let mut deleted = vec![false; self.nodes.len()];
+        let mut stack = vec![id];
+        while let Some(node) = stack.pop() {
+            deleted[node] = true;
+            stack.extend(&self.nodes[node].children);
+        }
+        if deleted[self.current] {
+            self.current = parent;
+        }
+
+        // The nodes are kept in the same order, without the deleted ones
+        let mut new_ids = vec![0; self.nodes.len()];
+        let mut count = 0;
+        for (node, new_id) in new_ids.iter_mut().enumerate() {
+            *new_id = count;
+            count += !deleted[node] as usize;
+        }
+        let nodes = std::mem::take(&mut self.nodes);
+        self.nodes = nodes
+            .into_iter()
+            .zip(&deleted)
+            .filter(|(_, &deleted)| !deleted)
+            .map(|(mut node, _)| {
+                node.parent = node.parent.map(|parent| new_ids[parent]);
+                node.children
+                    .iter_mut()
+                    .for_each(|child| *child = new_ids[*child]);
+                node
+            })
+            .collect();
+        self.current = new_ids[self.current];
+        true
+    }
+
+    /// Replays a game read in PGN, with its variations, its comments and its annotations. The
+    /// current node is the initial position.
+    pub fn from_pgn(pgn: &PgnGame) -> Result<Self, String> {
+        let mut tree = Self::new(pgn.initial_position()?);
+        tree.tags = pgn.tags.clone();
+        tree.result = pgn.result.clone();
+        tree.nodes[ROOT].comment = pgn.line.comment.clone();
+        tree.add_line(ROOT, &pgn.line.moves)?;
+        Ok(tree)
+    }
+
+    /// Adds the moves of a line, played from a node, with their variations
+    fn add_line(&mut self, mut node: NodeId, moves: &[PgnMove]) -> Result<(), String> {
+        for pgn_move in moves {
+            let parent = &self.nodes[node];
+            let m = parent
+                .game
+                .from_san(&pgn_move.san, parent.game.white_to_play())
+                .ok_or(format!("Illegal move: {}", pgn_move.san))?;
+            let child = self.add_move(node, &m).unwrap();
+            self.nodes[child].nags = pgn_move.nags.clone();
+            self.nodes[child].comment = pgn_move.comment.clone();
+
+            // The variations are played instead of the move
+            for variation in &pgn_move.variations {
+                let first = self.nodes[node].children.len();
+                self.add_line(node, &variation.moves)?;
+                if let Some(&start) = self.nodes[node].children.get(first) {
+                    self.nodes[start].comment_before = variation.comment.clone();
+                }
+            }
+            node = child;
+        }
+        Ok(())
+    }
+
+    /// Writes the game in PGN, with its variations, its comments and its annotations. The initial
+    /// position is given by the `SetUp` and `FEN` tags when it isn't the standard one.
+    pub fn to_pgn(&self) -> PgnGame {
+        let mut pgn = PgnGame {
+            tags: self.tags.clone(),
+            line: self.pgn_line(ROOT, self.nodes[ROOT].comment.clone()),
+            result: self.result.clone(),
+        };
+        let fen = self.nodes[ROOT].game.to_fen();
+        if pgn.tag("FEN").is_none() && fen != ChessGame::standard_game().to_fen() {
+            pgn.set_tag("SetUp", "1");
+            pgn.set_tag("FEN", &fen);
+        }
+        pgn
+    }
+
+    /// Writes the line which continues after a node
+    fn pgn_line(&self, mut node: NodeId, comment: Option<String>) -> PgnLine {
+        let mut line = PgnLine {
+            comment,
+            moves: vec![],
+        };
+        while let Some((&child, variations)) = self.nodes[node].children.split_first() {
+            let mut m = self.pgn_move(child);
+            m.variations = variations
+                .iter()
+                .map(|&variation| {
+                    let comment = self.nodes[variation].comment_before.clone();
+                    let mut line = self.pgn_line(variation, comment);
+                    line.moves.insert(0, self.pgn_move(variation));
+                    line
+                })
+                .collect();
+            line.moves.push(m);
+            node = child;
+        }
+        line
+    }
+
+    /// Writes the move of a node, without its variations
+    fn pgn_move(&self, id: NodeId) -> PgnMove {
+        PgnMove {
+            san: self.san(id).unwrap(),
+            nags: self.nodes[id].nags.clone(),
+            comment: self.nodes[id].comment.clone(),
+            variations: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_type::Type::Knight;
+    use crate::game::ChessGame;
+    use crate::game_tree::{GameTree, ROOT};
+    use crate::moves::Move;
+    use crate::pgn::read_games;
+
+    fn sans(tree: &GameTree, ids: &[usize]) -> Vec<String> {
+        ids.iter().map(|&id| tree.san(id).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_play_and_navigate() {
+        let mut tree = GameTree::default();
+        let e4 = tree.play(&Move::from_str("e2", "e4", true)).unwrap();
+        tree.play_san("e5").unwrap();
+        assert_eq!(None, tree.play_san("e4"));
+        assert_eq!(None, tree.play(&Move::from_str("e1", "e3", true)));
+        tree.play_san("Nf3").unwrap();
+        assert_eq!(vec!["e4", "e5", "Nf3"], sans(&tree, &tree.main_line()));
+
+        // Playing another move after e4 creates a variation
+        tree.go_to(e4);
+        let c5 = tree.play_san("c5").unwrap();
+        assert_eq!(vec!["e4", "e5", "Nf3"], sans(&tree, &tree.main_line()));
+        assert_eq!(vec!["e4", "c5"], sans(&tree, &tree.path(c5)));
+        assert!(tree.white_to_play());
+
+        // Playing a move again goes to its node
+        tree.go_to(e4);
+        assert_eq!(Some(c5), tree.play_san("c5"));
+        assert_eq!(5, tree.len());
+
+        assert!(tree.back());
+        assert_eq!(e4, tree.current());
+        tree.go_to_end();
+        assert_eq!(
+            vec!["e4", "e5", "Nf3"],
+            sans(&tree, &tree.path(tree.current()))
+        );
+        assert!(!tree.forward());
+        tree.go_to_start();
+        assert_eq!(ROOT, tree.current());
+        assert!(!tree.back());
+        assert_eq!(&ChessGame::standard_game(), tree.game());
+    }
+
+    #[test]
+    fn test_edit_variations() {
+        let mut tree = GameTree::default();
+        let e4 = tree.play_san("e4").unwrap();
+        let e5 = tree.play_san("e5").unwrap();
+        tree.go_to(e4);
+        let c5 = tree.play_san("c5").unwrap();
+        let nf3 = tree.play_san("Nf3").unwrap();
+        tree.go_to(e4);
+        let e6 = tree.play_san("e6").unwrap();
+
+        assert!(!tree.promote_variation(e5));
+        assert!(!tree.demote_variation(e6));
+        assert!(tree.promote_variation(c5));
+        assert_eq!(vec![c5, e5, e6], tree.node(e4).children());
+        assert_eq!(vec!["e4", "c5", "Nf3"], sans(&tree, &tree.main_line()));
+        assert!(tree.demote_variation(c5));
+        assert!(tree.demote_variation(c5));
+        assert_eq!(vec![e5, e6, c5], tree.node(e4).children());
+
+        // The current node is deleted with the variation
+        tree.go_to(nf3);
+        assert!(tree.delete_variation(c5));
+        assert!(!tree.delete_variation(ROOT));
+        assert_eq!(e4, tree.current());
+        assert_eq!(4, tree.len());
+        assert_eq!(vec!["e5", "e6"], sans(&tree, tree.node(e4).children()));
+        for &child in tree.node(e4).children() {
+            assert_eq!(Some(e4), tree.node(child).parent());
+        }
+    }
+
+    #[test]
+    fn test_pgn_round_trip() {
+        let pgn = "[Event \"Test\"]\n\n{Start} 1. e4 $1 e5 (1... c5 {Sicilian} 2. Nf3 (2. c3) d6) \
+                   ({French} 1... e6) 2. Nf3 Nc6 {Main line} 1-0\n";
+        let pgn = &read_games(pgn)[0];
+        let tree = GameTree::from_pgn(pgn).unwrap();
+        assert_eq!(
+            vec!["e4", "e5", "Nf3", "Nc6"],
+            sans(&tree, &tree.main_line())
+        );
+        let e4 = tree.main_line()[0];
+        assert_eq!(vec![1], tree.node(e4).nags);
+        assert_eq!(
+            vec!["e5", "c5", "e6"],
+            sans(&tree, tree.node(e4).children())
+        );
+        let e6 = tree.node(e4).children()[2];
+        assert_eq!(Some("French"), tree.node(e6).comment_before.as_deref());
+        assert_eq!(pgn, &tree.to_pgn());
+
+        // Illegal moves are rejected
+        let pgn = &read_games("1. e4 e5 (1... Nf3) *")[0];
+        assert_eq!(
+            Some("Illegal move: Nf3".to_string()),
+            GameTree::from_pgn(pgn).err()
+        );
+    }
+
+    #[test]
+    fn test_pgn_from_position() {
+        let game = ChessGame::from_fen("k7/8/1K6/8/8/8/7Q/8 w - - 0 1");
+        let mut tree = GameTree::new(game);
+        tree.play_san("Qh8#").unwrap();
+        let pgn = tree.to_pgn();
+        assert_eq!(Some("1"), pgn.tag("SetUp"));
+        let tree = GameTree::from_pgn(&read_games(&pgn.to_pgn())[0]).unwrap();
+        assert_eq!(vec!["Qh8#"], sans(&tree, &tree.main_line()));
+        assert_eq!(&game, tree.node(ROOT).game());
+    }
+
+    #[test]
+    fn test_underpromotions() {
+        let game = ChessGame::from_fen("8/5P1k/8/8/8/8/8/K7 w - - 0 1");
+        let mut tree = GameTree::new(game);
+        let queen = tree.play_san("f8=Q").unwrap();
+        tree.back();
+        let knight = tree.play_san("f8=N+").unwrap();
+        assert_ne!(queen, knight);
+        assert_eq!(Some(Knight), tree.game().type_at_index(61));
+
+        let tree = GameTree::from_pgn(&read_games(&tree.to_pgn().to_pgn())[0]).unwrap();
+        assert_eq!(vec!["f8=Q", "f8=N+"], sans(&tree, tree.node(ROOT).children()));
+    }
+}
@@ -1,6 +1,10 @@
 pub mod game;
 pub mod moves;
+pub mod compact_move;
 pub mod chess_type;
 pub mod utils;
 pub mod moves_container;
+pub mod pawn_hash_table;
+pub mod pgn;
+pub mod game_tree;
 mod motion_iterator;
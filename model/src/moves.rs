@@ -40,12 +40,17 @@ pub struct Move {
     pub to: ChessPosition,
     pub is_white: bool,
     pub quality: MoveQuality,
+    /// Piece of the promotion when it isn't a queen. The move generator only promotes to queens,
+    /// the underpromotions are only played from the notations which give them.
+    pub underpromotion: Option<Type>,
 }
 
 impl PartialEq<Self> for Move {
     fn eq(&self, other: &Self) -> bool {
         // The implementation of `PartialEq` is a bit more minimalist than the default
-        self.from == other.from && self.to == other.to
+        self.from == other.from
+            && self.to == other.to
+            && self.underpromotion == other.underpromotion
     }
 }
 
@@ -60,7 +65,11 @@ impl Debug for Move {
             "{}->{}",
             index_to_chesspos(self.from),
             index_to_chesspos(self.to)
-        )
+        )?;
+        match self.underpromotion {
+            Some(piece) => write!(f, "={piece:?}"),
+            None => Ok(()),
+        }
     }
 }
 
@@ -76,7 +85,11 @@ impl fmt::Display for Move {
             "{}->{}",
             index_to_chesspos(self.from),
             index_to_chesspos(self.to)
-        )
+        )?;
+        match self.underpromotion {
+            Some(piece) => write!(f, "={piece:?}"),
+            None => Ok(()),
+        }
     }
 }
 
@@ -87,6 +100,7 @@ impl Move {
             to,
             is_white,
             quality: Motion,
+            underpromotion: None,
         }
     }
 
@@ -101,6 +115,7 @@ impl Move {
             to: to.as_chess_position(),
             is_white,
             quality: Motion,
+            underpromotion: None,
         }
     }
 
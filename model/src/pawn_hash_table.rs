@@ -0,0 +1,79 @@
+use crate::game::pawns::PawnStructure;
+use crate::game::ChessGame;
+
+/// Default number of entries of the table (must be a power of two)
+const DEFAULT_SIZE: usize = 1 << 14;
+
+#[derive(Copy, Clone, Default)]
+struct Entry {
+    key: u64,
+    structure: PawnStructure,
+}
+
+/// A cache of the evaluation of the pawn structures, indexed by the pawn key of the games.
+///
+/// Pawns move rarely, so the same pawn structure is evaluated many times during a search.
+pub struct PawnHashTable {
+    entries: Vec<Entry>,
+    mask: usize,
+}
+
+impl Default for PawnHashTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_SIZE)
+    }
+}
+
+impl PawnHashTable {
+    /// Creates a table with `size` entries, rounded up to the next power of two.
+    pub fn new(size: usize) -> Self {
+        let size = size.max(1).next_power_of_two();
+        Self {
+            entries: vec![Entry::default(); size],
+            mask: size - 1,
+        }
+    }
+
+    /// Removes all the entries of the table
+    pub fn clear(&mut self) {
+        self.entries.fill(Entry::default());
+    }
+
+    /// Returns the evaluation of the pawn structure of the game, computing it only if it is not
+    /// already in the table.
+    pub(crate) fn probe(&mut self, game: &ChessGame) -> PawnStructure {
+        let key = game.pawn_key();
+        let entry = &mut self.entries[key as usize & self.mask];
+        // The empty pawn structure has a key of 0, which is also the key of the empty entries.
+        // This is fine since both evaluations are the default value.
+        if entry.key != key {
+            entry.key = key;
+            entry.structure = game.pawn_structure();
+        }
+        entry.structure
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::ChessGame;
+    use crate::pawn_hash_table::PawnHashTable;
+
+    #[test]
+    fn test_cached_score_is_identical() {
+        let mut table = PawnHashTable::new(16);
+        let games = [
+            ChessGame::standard_game(),
+            ChessGame::from_fen("4k3/8/8/P2p4/4P3/8/8/4K3 w - - 0 1"),
+            ChessGame::from_fen("4k3/ppp5/8/8/8/P7/P1P5/4K3 w - - 0 1"),
+            ChessGame::from_fen("6r1/p1q3bk/4rnR1/2p2Q1P/1p1p4/3P2P1/2PK1B2/8 w - - 0 46"),
+        ];
+
+        // Twice, so that the second time the results come from the cache
+        for _ in 0..2 {
+            for game in &games {
+                assert_eq!(game.score(), game.score_with_pawn_table(&mut table));
+            }
+        }
+    }
+}
@@ -0,0 +1,600 @@
+//! Reading and writing of games in the Portable Game Notation.
+//!
+//! A game is read as a tree of moves: each move of a line may have alternatives, the variations,
+//! which are lines starting from the same position. The moves are kept in standard algebraic
+//! notation, exactly as written: they aren't checked against the positions of the game.
+
+use crate::game::ChessGame;
+use std::fmt::Write;
+use std::io::{self, BufRead};
+
+/// Tags which every game has, in this order
+pub const SEVEN_TAG_ROSTER: [&str; 7] =
+    ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// Maximum length of the lines written
+const LINE_LENGTH: usize = 80;
+
+/// Annotations written after the moves, with their numeric annotation glyph
+const SUFFIX_ANNOTATIONS: [(&str, u8); 6] = [
+    ("!!", 3),
+    ("??", 4),
+    ("!?", 5),
+    ("?!", 6),
+    ("!", 1),
+    ("?", 2),
+];
+
+/// A move of a game, with the annotations which follow it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PgnMove {
+    /// The move in standard algebraic notation, without its annotation suffix (`!`, `?!`...)
+    pub san: String,
+    /// Numeric annotation glyphs, such as 1 for a good move (`$1` or `!`)
+    pub nags: Vec<u8>,
+    /// Comment written after the move
+    pub comment: Option<String>,
+    /// Lines which can be played instead of this move
+    pub variations: Vec<PgnLine>,
+}
+
+impl PgnMove {
+    pub fn new(san: &str) -> Self {
+        Self {
+            san: san.to_string(),
+            ..Self::default()
+        }
+    }
+}
+
+/// A sequence of moves, from the start of the game or of a variation
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PgnLine {
+    /// Comment written before the first move
+    pub comment: Option<String>,
+    pub moves: Vec<PgnMove>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PgnGame {
+    /// Tags, in the order of the file
+    pub tags: Vec<(String, String)>,
+    /// Main line of the game, with its variations
+    pub line: PgnLine,
+    /// Result of the game written after the moves: `1-0`, `0-1`, `1/2-1/2` or `*`
+    pub result: Option<String>,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets the value of a tag, which is added after the others if the game doesn't have it
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// Moves of the main line, in standard algebraic notation
+    pub fn main_line(&self) -> Vec<&str> {
+        self.line.moves.iter().map(|m| m.san.as_str()).collect()
+    }
+
+    /// Reads the initial position of the game, which is given by the `FEN` tag when the game
+    /// doesn't start from the standard position.
+    pub fn initial_position(&self) -> Result<ChessGame, String> {
+        let Some(fen) = self.tag("FEN") else {
+            return Ok(ChessGame::standard_game());
+        };
+        let mut fields = fen.split_whitespace();
+        let board = fields.next().unwrap_or_default();
+        let valid_board = board.split('/').count() == 8
+            && board.chars().all(|c| "pnbrqkPNBRQK12345678/".contains(c));
+        if !valid_board {
+            return Err(format!("Invalid FEN: {fen}"));
+        }
+        if fields.count() < 3 {
+            return Err(format!("Invalid FEN: {fen}"));
+        }
+        Ok(ChessGame::from_fen(fen))
+    }
+
+    /// Score of white: 1 for a win, 0.5 for a draw and 0 for a loss. Unfinished games have no
+    /// score.
+    pub fn white_score(&self) -> Option<f64> {
+        match self.result.as_deref().or(self.tag("Result"))? {
+            "1-0" => Some(1.0),
+            "1/2-1/2" => Some(0.5),
+            "0-1" => Some(0.0),
+            _ => None,
+        }
+    }
+
+    /// Writes the game in the PGN format.
+    ///
+    /// The tags of the seven tag roster are written first, with their unknown values when the game
+    /// doesn't have them, and the moves are numbered from the `FEN` tag when there is one. The
+    /// lines are at most 80 characters long, and the game is followed by an empty line.
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+        let result = self.result.as_deref().or(self.tag("Result")).unwrap_or("*");
+        for name in SEVEN_TAG_ROSTER {
+            let value = match (name, self.tag(name)) {
+                ("Result", _) => result,
+                (_, Some(value)) => value,
+                ("Date", None) => "????.??.??",
+                (_, None) => "?",
+            };
+            write_tag(&mut pgn, name, value);
+        }
+        for (name, value) in &self.tags {
+            if !SEVEN_TAG_ROSTER.contains(&name.as_str()) {
+                write_tag(&mut pgn, name, value);
+            }
+        }
+        pgn.push('\n');
+
+        let (number, white) = self.first_move();
+        let mut tokens = vec![];
+        write_line(&self.line, number, white, &mut tokens);
+        tokens.push(result.to_string());
+
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > LINE_LENGTH {
+                pgn += &line;
+                pgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() && !line.ends_with('(') && !token.starts_with(')') {
+                line.push(' ');
+            }
+            line += &token;
+        }
+        pgn += &line;
+        pgn += "\n\n";
+        pgn
+    }
+
+    /// Number of the first move, and whether white plays it, from the `FEN` tag
+    fn first_move(&self) -> (usize, bool) {
+        let Some(fen) = self.tag("FEN") else {
+            return (1, true);
+        };
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        let white = fields.get(1) != Some(&"b");
+        let number = fields.get(5).and_then(|n| n.parse().ok()).unwrap_or(1);
+        (number, white)
+    }
+}
+
+fn write_tag(pgn: &mut String, name: &str, value: &str) {
+    let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+    writeln!(pgn, "[{name} \"{value}\"]").unwrap();
+}
+
+/// A comment can't contain the character which ends it
+fn comment_token(comment: &str) -> String {
+    format!("{{{}}}", comment.replace('}', ")"))
+}
+
+/// Adds the tokens of a line: the move numbers, the moves, their annotations and their variations
+fn write_line(line: &PgnLine, mut number: usize, mut white: bool, tokens: &mut Vec<String>) {
+    if let Some(comment) = &line.comment {
+        tokens.push(comment_token(comment));
+    }
+    // The number of a move of black is written when something separates it from the move of white
+    let mut numbered = false;
+    for m in &line.moves {
+        if white {
+            tokens.push(format!("{number}."));
+        } else if !numbered {
+            tokens.push(format!("{number}..."));
+        }
+        tokens.push(m.san.clone());
+        tokens.extend(m.nags.iter().map(|nag| format!("${nag}")));
+        numbered = m.comment.is_none() && m.variations.is_empty();
+        if let Some(comment) = &m.comment {
+            tokens.push(comment_token(comment));
+        }
+        for variation in &m.variations {
+            tokens.push("(".to_string());
+            write_line(variation, number, white, tokens);
+            tokens.push(")".to_string());
+        }
+        if !white {
+            number += 1;
+        }
+        white = !white;
+    }
+}
+
+/// Parses a tag pair such as `[White "Carlsen, Magnus"]`
+fn parse_tag(line: &str) -> Option<(String, String)> {
+    let inner = line.trim().strip_prefix('[')?.strip_suffix(']')?;
+    let (name, value) = inner.split_once(char::is_whitespace)?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    Some((
+        name.to_string(),
+        value.replace("\\\"", "\"").replace("\\\\", "\\"),
+    ))
+}
+
+fn is_result(token: &str) -> bool {
+    matches!(token, "1-0" | "0-1" | "1/2-1/2" | "*")
+}
+
+/// Reads the games of a PGN file one by one, without loading the whole file in memory
+pub struct PgnReader<R: BufRead> {
+    reader: R,
+    /// Line read ahead, which starts the next game
+    pending_line: Option<String>,
+}
+
+/// Game being read, with the lines of the variations which aren't closed yet
+struct GameBuilder {
+    game: PgnGame,
+    /// The lines being read, from the main line to the innermost variation
+    lines: Vec<PgnLine>,
+    /// Comment being read, which may span several lines of the file
+    comment: Option<String>,
+    has_content: bool,
+}
+
+impl GameBuilder {
+    fn new() -> Self {
+        Self {
+            game: PgnGame::default(),
+            lines: vec![PgnLine::default()],
+            comment: None,
+            has_content: false,
+        }
+    }
+
+    fn has_moves(&self) -> bool {
+        !self.lines[0].moves.is_empty()
+    }
+
+    fn current_line(&mut self) -> &mut PgnLine {
+        self.lines.last_mut().unwrap()
+    }
+
+    /// Attaches a comment to the last move, or to the line if it has no move yet
+    fn add_comment(&mut self, comment: &str) {
+        let comment = comment.trim();
+        if comment.is_empty() {
+            return;
+        }
+        let line = self.current_line();
+        let target = match line.moves.last_mut() {
+            Some(m) => &mut m.comment,
+            None => &mut line.comment,
+        };
+        match target {
+            Some(previous) => {
+                previous.push(' ');
+                previous.push_str(comment);
+            }
+            None => *target = Some(comment.to_string()),
+        }
+        self.has_content = true;
+    }
+
+    fn add_nag(&mut self, nag: u8) {
+        if let Some(m) = self.current_line().moves.last_mut() {
+            m.nags.push(nag);
+        }
+    }
+
+    fn open_variation(&mut self) {
+        self.lines.push(PgnLine::default());
+    }
+
+    /// Attaches the variation to the move that it replaces, the last move of the enclosing line
+    fn close_variation(&mut self) {
+        if self.lines.len() < 2 {
+            return;
+        }
+        let variation = self.lines.pop().unwrap();
+        if let Some(m) = self.current_line().moves.last_mut() {
+            m.variations.push(variation);
+        }
+    }
+
+    /// Adds a token which isn't a comment nor a parenthesis
+    fn add_token(&mut self, token: &str) {
+        // The move numbers (`12.`, `12...` or `12.e4`)
+        let token = match token.rfind('.') {
+            Some(i) => &token[i + 1..],
+            None => token,
+        };
+        if token.is_empty() {
+            return;
+        }
+        if let Some(nag) = token.strip_prefix('$') {
+            if let Ok(nag) = nag.parse() {
+                self.add_nag(nag);
+            }
+            return;
+        }
+        // Annotations written alone, or after the move
+        let san = token.trim_end_matches(['!', '?']);
+        let suffix = &token[san.len()..];
+        if !san.is_empty() {
+            self.current_line().moves.push(PgnMove::new(san));
+            self.has_content = true;
+        }
+        if let Some((_, nag)) = SUFFIX_ANNOTATIONS.iter().find(|(s, _)| *s == suffix) {
+            self.add_nag(*nag);
+        }
+    }
+
+    fn finish(mut self) -> Option<PgnGame> {
+        while self.lines.len() > 1 {
+            self.close_variation();
+        }
+        self.game.line = self.lines.pop().unwrap();
+        self.has_content.then_some(self.game)
+    }
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            pending_line: None,
+        }
+    }
+
+    fn next_line(&mut self) -> io::Result<Option<String>> {
+        if let Some(line) = self.pending_line.take() {
+            return Ok(Some(line));
+        }
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line))
+    }
+
+    fn read_game(&mut self) -> io::Result<Option<PgnGame>> {
+        let mut builder = GameBuilder::new();
+
+        while let Some(line) = self.next_line()? {
+            let trimmed = line.trim();
+            if builder.comment.is_none() && builder.lines.len() == 1 && trimmed.starts_with('[') {
+                // The tags of the next game, when the previous one had no result
+                if builder.has_moves() {
+                    self.pending_line = Some(line);
+                    return Ok(builder.finish());
+                }
+                if let Some(tag) = parse_tag(trimmed) {
+                    builder.game.tags.push(tag);
+                    builder.has_content = true;
+                }
+                continue;
+            }
+            // Escaped lines
+            if builder.comment.is_none() && line.starts_with('%') {
+                continue;
+            }
+
+            let mut token = String::new();
+            let mut chars = line.trim_end_matches(['\n', '\r']).chars();
+            loop {
+                let c = chars.next();
+                if let Some(comment) = &mut builder.comment {
+                    match c {
+                        Some('}') => {
+                            let comment = builder.comment.take().unwrap();
+                            builder.add_comment(&comment);
+                        }
+                        Some(c) => comment.push(c),
+                        // Comments spanning several lines keep a space between the lines
+                        None => {
+                            if !comment.ends_with(' ') {
+                                comment.push(' ');
+                            }
+                            break;
+                        }
+                    }
+                    continue;
+                }
+
+                let ends_token = c.is_none_or(|c| c.is_whitespace() || "{}();".contains(c));
+                if ends_token && !token.is_empty() {
+                    let word = std::mem::take(&mut token);
+                    if is_result(&word) && builder.lines.len() == 1 {
+                        builder.game.result = Some(word);
+                        // The rest of the line belongs to this game
+                        return Ok(builder.finish());
+                    }
+                    builder.add_token(&word);
+                }
+                let Some(c) = c else { break };
+                match c {
+                    '{' => builder.comment = Some(String::new()),
+                    ';' => {
+                        // The rest of the line is a comment
+                        builder.add_comment(chars.as_str());
+                        break;
+                    }
+                    '(' => builder.open_variation(),
+                    ')' => builder.close_variation(),
+                    c if !c.is_whitespace() && c != '}' => token.push(c),
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(builder.finish())
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = io::Result<PgnGame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_game().transpose()
+    }
+}
+
+/// Reads all the games of a PGN text
+pub fn read_games(pgn: &str) -> Vec<PgnGame> {
+    PgnReader::new(pgn.as_bytes())
+        .map(|game| game.expect("Reading from memory can't fail"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pgn::{parse_tag, read_games, PgnGame, PgnLine, PgnMove};
+
+    #[test]
+    fn test_parse_tag() {
+        assert_eq!(
+            Some(("White".to_string(), "Carlsen, Magnus".to_string())),
+            parse_tag("[White \"Carlsen, Magnus\"]")
+        );
+        assert_eq!(
+            Some(("Event".to_string(), "The \"Open\"".to_string())),
+            parse_tag(r#"[Event "The \"Open\""]"#)
+        );
+        assert_eq!(None, parse_tag("[White]"));
+    }
+
+    #[test]
+    fn test_read_games() {
+        let pgn = r#"[Event "Test"]
+[Result "1-0"]
+
+1. e4 {best by test} e5 2. Nf3 (2. f4 exf4 (2... d5)) 2... Nc6 $1 3. Bb5 ; Spanish
+a6 1-0
+
+[Event "Second"]
+[Result "1/2-1/2"]
+
+1.d4 d5 2.c4 {a comment
+on two lines} e6 1/2-1/2
+[Event "Unfinished"]
+1. c4 *
+"#;
+        let games = read_games(pgn);
+        assert_eq!(3, games.len());
+
+        assert_eq!(Some("Test"), games[0].tag("Event"));
+        assert_eq!(
+            vec!["e4", "e5", "Nf3", "Nc6", "Bb5", "a6"],
+            games[0].main_line()
+        );
+        assert_eq!(Some(1.0), games[0].white_score());
+
+        assert_eq!(vec!["d4", "d5", "c4", "e6"], games[1].main_line());
+        assert_eq!(
+            Some("a comment on two lines"),
+            games[1].line.moves[2].comment.as_deref()
+        );
+        assert_eq!(Some(0.5), games[1].white_score());
+
+        assert_eq!(vec!["c4"], games[2].main_line());
+        assert_eq!(None, games[2].white_score());
+    }
+
+    #[test]
+    fn test_game_without_result() {
+        let pgn = "[Event \"A\"]\n1. e4 e5 2. O-O 0-0\n\n[Event \"B\"]\n1. d4 0-1\n";
+        let games = read_games(pgn);
+        assert_eq!(2, games.len());
+        assert_eq!(None, games[0].result);
+        assert_eq!(vec!["e4", "e5", "O-O", "0-0"], games[0].main_line());
+        assert_eq!(vec!["d4"], games[1].main_line());
+        assert_eq!(Some(0.0), games[1].white_score());
+    }
+
+    #[test]
+    fn test_annotations_and_variations() {
+        let pgn = "{Start} 1. e4!? e5 $2 2. Nf3 (2. f4 {King's gambit} exf4 (2... d5!) 3. Nf3) \
+                   (2. Nc3) Nc6 *";
+        let game = &read_games(pgn)[0];
+        let line = &game.line;
+        assert_eq!(Some("Start"), line.comment.as_deref());
+        assert_eq!(vec![5], line.moves[0].nags);
+        assert_eq!(vec![2], line.moves[1].nags);
+
+        // The variations replace the move of white
+        let variations = &line.moves[2].variations;
+        assert_eq!(2, variations.len());
+        let gambit = &variations[0];
+        assert_eq!(3, gambit.moves.len());
+        assert_eq!(Some("King's gambit"), gambit.moves[0].comment.as_deref());
+        let counter_gambit = &gambit.moves[1].variations[0];
+        assert_eq!(
+            vec![PgnMove {
+                san: "d5".to_string(),
+                nags: vec![1],
+                ..PgnMove::default()
+            }],
+            counter_gambit.moves
+        );
+        assert_eq!(PgnMove::new("Nc3"), variations[1].moves[0]);
+        assert_eq!(vec!["e4", "e5", "Nf3", "Nc6"], game.main_line());
+        assert_eq!(Some("*"), game.result.as_deref());
+    }
+
+    #[test]
+    fn test_write_game() {
+        let mut game = PgnGame::default();
+        game.set_tag("White", "Morphy, Paul");
+        game.set_tag("Annotator", "Me");
+        game.set_tag("Event", "Paris");
+        game.result = Some("1-0".to_string());
+        let mut moves: Vec<PgnMove> = ["e4", "e5", "Nf3", "d6"]
+            .into_iter()
+            .map(PgnMove::new)
+            .collect();
+        moves[1].nags.push(1);
+        moves[2].comment = Some("The {best} move".to_string());
+        moves[3].variations.push(PgnLine {
+            comment: None,
+            moves: vec![PgnMove::new("Nc6"), PgnMove::new("Bb5")],
+        });
+        game.line.moves = moves;
+
+        let pgn = game.to_pgn();
+        assert_eq!(
+            "[Event \"Paris\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n\
+             [White \"Morphy, Paul\"]\n[Black \"?\"]\n[Result \"1-0\"]\n[Annotator \"Me\"]\n\n\
+             1. e4 e5 $1 2. Nf3 {The {best) move} 2... d6 (2... Nc6 3. Bb5) 1-0\n\n",
+            pgn
+        );
+
+        // The game is read back, except for the comment which can't be written as is
+        game.line.moves[2].comment = Some("The {best) move".to_string());
+        let read = &read_games(&pgn)[0];
+        assert_eq!(game.line, read.line);
+        assert_eq!(Some("1-0"), read.tag("Result"));
+    }
+
+    #[test]
+    fn test_write_from_position() {
+        let mut game = PgnGame::default();
+        game.set_tag("FEN", "4k3/8/8/8/8/8/8/R3K3 b - - 0 12");
+        game.line.moves = ["Kd7", "Ra7+", "Kc6", "Ra6+"]
+            .iter()
+            .cycle()
+            .take(100)
+            .map(|san| PgnMove::new(san))
+            .collect();
+        let pgn = game.to_pgn();
+        assert!(pgn.contains("\n\n12... Kd7 13. Ra7+ Kc6 14. Ra6+ Kd7"));
+        assert!(pgn.ends_with(" *\n\n"));
+        assert!(pgn.lines().all(|line| line.len() <= 80));
+        assert_eq!(game.line, read_games(&pgn)[0].line);
+    }
+}
@@ -97,6 +97,12 @@ impl IntoChessPosition for &str {
     }
 }
 
+impl IntoChessPosition for ChessPosition {
+    fn as_chess_position(&self) -> ChessPosition {
+        *self
+    }
+}
+
 // transforms a position (x,y) into a bit index
 pub fn pos_to_index(x: ChessPosition, y: ChessPosition) -> ChessPosition {
     x + 8 * y
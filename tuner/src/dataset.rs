@@ -0,0 +1,97 @@
+use model::game::ChessGame;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// A quiet position, with the result of the game it comes from: 1 if white won, 0.5 for a draw and
+/// 0 if black won.
+pub struct LabelledPosition {
+    pub game: ChessGame,
+    pub result: f64,
+}
+
+/// Reads the result of a line, written either as a PGN result (`1-0`, `1/2-1/2`, `0-1`, possibly
+/// between quotes as in the `c9` field of EPD files) or as a number between brackets (`[1.0]`).
+fn parse_result(line: &str) -> Option<f64> {
+    if let Some(start) = line.rfind('[') {
+        let end = line[start..].find(']')? + start;
+        return line[start + 1..end]
+            .trim()
+            .parse()
+            .ok()
+            .filter(|r| (0.0..=1.0).contains(r));
+    }
+
+    line.split(|c: char| c.is_whitespace() || c == '"' || c == ';')
+        .find_map(|token| match token {
+            "1-0" => Some(1.0),
+            "1/2-1/2" => Some(0.5),
+            "0-1" => Some(0.0),
+            _ => None,
+        })
+}
+
+/// Parses a line made of the FEN of the position (at least its first four fields) followed by the
+/// result of the game.
+pub fn parse_line(line: &str) -> Option<LabelledPosition> {
+    let fields: Vec<&str> = line.split_whitespace().take(4).collect();
+    let valid_board = |board: &str| {
+        board.split('/').count() == 8 && board.chars().all(|c| "pnbrqkPNBRQK12345678/".contains(c))
+    };
+    if fields.len() < 4 || !valid_board(fields[0]) {
+        return None;
+    }
+    let result = parse_result(line)?;
+    let game = ChessGame::from_fen(&fields.join(" "));
+    // Both kings are needed by the evaluation
+    if game.is_finished() {
+        return None;
+    }
+    Some(LabelledPosition { game, result })
+}
+
+/// Reads all the labelled positions of a file, skipping the lines which can't be parsed.
+pub fn load(path: impl AsRef<Path>) -> std::io::Result<Vec<LabelledPosition>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut positions = vec![];
+    for line in reader.lines() {
+        if let Some(position) = parse_line(&line?) {
+            positions.push(position);
+        }
+    }
+    Ok(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dataset::{parse_line, parse_result};
+    use model::game::ChessGame;
+
+    #[test]
+    fn test_parse_result() {
+        assert_eq!(Some(1.0), parse_result("8/8/8/8/8/8/8/8 w - - c9 \"1-0\";"));
+        assert_eq!(
+            Some(0.5),
+            parse_result("8/8/8/8/8/8/8/8 w - - c9 \"1/2-1/2\";")
+        );
+        assert_eq!(Some(0.0), parse_result("8/8/8/8/8/8/8/8 w - - 0 1 0-1"));
+        assert_eq!(Some(0.5), parse_result("8/8/8/8/8/8/8/8 w - - 0 1 [0.5]"));
+        assert_eq!(None, parse_result("8/8/8/8/8/8/8/8 w - - 0 1"));
+        assert_eq!(None, parse_result("8/8/8/8/8/8/8/8 w - - 0 1 [2.0]"));
+    }
+
+    #[test]
+    fn test_parse_line() {
+        let position =
+            parse_line("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - c9 \"1/2-1/2\";")
+                .unwrap();
+        assert_eq!(ChessGame::standard_game(), position.game);
+        assert_eq!(0.5, position.result);
+
+        // Missing result, or missing king
+        assert!(parse_line("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").is_none());
+        assert!(parse_line("4k3/8/8/8/8/8/8/8 w - - 0 1 [1.0]").is_none());
+        assert!(parse_line("").is_none());
+        assert!(parse_line("4k3/8/8/8/8/8/8/4X3 w - - 0 1 [1.0]").is_none());
+    }
+}
@@ -0,0 +1,59 @@
+//! Tunes the weights of the evaluation with the Texel method.
+//!
+//! Usage: `texel_tuner <positions file> [passes] [output file]`
+//!
+//! Each line of the positions file contains the FEN of a quiet position, followed by the result of
+//! the game it comes from: either `1-0`, `1/2-1/2`, `0-1` (as in the `c9` field of EPD files) or a
+//! number between brackets such as `[0.5]`. After each pass, the tuned weights are written as the
+//! Rust source of `DEFAULT_PARAMETERS`, which can replace the one of
+//! `model/src/game/parameters.rs`.
+
+use crate::tuner::Tuner;
+use model::game::parameters::DEFAULT_PARAMETERS;
+use std::time::Instant;
+
+mod dataset;
+mod tuner;
+
+const DEFAULT_PASSES: usize = 10;
+const DEFAULT_OUTPUT: &str = "tuned_parameters.rs";
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(path) = args.get(1) else {
+        eprintln!("Usage: texel_tuner <positions file> [passes] [output file]");
+        std::process::exit(1);
+    };
+    let passes = args
+        .get(2)
+        .and_then(|a| a.parse().ok())
+        .unwrap_or(DEFAULT_PASSES);
+    let output = args.get(3).map(String::as_str).unwrap_or(DEFAULT_OUTPUT);
+
+    let positions = dataset::load(path).expect("Can't read the positions file");
+    println!("Positions           : {}", positions.len());
+
+    let mut parameters = DEFAULT_PARAMETERS;
+    let mut tuner = Tuner::new(positions);
+    let k = tuner.fit_scaling_constant(&parameters);
+    let mut error = tuner.error(&parameters);
+    println!("Scaling constant    : {k:.4}");
+    println!("Parameters          : {}", parameters.to_vector().len());
+    println!("Initial error       : {error:.6}");
+
+    for pass in 1..=passes {
+        let start = Instant::now();
+        let (new_error, improved) = tuner.local_search_pass(&mut parameters, error);
+        error = new_error;
+        println!(
+            "Pass {pass:>3}            : error {error:.6} ({:.1} s)",
+            start.elapsed().as_secs_f64()
+        );
+
+        std::fs::write(output, parameters.to_rust_source()).expect("Can't write the parameters");
+        if !improved {
+            break;
+        }
+    }
+    println!("Parameters written to {output}");
+}
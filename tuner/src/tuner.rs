@@ -0,0 +1,142 @@
+use crate::dataset::LabelledPosition;
+use model::chess_type::ScoreType;
+use model::game::parameters::EvaluationParameters;
+
+/// Texel tuning of the weights of the evaluation.
+///
+/// The evaluation of each position is converted into an expected result with a sigmoid, and the
+/// tuner minimizes the mean squared error between the expected results and the actual results of
+/// the games.
+///
+/// https://www.chessprogramming.org/Texel%27s_Tuning_Method
+pub struct Tuner {
+    positions: Vec<LabelledPosition>,
+    /// Scaling constant of the sigmoid
+    k: f64,
+    threads: usize,
+}
+
+/// Converts a score in centipawns into an expected result
+fn sigmoid(score: ScoreType, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * score as f64 / 400.0))
+}
+
+impl Tuner {
+    pub fn new(positions: Vec<LabelledPosition>) -> Self {
+        let threads = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        Self {
+            positions,
+            k: 1.0,
+            threads,
+        }
+    }
+
+    /// Mean squared error of the evaluation with the given weights over all the positions
+    pub fn error(&self, parameters: &EvaluationParameters) -> f64 {
+        if self.positions.is_empty() {
+            return 0.0;
+        }
+        let chunk_size = self.positions.len().div_ceil(self.threads);
+        let total: f64 = std::thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .positions
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|position| {
+                                let score = position.game.score_with_parameters(parameters);
+                                (position.result - sigmoid(score, self.k)).powi(2)
+                            })
+                            .sum::<f64>()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).sum()
+        });
+        total / self.positions.len() as f64
+    }
+
+    /// Finds the scaling constant of the sigmoid which minimizes the error of the given weights,
+    /// with a ternary search. It is computed once, before tuning the weights.
+    pub fn fit_scaling_constant(&mut self, parameters: &EvaluationParameters) -> f64 {
+        let (mut low, mut high) = (0.05, 5.0);
+        for _ in 0..40 {
+            let a = low + (high - low) / 3.0;
+            let b = high - (high - low) / 3.0;
+            self.k = a;
+            let error_a = self.error(parameters);
+            self.k = b;
+            let error_b = self.error(parameters);
+            if error_a < error_b {
+                high = b;
+            } else {
+                low = a;
+            }
+        }
+        self.k = (low + high) / 2.0;
+        self.k
+    }
+
+    /// Runs one pass of local search: each weight is increased, or else decreased, by one
+    /// centipawn when this reduces the error. Returns the new error, and whether any weight changed.
+    pub fn local_search_pass(
+        &self,
+        parameters: &mut EvaluationParameters,
+        mut best_error: f64,
+    ) -> (f64, bool) {
+        let mut vector = parameters.to_vector();
+        let mut improved = false;
+
+        for i in 0..vector.len() {
+            for step in [1, -1] {
+                vector[i] += step;
+                parameters.set_vector(&vector);
+                let error = self.error(parameters);
+                if error < best_error {
+                    best_error = error;
+                    improved = true;
+                    break;
+                }
+                vector[i] -= step;
+            }
+        }
+
+        parameters.set_vector(&vector);
+        (best_error, improved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dataset::parse_line;
+    use crate::tuner::Tuner;
+    use model::game::parameters::DEFAULT_PARAMETERS;
+
+    #[test]
+    fn test_local_search_reduces_the_error() {
+        // White wins all the games where it has an extra knight, even if the knight is poorly
+        // placed: the tuner should increase the value of the knight.
+        let lines = [
+            "4k3/pppppppp/8/8/8/8/PPPPPPPP/N3K3 w - - 0 1 [1.0]",
+            "4k3/pppppppp/8/8/8/8/PPPPPPPP/4K2N w - - 0 1 [1.0]",
+            "n3k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1 [0.0]",
+            "4k2n/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1 [0.0]",
+            "4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1 [0.5]",
+        ];
+        let positions = lines.iter().filter_map(|l| parse_line(l)).collect();
+        let mut tuner = Tuner::new(positions);
+        let mut parameters = DEFAULT_PARAMETERS;
+        tuner.fit_scaling_constant(&parameters);
+
+        let initial_error = tuner.error(&parameters);
+        let (error, improved) = tuner.local_search_pass(&mut parameters, initial_error);
+        assert!(improved);
+        assert!(error < initial_error);
+        assert_eq!(error, tuner.error(&parameters));
+        assert!(parameters.mg_values[1] + parameters.eg_values[1] > 620);
+    }
+}
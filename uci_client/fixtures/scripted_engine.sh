@@ -0,0 +1,20 @@
+#!/bin/sh
+# Minimal UCI engine used by the tests: it always answers with the move given as first argument,
+# and with the score given as second argument.
+while read -r line; do
+    case "$line" in
+        uci)
+            echo "id name Scripted engine"
+            echo "id author Tests"
+            echo "option name Hash type spin default 16 min 1 max 1024"
+            echo "uciok"
+            ;;
+        isready) echo "readyok" ;;
+        go*)
+            echo "info depth 1 score cp 0 nodes 10 pv $1"
+            echo "info depth 2 score $2 nodes 20 pv $1 e7e5"
+            echo "bestmove $1"
+            ;;
+        quit) exit 0 ;;
+    esac
+done
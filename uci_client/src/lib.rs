@@ -0,0 +1,572 @@
+//! Client of the UCI protocol, to play against any UCI engine through the `Engine` trait.
+//!
+//! The engine runs in a child process. Its output is read by a background thread, so that an
+//! engine which stops answering can't block the caller forever.
+//!
+//! Requirements of the UCI protocol: https://gist.github.com/DOBRO/2592c6dad754ba67e6dcaec8c90165bf
+
+use engine::alpha_beta::MATE_SCORE;
+use engine::engine::{Engine, SearchResult};
+use model::chess_type::Type::{Bishop, Knight, Rook};
+use model::chess_type::{ScoreType, PAWN_SCORE};
+use model::game::ChessGame;
+use model::moves::Move;
+use model::utils::{index_to_chesspos, ChessPosition};
+use std::ffi::OsStr;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// Limit of the searches of the engine, sent with the `go` command. The duration of a search
+/// limited by depth is unknown, so the client waits for its move as long as the engine runs.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SearchLimit {
+    Depth(usize),
+    MoveTime(Duration),
+}
+
+/// A UCI engine running in a child process
+pub struct UciEngineClient {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    name: String,
+    limit: SearchLimit,
+    /// Maximum time to wait for an answer of the engine
+    timeout: Duration,
+    /// Set when the engine has stopped, or has been killed because it stopped answering
+    is_dead: bool,
+}
+
+/// Parses a square such as `e4`
+fn parse_square(text: &str) -> Option<ChessPosition> {
+    let mut chars = text.chars();
+    let file = chars.next().filter(|c| ('a'..='h').contains(c))?;
+    let rank = chars.next().filter(|c| ('1'..='8').contains(c))?;
+    Some((rank as u8 - b'1') as ChessPosition * 8 + (file as u8 - b'a') as ChessPosition)
+}
+
+/// Parses a move in the long algebraic notation of UCI, such as `e2e4` or `e7e8q`
+pub fn parse_uci_move(text: &str, is_white: bool) -> Option<Move> {
+    if !(4..=5).contains(&text.len()) || !text.is_ascii() {
+        return None;
+    }
+    let from = parse_square(&text[0..2])?;
+    let to = parse_square(&text[2..4])?;
+    let underpromotion = match text.get(4..) {
+        Some("") | Some("q") => None,
+        Some("n") => Some(Knight),
+        Some("b") => Some(Bishop),
+        Some("r") => Some(Rook),
+        _ => return None,
+    };
+    Some(Move {
+        underpromotion,
+        ..Move::new(from, to, is_white)
+    })
+}
+
+/// Writes a move in the long algebraic notation of UCI, in the position where it is played
+fn format_uci_move(game: &ChessGame, m: &Move) -> String {
+    let promotion = match m.underpromotion {
+        Some(Knight) => "n",
+        Some(Bishop) => "b",
+        Some(Rook) => "r",
+        _ if game.is_promotion(m) => "q",
+        _ => "",
+    };
+    format!(
+        "{}{}{promotion}",
+        index_to_chesspos(m.from),
+        index_to_chesspos(m.to)
+    )
+}
+
+/// Writes the `position` command of the position reached by playing `moves` from `start`
+fn position_command(start: ChessGame, moves: &[Move]) -> String {
+    let mut command = if start == ChessGame::standard_game() {
+        "position startpos".to_string()
+    } else {
+        format!("position fen {}", start.to_fen())
+    };
+    if !moves.is_empty() {
+        command.push_str(" moves");
+        let mut game = start;
+        for m in moves {
+            command.push(' ');
+            command.push_str(&format_uci_move(&game, m));
+            game.apply_move_unsafe(m);
+        }
+    }
+    command
+}
+
+/// Converts a mate announced in `moves` moves by the engine (negative when the side to move is
+/// mated) to the score given to this mate by `AlphaBetaEngine`, from the point of view of the side
+/// to move. The king is captured one ply after the mate, as in `engine::alpha_beta::mate_moves`.
+fn mate_score(moves: ScoreType) -> ScoreType {
+    if moves > 0 {
+        MATE_SCORE - (2 * moves + 1)
+    } else {
+        -(MATE_SCORE - (2 * -moves + 2))
+    }
+}
+
+fn stopped_error() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "The engine has stopped")
+}
+
+/// Reads the score, the number of nodes and the principal variation of an `info` line. The score is
+/// given from the point of view of the side to move, and is returned in the unit of
+/// `ChessGame::score`, from white's point of view.
+fn parse_info(line: &str, white_to_play: bool, result: &mut SearchResult) {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    // Only the best line is kept when the engine analyses several lines
+    if let Some(i) = tokens.iter().position(|t| *t == "multipv") {
+        if tokens.get(i + 1) != Some(&"1") {
+            return;
+        }
+    }
+
+    let mut i = 1;
+    while i < tokens.len() {
+        match tokens[i] {
+            "score" => {
+                let value = tokens.get(i + 2).and_then(|v| v.parse::<ScoreType>().ok());
+                let score = match (tokens.get(i + 1), value) {
+                    (Some(&"cp"), Some(cp)) => Some(cp * PAWN_SCORE / 100),
+                    (Some(&"mate"), Some(moves)) => Some(mate_score(moves)),
+                    _ => None,
+                };
+                if let Some(score) = score {
+                    result.score = if white_to_play { score } else { -score };
+                }
+                i += 3;
+            }
+            "nodes" => {
+                if let Some(nodes) = tokens.get(i + 1).and_then(|v| v.parse().ok()) {
+                    result.nodes = nodes;
+                }
+                i += 2;
+            }
+            "pv" => {
+                let mut white = white_to_play;
+                result.pv = tokens[i + 1..]
+                    .iter()
+                    .map_while(|m| {
+                        let m = parse_uci_move(m, white);
+                        white = !white;
+                        m
+                    })
+                    .collect();
+                break;
+            }
+            _ => i += 1,
+        }
+    }
+}
+
+impl UciEngineClient {
+    /// Starts the engine and waits until it is ready
+    pub fn spawn<S: AsRef<OsStr>>(program: S, args: &[S]) -> io::Result<Self> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child
+            .stdin
+            .take()
+            .expect("The input of the engine is piped");
+        let stdout = child
+            .stdout
+            .take()
+            .expect("The output of the engine is piped");
+
+        let (sender, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut client = Self {
+            child,
+            stdin,
+            lines,
+            name: String::new(),
+            limit: SearchLimit::MoveTime(Duration::from_secs(1)),
+            timeout: Duration::from_secs(30),
+            is_dead: false,
+        };
+
+        client.send("uci")?;
+        loop {
+            let line = client.read_line()?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                client.name = name.trim().to_string();
+            } else if line.trim() == "uciok" {
+                break;
+            }
+        }
+        client.wait_until_ready()?;
+        Ok(client)
+    }
+
+    /// Name given by the engine
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn set_limit(&mut self, limit: SearchLimit) {
+        self.limit = limit;
+    }
+
+    /// Sets the maximum time to wait for an answer of the engine. A search limited by time is
+    /// considered as failed after this time, in addition to the time of the search itself.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Sets an option of the engine, for instance `Hash` or `MultiPV`
+    pub fn set_option(&mut self, name: &str, value: &str) -> io::Result<()> {
+        self.send(&format!("setoption name {name} value {value}"))?;
+        self.wait_until_ready()
+    }
+
+    /// Tells the engine that the next searches belong to a new game
+    pub fn new_game(&mut self) -> io::Result<()> {
+        self.send("ucinewgame")?;
+        self.wait_until_ready()
+    }
+
+    /// Asks the engine for the best move of the side to move in the given position.
+    ///
+    /// When a search limited by time takes too long, the engine is told to stop and its move is
+    /// discarded, so that it isn't read as the answer to the next search. An engine which doesn't
+    /// stop either is killed, and all its next searches fail.
+    pub fn search(&mut self, game: ChessGame) -> io::Result<SearchResult> {
+        self.search_in_game(game, &[])
+    }
+
+    /// Asks the engine for the best move of the position reached by playing `moves` from `start`.
+    /// The moves are sent to the engine, so that it knows the positions which were repeated.
+    pub fn search_in_game(&mut self, start: ChessGame, moves: &[Move]) -> io::Result<SearchResult> {
+        if self.is_dead {
+            return Err(stopped_error());
+        }
+        let mut game = start;
+        for m in moves {
+            game.apply_move_unsafe(m);
+        }
+        let white_to_play = game.white_to_play();
+        self.send(&position_command(start, moves))?;
+        let (go, deadline) = match self.limit {
+            SearchLimit::Depth(depth) => (format!("go depth {depth}"), None),
+            SearchLimit::MoveTime(time) => (
+                format!("go movetime {}", time.as_millis()),
+                Some(Instant::now() + time + self.timeout),
+            ),
+        };
+        self.send(&go)?;
+
+        let mut result = SearchResult {
+            score: 0,
+            best_move: None,
+            pv: vec![],
+            nodes: 0,
+            stats: None,
+        };
+        loop {
+            let line = match deadline {
+                Some(deadline) => {
+                    self.read_line_within(deadline.saturating_duration_since(Instant::now()))
+                }
+                None => self.lines.recv().map_err(|_| stopped_error()),
+            };
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    if e.kind() == io::ErrorKind::TimedOut {
+                        self.stop_search();
+                    } else {
+                        self.is_dead = true;
+                    }
+                    return Err(e);
+                }
+            };
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("info") => parse_info(&line, white_to_play, &mut result),
+                Some("bestmove") => {
+                    // `0000` or `(none)` when there is no legal move
+                    result.best_move = tokens.next().and_then(|m| parse_uci_move(m, white_to_play));
+                    if result.pv.first() != result.best_move.as_ref() {
+                        result.pv = result.best_move.into_iter().collect();
+                    }
+                    return Ok(result);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Stops the current search and skips its move. The engine is killed if it doesn't answer.
+    fn stop_search(&mut self) {
+        let mut stopped = self.send("stop").is_ok();
+        while stopped {
+            match self.read_line() {
+                Ok(line) if line.starts_with("bestmove") => break,
+                Ok(_) => {}
+                Err(_) => stopped = false,
+            }
+        }
+        if !stopped {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+            self.is_dead = true;
+        }
+    }
+
+    fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{command}")?;
+        self.stdin.flush()
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        self.read_line_within(self.timeout)
+    }
+
+    fn read_line_within(&mut self, timeout: Duration) -> io::Result<String> {
+        self.lines.recv_timeout(timeout).map_err(|e| match e {
+            RecvTimeoutError::Timeout => {
+                io::Error::new(io::ErrorKind::TimedOut, "The engine is not answering")
+            }
+            RecvTimeoutError::Disconnected => stopped_error(),
+        })
+    }
+
+    fn wait_until_ready(&mut self) -> io::Result<()> {
+        self.send("isready")?;
+        while self.read_line()?.trim() != "readyok" {}
+        Ok(())
+    }
+}
+
+impl Engine for UciEngineClient {
+    /// Returns a result without any move if the engine fails to answer
+    fn find_best_move(&mut self, game: ChessGame) -> SearchResult {
+        self.find_best_move_in_game(game, &[])
+    }
+
+    /// Returns a result without any move if the engine fails to answer
+    fn find_best_move_in_game(&mut self, start: ChessGame, moves: &[Move]) -> SearchResult {
+        self.search_in_game(start, moves)
+            .unwrap_or_else(|_| SearchResult {
+                score: 0,
+                best_move: None,
+                pv: vec![],
+                nodes: 0,
+                stats: None,
+            })
+    }
+}
+
+impl Drop for UciEngineClient {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        // Give the engine some time to stop by itself
+        for _ in 0..50 {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse_info, parse_uci_move, position_command, SearchLimit, UciEngineClient};
+    use engine::alpha_beta::{mate_moves, MATE_SCORE};
+    use engine::engine::{Engine, SearchResult};
+    use model::chess_type::Type::Knight;
+    use model::game::ChessGame;
+    use model::moves::Move;
+    use std::time::Duration;
+
+    const SCRIPT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/scripted_engine.sh");
+
+    fn scripted_engine(best_move: &str, score: &str) -> UciEngineClient {
+        UciEngineClient::spawn("sh", &[SCRIPT, best_move, score]).unwrap()
+    }
+
+    #[test]
+    fn test_parse_uci_move() {
+        assert_eq!(
+            Some(Move::from_str("e2", "e4", true)),
+            parse_uci_move("e2e4", true)
+        );
+        assert_eq!(
+            Some(Move::from_str("a7", "a8", true)),
+            parse_uci_move("a7a8q", true)
+        );
+        assert_eq!(
+            Some(Move {
+                underpromotion: Some(Knight),
+                ..Move::from_str("a2", "a1", false)
+            }),
+            parse_uci_move("a2a1n", false)
+        );
+        assert_eq!(None, parse_uci_move("a7a8k", true));
+        assert_eq!(None, parse_uci_move("0000", true));
+        assert_eq!(None, parse_uci_move("(none)", true));
+        assert_eq!(None, parse_uci_move("e2e9", true));
+    }
+
+    #[test]
+    fn test_parse_info() {
+        let mut result = SearchResult {
+            score: 0,
+            best_move: None,
+            pv: vec![],
+            nodes: 0,
+            stats: None,
+        };
+        parse_info(
+            "info depth 5 seldepth 7 multipv 1 score cp -35 nodes 1234 nps 1000 pv e7e5 g1f3",
+            false,
+            &mut result,
+        );
+        // The score of black is converted to white's point of view
+        assert_eq!(35, result.score);
+        assert_eq!(1234, result.nodes);
+        assert_eq!(
+            vec![
+                Move::from_str("e7", "e5", false),
+                Move::from_str("g1", "f3", true)
+            ],
+            result.pv
+        );
+
+        // The other lines of a MultiPV analysis are ignored
+        parse_info("info multipv 2 score cp 100 pv d7d5", false, &mut result);
+        assert_eq!(35, result.score);
+
+        // The mates are scored as by the engine of this project, in plies
+        parse_info("info score mate 3 pv d1h5", true, &mut result);
+        assert_eq!(MATE_SCORE - 7, result.score);
+        assert_eq!(Some(3), mate_moves(result.score));
+        parse_info("info score mate -2", true, &mut result);
+        assert_eq!(-MATE_SCORE + 6, result.score);
+        assert_eq!(Some(2), mate_moves(result.score));
+    }
+
+    #[test]
+    fn test_position_command() {
+        let mut game = ChessGame::standard_game();
+        assert_eq!("position startpos", position_command(game, &[]));
+        let moves = [
+            Move::from_str("e2", "e4", true),
+            Move::from_str("e7", "e5", false),
+        ];
+        assert_eq!(
+            "position startpos moves e2e4 e7e5",
+            position_command(game, &moves)
+        );
+
+        // The other positions are sent in FEN, and the promotions get their piece
+        game = ChessGame::from_fen("k7/6P1/8/8/8/8/1p6/7K w - - 0 1");
+        let moves = [
+            Move::from_str("g7", "g8", true),
+            Move {
+                underpromotion: Some(Knight),
+                ..Move::from_str("b2", "b1", false)
+            },
+        ];
+        assert_eq!(
+            "position fen k7/6P1/8/8/8/8/1p6/7K w - - 0 1 moves g7g8q b2b1n",
+            position_command(game, &moves)
+        );
+    }
+
+    #[test]
+    fn test_scripted_engine() {
+        let mut client = scripted_engine("e2e4", "cp 35");
+        assert_eq!("Scripted engine", client.name());
+        client.set_limit(SearchLimit::Depth(2));
+        client.set_option("Hash", "32").unwrap();
+        client.new_game().unwrap();
+
+        let result = client.find_best_move(ChessGame::standard_game());
+        assert_eq!(Some(Move::from_str("e2", "e4", true)), result.best_move);
+        assert_eq!(35, result.score);
+        assert_eq!(20, result.nodes);
+        assert_eq!(2, result.pv.len());
+
+        // The score is given from the point of view of the side to move
+        let mut client = scripted_engine("e7e5", "mate 1");
+        let mut game = ChessGame::standard_game();
+        game.set_white_to_play(false);
+        let result = client.find_best_move(game);
+        assert_eq!(Some(Move::from_str("e7", "e5", false)), result.best_move);
+        assert_eq!(-MATE_SCORE + 3, result.score);
+    }
+
+    #[test]
+    fn test_engine_failures() {
+        // Not an engine at all
+        assert!(UciEngineClient::spawn("sh", &["-c", "exit 0"]).is_err());
+        assert!(UciEngineClient::spawn("this-engine-does-not-exist", &[]).is_err());
+
+        // An engine which never answers to `go`
+        let mut client = UciEngineClient::spawn(
+            "sh",
+            &["-c", "while read -r l; do case $l in uci) echo uciok;; isready) echo readyok;; esac; done"],
+        )
+        .unwrap();
+        client.set_timeout(Duration::from_millis(200));
+        client.set_limit(SearchLimit::MoveTime(Duration::from_millis(100)));
+        let result = client.find_best_move(ChessGame::standard_game());
+        assert!(result.best_move.is_none());
+        // It doesn't stop either, so it is killed
+        assert!(client.search(ChessGame::standard_game()).is_err());
+    }
+
+    #[test]
+    fn test_slow_engine() {
+        // An engine which plays a2a3 after one second in the odd searches, and e7e5 at once in the
+        // even ones
+        let mut client = UciEngineClient::spawn(
+            "sh",
+            &["-c", "n=0; while read -r l; do case $l in uci) echo uciok;; isready) echo readyok;; go*) n=$((n+1)); if [ $((n % 2)) = 1 ]; then sleep 1; echo bestmove a2a3; else echo bestmove e7e5; fi;; esac; done"],
+        )
+        .unwrap();
+        client.set_timeout(Duration::from_millis(700));
+        client.set_limit(SearchLimit::MoveTime(Duration::from_millis(100)));
+        let mut game = ChessGame::standard_game();
+        assert!(client.find_best_move(game).best_move.is_none());
+
+        // The late move of the first search isn't taken as the answer to the next one
+        game.set_white_to_play(false);
+        assert_eq!(
+            Some(Move::from_str("e7", "e5", false)),
+            client.find_best_move(game).best_move
+        );
+
+        // Searches limited by depth are never timed out
+        client.set_timeout(Duration::from_millis(10));
+        client.set_limit(SearchLimit::Depth(20));
+        assert_eq!(
+            Some(Move::from_str("a2", "a3", false)),
+            client.find_best_move(game).best_move
+        );
+    }
+}
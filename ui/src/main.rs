@@ -1,16 +1,23 @@
 use crate::view::fltk_view::GTKView;
+use cli_options::{create_engine, parse_options};
+use engine::engine::Engine;
 
 mod view;
 
-fn play() {
+fn play(solver: Box<dyn Engine>) {
     // Hierarchy problem:
     // At the moment, the view instantiate the view model which itself instantiate the game.
     // Everything is reversed.
-    let mut my_view = GTKView::new();
+    let mut my_view = GTKView::new(solver);
     my_view.run_app();
 }
 
 fn main() {
-    play();
+    // The opponent of the player is the engine of this project, or the external UCI engine given
+    // with `--uci <command>`, both searching at depth 7 by default
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut options = parse_options(&args);
+    options.entry("depth".to_string()).or_insert("7".to_string());
+    play(create_engine(&options));
 }
 
@@ -1,7 +1,6 @@
-use engine::engine::Engine;
-use engine::iterative_deepening::IterativeDeepeningEngine;
-use model::chess_type::Type;
-use model::game::ChessGame;
+use engine::engine::{Engine, SearchResult};
+use model::chess_type::{Type, PAWN_SCORE};
+use model::game_tree::{GameTree, ROOT};
 use model::moves::Move;
 use model::moves_container::SimpleMovesContainer;
 use model::utils::pos_to_index;
@@ -14,6 +13,9 @@ pub enum Msg {
     KeyPressed(char),
 }
 
+/// Number of candidate moves displayed when analysing the position
+const ANALYSIS_LINES: usize = 3;
+
 pub enum SquareType {
     Attacked,
     Idle,
@@ -21,27 +23,32 @@ pub enum SquareType {
 }
 
 pub struct ChessViewModel {
-    game: ChessGame,
+    /// Moves played on the board, with the variations explored by taking moves back
+    tree: GameTree,
     solver: Box<dyn Engine>,
     selected_pos: Option<i8>,
     attacked_positions: Vec<i8>,
     engine_move: Option<(i8, i8)>,
+    /// Best moves of the side to move found by the last analysis of the position
+    candidate_moves: Vec<SearchResult>,
 }
 
 impl ChessViewModel {
-    pub fn new() -> Self {
+    /// Creates a new game against the given engine
+    pub fn new(solver: Box<dyn Engine>) -> Self {
         Self {
-            game: ChessGame::standard_game(),
-            solver: Box::new(IterativeDeepeningEngine::new(7, 0)),
+            tree: GameTree::default(),
+            solver,
             selected_pos: None,
             attacked_positions: vec![],
             engine_move: None,
+            candidate_moves: vec![],
         }
     }
 
     pub fn get_image_name_at(&self, i: i8, j: i8) -> Option<String> {
-        if let Some(t) = self.game.type_at_xy(i, j) {
-            if self.game.is_white_at_xy(i, j) {
+        if let Some(t) = self.tree.game().type_at_xy(i, j) {
+            if self.tree.game().is_white_at_xy(i, j) {
                 match t {
                     Type::Pawn => Some("pawn_white.svg".to_string()),
                     Type::Bishop => Some("bishop_white.svg".to_string()),
@@ -67,8 +74,8 @@ impl ChessViewModel {
 
     #[allow(dead_code)]
     pub fn get_char_at(&self, i: i8, j: i8) -> String {
-        if let Some(t) = self.game.type_at_xy(i, j) {
-            if self.game.is_white_at_xy(i, j) {
+        if let Some(t) = self.tree.game().type_at_xy(i, j) {
+            if self.tree.game().is_white_at_xy(i, j) {
                 match t {
                     Type::Pawn => "♙".to_string(),
                     Type::Bishop => "♗".to_string(),
@@ -120,23 +127,102 @@ impl ChessViewModel {
     }
 
     pub fn play_with_engine(&mut self) -> bool {
-        // Make the engine play
-        let search_result = self.solver.find_best_move(self.game, false);
+        // Make the engine play, with the moves of the game which lead to the position
+        let start = *self.tree.node(ROOT).game();
+        let moves: Vec<Move> = self
+            .tree
+            .path(self.tree.current())
+            .into_iter()
+            .filter_map(|id| self.tree.node(id).played())
+            .collect();
+        let search_result = self.solver.find_best_move_in_game(start, &moves);
         if let Some(best_move) = search_result.best_move {
             // Save the move
             self.engine_move = Some((best_move.from, best_move.to));
             // Apply the move
-            self.game
-                .apply_move_safe(Move::new(best_move.from, best_move.to, false))
+            self.tree.play(&best_move).is_some()
         } else {
             false
         }
     }
 
+    /// Runs a MultiPV analysis of the current position, for the side to move.
+    pub fn analyse_position(&mut self) {
+        self.candidate_moves = self
+            .solver
+            .find_best_moves(*self.tree.game(), ANALYSIS_LINES);
+    }
+
+    /// Returns the candidate moves of the last analysis with their evaluation (in pawns) and the
+    /// line expected by the engine, in standard algebraic notation, one per line.
+    pub fn get_analysis_text(&self) -> String {
+        self.candidate_moves
+            .iter()
+            .enumerate()
+            .filter_map(|(i, result)| {
+                let best_move = self.tree.game().to_display_san(&result.best_move?);
+                // Each move of the line is written in the position where it is played
+                let mut game = *self.tree.game();
+                let line: Vec<String> = result
+                    .pv
+                    .iter()
+                    .map(|m| {
+                        let san = game.to_display_san(m);
+                        game.apply_move_unsafe(m);
+                        san
+                    })
+                    .collect();
+                Some(format!(
+                    "{}. {best_move} ({:+.2})\n    {}",
+                    i + 1,
+                    result.score as f64 / PAWN_SCORE as f64,
+                    line.join(" ")
+                ))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The player has the first move of the game, and the engine answers
+    fn player_is_white(&self) -> bool {
+        self.tree.node(ROOT).game().white_to_play()
+    }
+
+    /// Moves to another position of the game, with one of the navigation methods of the tree
+    fn navigate(&mut self, go: impl FnOnce(&mut GameTree) -> bool) {
+        if go(&mut self.tree) {
+            self.selected_pos = None;
+            self.attacked_positions.clear();
+            self.engine_move = None;
+            self.candidate_moves.clear();
+        }
+    }
+
+    /// Takes back the last move of the player with the answer of the engine. The moves which
+    /// end their line are removed from the game, while the ones followed by other moves (after
+    /// going back in the game) are kept.
+    fn take_back(&mut self) {
+        // When the player is to move, the answer of the engine is taken back first
+        let plies = if self.tree.white_to_play() == self.player_is_white() {
+            2
+        } else {
+            1
+        };
+        for _ in 0..plies {
+            let current = self.tree.current();
+            if self.tree.node(current).children().is_empty() {
+                self.navigate(|tree| tree.delete_variation(current));
+            } else {
+                self.navigate(GameTree::back);
+            }
+        }
+    }
+
     fn compute_attacked_positions(&mut self) {
         if let Some(pos) = self.selected_pos {
             let mut container = SimpleMovesContainer::new();
-            self.game.update_move_container(&mut container, true);
+            let white = self.tree.white_to_play();
+            self.tree.game().update_move_container(&mut container, white);
             self.attacked_positions = container
                 .moves
                 .iter()
@@ -149,19 +235,24 @@ impl ChessViewModel {
     pub fn message_received(&mut self, msg: &Msg) -> bool {
         match msg {
             Msg::RestartGame => {
-                self.game = ChessGame::standard_game();
+                self.tree = GameTree::default();
+                self.candidate_moves.clear();
                 true
             }
 
             Msg::SquareTapped(pos) => {
                 if let Some(previous_pos) = self.selected_pos {
                     self.engine_move = None;
-                    if self
-                        .game
-                        .apply_move_safe(Move::new(previous_pos, *pos, true))
+                    let white = self.tree.white_to_play();
+                    if white == self.player_is_white()
+                        && self
+                            .tree
+                            .play(&Move::new(previous_pos, *pos, white))
+                            .is_some()
                     {
                         self.selected_pos = None;
                         self.attacked_positions = vec![];
+                        self.candidate_moves.clear();
                         self.play_with_engine();
                     } else {
                         self.selected_pos = Some(*pos);
@@ -178,7 +269,12 @@ impl ChessViewModel {
             Msg::KeyPressed(key) => {
                 println!("Key tapped: {key:?}");
                 match key {
-                    'p' => self.game.print_game_integers(),
+                    'p' => self.tree.game().print_game_integers(),
+                    'a' => self.analyse_position(),
+                    // Navigation in the moves played
+                    'b' => self.navigate(GameTree::back),
+                    'f' => self.navigate(GameTree::forward),
+                    't' => self.take_back(),
                     _ => {}
                 }
                 true
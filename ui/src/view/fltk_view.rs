@@ -1,5 +1,7 @@
+use engine::engine::Engine;
 use fltk::app::{event_text, App, Sender};
-use fltk::enums::{Color, Event};
+use fltk::enums::{Align, Color, Event, Font};
+use fltk::frame::Frame;
 use fltk::image::SvgImage;
 use fltk::{button::Button, prelude::*};
 use fltk::{window::Window, *};
@@ -11,9 +13,9 @@ pub struct GTKView {
 }
 
 impl GTKView {
-    pub fn new() -> Self {
+    pub fn new(solver: Box<dyn Engine>) -> Self {
         Self {
-            chess_view: ChessViewModel::new(),
+            chess_view: ChessViewModel::new(solver),
         }
     }
 
@@ -46,17 +48,27 @@ impl GTKView {
         button.set_frame(enums::FrameType::FlatBox);
     }
 
+    fn draw_analysis(&self, frame: &mut Frame) {
+        let text = self.chess_view.get_analysis_text();
+        if text.is_empty() {
+            frame.set_label("Press 'a' to analyse the position");
+        } else {
+            frame.set_label(&text);
+        }
+    }
+
     /// Creates the FLTK window
-    fn draw_window(&self, s: &Sender<Msg>) -> (Window, Vec<Vec<Button>>) {
+    fn draw_window(&self, s: &Sender<Msg>) -> (Window, Vec<Vec<Button>>, Frame) {
         const BUTTON_WIDTH: i32 = 50;
         const TOP_MARGIN: i32 = 10;
         const SIDE_MARGIN: i32 = 30;
         const TEXT_SIZE: i32 = 0;
+        const ANALYSIS_WIDTH: i32 = 260;
 
         // Create the window for the application
         let mut app_window = Window::default()
             .with_size(
-                8 * BUTTON_WIDTH + 2 * SIDE_MARGIN + TEXT_SIZE,
+                8 * BUTTON_WIDTH + 2 * SIDE_MARGIN + TEXT_SIZE + ANALYSIS_WIDTH,
                 8 * BUTTON_WIDTH + 2 * TOP_MARGIN,
             )
             .with_label("Chess Engine by Arthur Bricq");
@@ -97,15 +109,24 @@ impl GTKView {
             buttons.push(row);
         }
 
+        // Candidate moves of the engine, displayed at the right of the board
+        let mut analysis = Frame::default()
+            .with_pos(8 * BUTTON_WIDTH + 2 * SIDE_MARGIN + TEXT_SIZE, TOP_MARGIN)
+            .with_size(ANALYSIS_WIDTH - SIDE_MARGIN, 8 * BUTTON_WIDTH);
+        analysis.set_align(Align::Left | Align::Top | Align::Inside | Align::Wrap);
+        analysis.set_label_font(Font::Courier);
+        analysis.set_label_size(12);
+        self.draw_analysis(&mut analysis);
+
         app_window.end();
         app_window.show();
-        (app_window, buttons)
+        (app_window, buttons, analysis)
     }
 
     pub fn run_app(&mut self) {
         let app = App::default();
         let (s, r) = fltk::app::channel();
-        let (mut win, mut buttons) = self.draw_window(&s);
+        let (mut win, mut buttons, mut analysis) = self.draw_window(&s);
 
         // Handle when pressing some keys
         win.handle(move |_, event| -> bool {
@@ -131,6 +152,7 @@ impl GTKView {
                         self.draw_button_at(i as i8, 7 - j as i8, &mut buttons[i][j]);
                     }
                 }
+                self.draw_analysis(&mut analysis);
             }
         }
 